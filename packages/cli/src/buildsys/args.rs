@@ -1,7 +1,13 @@
 // SPDX-License-Identifier: MIT
 // Copyright (c) 2026 Megaton contributors
 
+use std::path::PathBuf;
+
 use cu::pre::*;
+
+use crate::buildsys::{MessageFormat, SizeFormat};
+use crate::config::PackageLayout;
+
 #[derive(Debug, clap::Parser)]
 pub struct BuildArgs {
     /// Select profile to build
@@ -15,6 +21,16 @@ pub struct BuildArgs {
     #[clap(short = 'g', long)]
     pub configure: bool,
 
+    /// Format of the build output. `json` prints one JSON message per line
+    /// to stdout and disables the logs
+    #[clap(long, default_value = "human")]
+    pub message_format: MessageFormat,
+
+    /// Record how long each phase and each compile job takes, and write
+    /// a report to `timings.html` and `timings.json` in the profile's target directory
+    #[clap(long)]
+    pub timings: bool,
+
     /// Specify the location of the Metagon.toml config file
     ///
     /// By default, the first Megaton.toml found in the current paths and its ancestors
@@ -22,3 +38,99 @@ pub struct BuildArgs {
     #[clap(short = 'c', long)]
     pub config: Option<String>,
 }
+
+#[derive(Debug, clap::Parser)]
+pub struct CheckArgs {
+    /// Select profile of the check config and the ELF to check
+    ///
+    /// See https://megaton-new.pistonite.dev/tutorial/profiles
+    #[clap(short, long, default_value = "none")]
+    pub profile: String,
+
+    /// Path to the ELF to check, instead of the one in the target directory
+    #[clap(long)]
+    pub elf: Option<PathBuf>,
+
+    /// Specify the location of the Megaton.toml config file
+    #[clap(short = 'c', long)]
+    pub config: Option<String>,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct SizeArgs {
+    /// Select profile of the build to analyze
+    ///
+    /// See https://megaton-new.pistonite.dev/tutorial/profiles
+    #[clap(short, long, default_value = "none")]
+    pub profile: String,
+
+    /// Number of symbols, object files and crates to show
+    #[clap(short = 'n', long, default_value_t = 20)]
+    pub top: usize,
+
+    /// Compare with the report saved by the last `megaton size` of the profile
+    #[clap(short, long)]
+    pub diff: bool,
+
+    /// Compare with a report saved somewhere else, instead of the last one
+    #[clap(long)]
+    pub baseline: Option<PathBuf>,
+
+    /// Output format. `json` includes all symbols
+    #[clap(short, long, default_value = "text")]
+    pub format: SizeFormat,
+
+    /// Specify the location of the Megaton.toml config file
+    #[clap(short = 'c', long)]
+    pub config: Option<String>,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct CleanArgs {
+    /// Only clean artifacts of this profile
+    #[clap(short, long)]
+    pub profile: Option<String>,
+
+    /// Only remove the unpacked megaton library
+    #[clap(long)]
+    pub lib: bool,
+
+    /// Only remove compiled objects and the compile/link caches
+    #[clap(long)]
+    pub objects: bool,
+
+    /// Run `cargo clean` for the Rust crate
+    #[clap(long)]
+    pub rust: bool,
+
+    /// Specify the location of the Megaton.toml config file
+    #[clap(short = 'c', long)]
+    pub config: Option<String>,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct PackageArgs {
+    /// Select profile to build and package
+    ///
+    /// See https://megaton-new.pistonite.dev/tutorial/profiles
+    #[clap(short, long, default_value = "none")]
+    pub profile: String,
+
+    /// Layouts to output, overrides `package.layouts` in the config
+    #[clap(short, long = "layout", value_delimiter = ',')]
+    pub layouts: Vec<PackageLayout>,
+
+    /// Also create a `.zip` archive for each layout
+    #[clap(short, long)]
+    pub zip: bool,
+
+    /// Directory to output the packages to
+    ///
+    /// Defaults to `package` in the module's target directory
+    #[clap(short, long)]
+    pub out: Option<PathBuf>,
+
+    /// Specify the location of the Megaton.toml config file
+    #[clap(short = 'c', long)]
+    pub config: Option<String>,
+}
@@ -2,35 +2,156 @@
 // Copyright (c) 2026 Megaton contributors
 
 use std::{
-    collections::HashSet,
+    collections::{BTreeSet, HashSet},
     path::{Path, PathBuf},
 };
 
 use cu::pre::*;
+use object::{Object as _, ObjectSymbol as _};
 use regex::Regex;
+use sha2::{Digest, Sha256};
 
-use crate::env;
+use crate::buildsys::disasm;
+use crate::buildsys::layout;
+use crate::buildsys::link;
+use crate::buildsys::link_map::LinkMap;
+use crate::buildsys::message::{self, Message};
+use crate::buildsys::missing;
+use crate::buildsys::size;
+use crate::buildsys::stack::{self, StackReport};
+use crate::buildsys::timings::{self, TimingKind};
+use crate::buildsys::{self, CheckArgs};
+use crate::config::{self, Check, Npdm};
 
 type Symbols = HashSet<String>;
 
-pub async fn check_all(
+/// Run the `check` command on an existing ELF, without building
+pub async fn check_elf(args: CheckArgs) -> cu::Result<()> {
+    let (root_path, manifest_path) = config::get_root_and_manifest(args.config.as_deref())?;
+    let config = config::load(&manifest_path)?;
+    let profile = config.profile.resolve(&args.profile)?;
+    let Some(check_config) = config.check else {
+        cu::error!("check is not configured");
+        cu::hint!("- please add a [check] section to the config");
+        cu::bail!("nothing to check");
+    };
+    let check_config = check_config.get_profile(profile);
+    let npdm_config = config.npdm.get_profile(profile);
+    let elf_path = match args.elf {
+        Some(elf) => elf.normalize_exists()?,
+        None => {
+            let elf = buildsys::elf_path(&root_path, &config.module, profile);
+            if !elf.exists() {
+                cu::bail!(
+                    "'{}' does not exist; please build the module first",
+                    elf.display()
+                );
+            }
+            elf
+        }
+    };
+    // the link command is cached next to the ELF when it's built by megaton
+    let link_cmd_path = elf_path.with_file_name("linkcmd.cache");
+    let link_inputs = if link_cmd_path.exists() {
+        link::load_link_inputs(&link_cmd_path)?
+    } else {
+        cu::debug!(
+            "Check: link command not found, cannot find where missing symbols are referenced"
+        );
+        vec![]
+    };
+    cu::info!("checking {}", elf_path.try_to_rel().display());
+    check_module(
+        &root_path,
+        &check_config,
+        &npdm_config,
+        &elf_path,
+        &link_inputs,
+    )
+    .context("Check failed")
+}
+
+/// Check the ELF with the resolved check config
+///
+/// Symbol files are relative to the root of the project. The link inputs are
+/// the object files and static libraries linked into the ELF. The NPDM config
+/// is for the default stack budget
+pub fn check_module(
+    root: &Path,
+    check_config: &Check,
+    npdm_config: &Npdm,
     elf: &Path,
+    link_inputs: &[PathBuf],
+) -> cu::Result<()> {
+    let mut symbol_files = vec![];
+    for symbol_file in &check_config.symbols {
+        symbol_files.push(root.join(symbol_file).normalize_exists()?);
+    }
+    size::check_size_budget(elf, &check_config.max_size)?;
+    check_all(
+        root,
+        elf,
+        link_inputs,
+        &check_config.ignore,
+        &check_config.disallowed_instructions,
+        &symbol_files,
+        check_config.max_stack(npdm_config),
+    )
+}
+
+pub fn check_all(
+    root: &Path,
+    elf: &Path,
+    link_inputs: &[PathBuf],
     ignored_symbols: &[String],
     disallowed_instructions: &[String],
     symbol_files: &[PathBuf],
+    max_stack: u64,
 ) -> cu::Result<()> {
     let expected_symbols = load_known_symbols(symbol_files)?;
-
-    let (missing_symbols, disallowed_instructions) = cu::co::try_join!(
-        check_symbols(elf, expected_symbols, ignored_symbols),
-        check_instructions(elf, disallowed_instructions)
+    let data = cu::fs::read(elf)?;
+    let file = cu::check!(
+        object::File::parse(data.as_slice()),
+        "failed to parse ELF {}",
+        elf.display()
     )?;
 
+    let link_map = load_link_map(root, elf)?;
+
+    let missing_symbols = check_symbols(&file, expected_symbols, ignored_symbols);
+    let instructions = disasm::disassemble(&file);
+    let disallowed_instructions =
+        check_instructions(&file, &instructions, disallowed_instructions)?;
+    let layout_problems = {
+        let _timing = timings::span(TimingKind::Phase, "check-layout");
+        layout::check_layout(&data, &link_map)?
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+    };
+    let stack_report = analyze_stack(&file, &instructions, &link_map, root, link_inputs);
+    let stack_usage = (stack_report.known_functions > 0).then(|| stack_report.max_usage());
+    StackCache::save(elf, &data, stack_usage)?;
+    let stack_exceeded = stack_usage.is_some_and(|x| x > max_stack);
+    message::emit(Message::Check {
+        elf,
+        success: missing_symbols.is_empty()
+            && disallowed_instructions.is_empty()
+            && layout_problems.is_empty()
+            && !stack_exceeded,
+        missing_symbols: &missing_symbols,
+        disallowed_instructions: &disallowed_instructions,
+        layout_problems: &layout_problems,
+        stack_usage,
+        max_stack,
+    });
+
     if !missing_symbols.is_empty() {
+        let references = missing::find_references(link_inputs, root, &missing_symbols);
         cu::bail!(
-            "Missing symbols in {}:\n{:#?}",
+            "Missing symbols in {}:\n{}",
             elf.display(),
-            missing_symbols
+            missing::format_report(&references)
         );
     } else {
         cu::debug!("Check: no missing symbols")
@@ -44,11 +165,151 @@ pub async fn check_all(
     } else {
         cu::debug!("Check: no disallowed instructions")
     }
+    if !layout_problems.is_empty() {
+        cu::bail!(
+            "Found problems in the layout of {}:\n{:#?}",
+            elf.display(),
+            layout_problems
+        );
+    } else {
+        cu::debug!("Check: no layout problems")
+    }
+    check_stack(&stack_report, max_stack)?;
 
     cu::hint!("Check: looks good to me");
     Ok(())
 }
 
+/// Check only the size and stack budgets of the ELF with the resolved check config
+///
+/// Unlike the other checks, the budgets are checked on every build, even if the ELF
+/// is up to date, so a build never succeeds with a module over budget. The stack usage
+/// is cached for the ELF, so it is only analyzed again when the ELF changes
+pub fn check_budgets(
+    root: &Path,
+    check_config: &Check,
+    npdm_config: &Npdm,
+    elf: &Path,
+    link_inputs: &[PathBuf],
+) -> cu::Result<()> {
+    size::check_size_budget(elf, &check_config.max_size)?;
+    let max_stack = check_config.max_stack(npdm_config);
+    let data = cu::fs::read(elf)?;
+    // analyze again if over the budget, to report the deepest paths
+    if let Some(usage) = StackCache::load(elf, &data).filter(|x| *x <= max_stack) {
+        message::emit(Message::Check {
+            elf,
+            success: true,
+            missing_symbols: &[],
+            disallowed_instructions: &[],
+            layout_problems: &[],
+            stack_usage: Some(usage),
+            max_stack,
+        });
+        cu::info!("Check: stack usage {usage} of {max_stack} bytes");
+        return Ok(());
+    }
+    let file = cu::check!(
+        object::File::parse(data.as_slice()),
+        "failed to parse ELF {}",
+        elf.display()
+    )?;
+    let link_map = load_link_map(root, elf)?;
+    let instructions = disasm::disassemble(&file);
+    let stack_report = analyze_stack(&file, &instructions, &link_map, root, link_inputs);
+    let stack_usage = (stack_report.known_functions > 0).then(|| stack_report.max_usage());
+    StackCache::save(elf, &data, stack_usage)?;
+    message::emit(Message::Check {
+        elf,
+        success: stack_usage.is_none_or(|x| x <= max_stack),
+        missing_symbols: &[],
+        disallowed_instructions: &[],
+        layout_problems: &[],
+        stack_usage,
+        max_stack,
+    });
+    check_stack(&stack_report, max_stack)
+}
+
+/// Stack usage of an ELF, saved next to it
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct StackCache {
+    /// SHA-256 of the ELF the stack usage is for
+    elf_hash: String,
+    stack_usage: u64,
+}
+
+impl StackCache {
+    fn path(elf: &Path) -> PathBuf {
+        elf.with_extension("stack.cache")
+    }
+
+    fn hash(data: &[u8]) -> String {
+        Sha256::digest(data)
+            .iter()
+            .map(|x| format!("{x:02x}"))
+            .collect()
+    }
+
+    /// Load the cached stack usage, if it is for the ELF with the content
+    fn load(elf: &Path, data: &[u8]) -> Option<u64> {
+        let cache = json::read::<Self>(cu::fs::read(Self::path(elf)).ok()?.as_slice()).ok()?;
+        if cache.elf_hash != Self::hash(data) {
+            cu::debug!("Check: stack usage cache is for another ELF");
+            return None;
+        }
+        Some(cache.stack_usage)
+    }
+
+    /// Save the stack usage of the ELF, or remove the cache if the usage is unknown
+    fn save(elf: &Path, data: &[u8], stack_usage: Option<u64>) -> cu::Result<()> {
+        let path = Self::path(elf);
+        let Some(stack_usage) = stack_usage else {
+            if path.exists() {
+                cu::fs::remove(&path)?;
+            }
+            return Ok(());
+        };
+        let cache = Self {
+            elf_hash: Self::hash(data),
+            stack_usage,
+        };
+        json::write_pretty(std::fs::File::create(&path)?, &cache)
+    }
+}
+
+/// Load the link map next to the ELF, used to find the object files of
+/// layout problems and stack usage
+fn load_link_map(root: &Path, elf: &Path) -> cu::Result<LinkMap> {
+    let map_path = elf.with_extension("map");
+    if map_path.exists() {
+        let map = LinkMap::parse(&cu::fs::read_string(&map_path)?, root);
+        if map.sections.is_empty() {
+            cu::warn!(
+                "Check: no input sections found in the link map {}",
+                map_path.display()
+            );
+            cu::hint!(
+                "- problems will not have object files, and the stack usage of C/C++ functions will be unknown"
+            );
+        }
+        return Ok(map);
+    }
+    cu::debug!("Check: link map not found, problems will not have object files");
+    Ok(LinkMap::default())
+}
+
+fn analyze_stack(
+    file: &object::File,
+    instructions: &[disasm::DecodedInstruction],
+    link_map: &LinkMap,
+    root: &Path,
+    link_inputs: &[PathBuf],
+) -> StackReport {
+    let _timing = timings::span(TimingKind::Phase, "check-stack");
+    stack::analyze_stack(file, instructions, link_map, root, link_inputs)
+}
+
 /// Expects canonical paths
 fn load_known_symbols(symbol_files: &[PathBuf]) -> cu::Result<Symbols> {
     let mut symbols = HashSet::new();
@@ -58,50 +319,42 @@ fn load_known_symbols(symbol_files: &[PathBuf]) -> cu::Result<Symbols> {
             "failed to read symbol file {}",
             symbol_file.display()
         ))?;
-        let syms = parse_objdump_syms(content);
+        let syms = parse_objdump_syms(&content);
         symbols.extend(syms);
     }
 
     Ok(symbols)
 }
 
-async fn check_symbols(
-    elf: &Path,
+/// Get the undefined dynamic symbols that are not provided by the symbol files
+fn check_symbols(
+    file: &object::File,
     expected_symbols: Symbols,
     ignored_symbols: &[String],
-) -> cu::Result<Vec<String>> {
-    let (child, stdout_handle) = env::get()
-        .objdump()
-        .command()
-        .arg("-T")
-        .arg(elf)
-        .stdout(cu::pio::string())
-        .stdie_null()
-        .co_spawn()
-        .await?;
-
-    child.co_wait_nz().await?;
-    let stdout = stdout_handle.co_join().await??;
-    let mut symbols = parse_objdump_syms(stdout);
-
-    cu::trace!("objdump symbols: {:#?}", symbols);
+) -> Vec<String> {
+    let _timing = timings::span(TimingKind::Phase, "check-symbols");
+    let mut symbols = file
+        .dynamic_symbols()
+        .filter(|symbol| symbol.is_undefined())
+        .filter_map(|symbol| symbol.name().ok())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect::<BTreeSet<_>>();
+
+    cu::trace!("undefined dynamic symbols: {:#?}", symbols);
 
     for ignored_symbol in ignored_symbols {
         symbols.remove(ignored_symbol);
     }
 
-    let missing_symbols = symbols
+    symbols
         .into_iter()
-        .filter(|symbol| {
-            // dot is not a valid character in a C identifier, most likely a false positive (.data, .text)
-            !symbol.starts_with(".") && !expected_symbols.contains(symbol)
-        })
-        .collect::<Vec<_>>();
-
-    Ok(missing_symbols)
+        .filter(|symbol| !expected_symbols.contains(symbol))
+        .collect()
 }
 
-fn parse_objdump_syms(content: String) -> Symbols {
+/// Parse the names of the symbols in the output of `objdump -T`
+fn parse_objdump_syms(content: &str) -> Symbols {
     let mut lines = content.lines();
 
     for line in lines.by_ref() {
@@ -110,77 +363,130 @@ fn parse_objdump_syms(content: String) -> Symbols {
         }
     }
 
+    // <value> <flags> <section>\t<size> [version] <name>
     lines
         .filter_map(|line| {
-            if line.len() <= 25 {
-                None
-            } else {
-                line[25..]
-                    .split_once(' ')
-                    .map(|x| x.1)
-                    .map(|sym| sym.to_owned())
-            }
+            let (_, size_and_name) = line.split_once('\t')?;
+            let name = size_and_name.split_whitespace().skip(1).last()?;
+            Some(name.to_string())
         })
         .collect()
 }
 
-async fn check_instructions(
-    elf: &Path,
+/// Report the deepest paths, and fail if the stack usage exceeds the budget
+fn check_stack(report: &StackReport, max_stack: u64) -> cu::Result<()> {
+    if report.known_functions == 0 {
+        cu::warn!("Check: no stack usage information found, skipping stack check");
+        cu::hint!(
+            "- stack usage is only collected when the module is built with a [check] section"
+        );
+        return Ok(());
+    }
+    let usage = report.max_usage();
+    let mut paths = String::new();
+    for path in &report.deepest {
+        paths.push_str(&format!("{} bytes:\n", path.total));
+        for line in path.to_lines() {
+            paths.push_str(&line);
+            paths.push('\n');
+        }
+    }
+    if !report.recursive.is_empty() {
+        cu::warn!(
+            "Check: recursive functions are only counted once in the stack usage:\n{}",
+            report.recursive.join("\n")
+        );
+    }
+    if usage > max_stack {
+        cu::bail!(
+            "Stack usage {usage} exceeds the budget {max_stack} (check.max-stack). Deepest paths:\n{paths}"
+        );
+    }
+    cu::info!(
+        "Check: stack usage {usage} of {max_stack} bytes ({} functions without stack usage)",
+        report.unknown_functions
+    );
+    cu::debug!("Check: deepest paths:\n{paths}");
+    Ok(())
+}
+
+fn check_instructions(
+    file: &object::File,
+    instructions: &[disasm::DecodedInstruction],
     disallowed_instructions: &[String],
 ) -> cu::Result<Vec<String>> {
-    let (child, stdout_handle) = env::get()
-        .objdump()
-        .command()
-        .arg("-d")
-        .arg(elf)
-        .stdout(cu::pio::string())
-        .stdie_null()
-        .co_spawn()
-        .await?;
-
-    child.co_wait_nz().await?;
-    let stdout = stdout_handle.co_join().await??;
-    let instructions = parse_objdump_insts(stdout);
+    let _timing = timings::span(TimingKind::Phase, "check-instructions");
 
     let mut disallowed_regexes = vec![
-        Regex::new(r"^msr\s*spsel").unwrap(),
-        Regex::new(r"^msr\s*daifset").unwrap(),
-        Regex::new(r"^mrs\.*daif").unwrap(),
-        Regex::new(r"^mrs\.*tpidr_el1").unwrap(),
-        Regex::new(r"^msr\s*tpidr_el1").unwrap(),
-        Regex::new(r"^hlt").unwrap(),
+        Regex::new(r"^msr\s+spsel\b").unwrap(),
+        Regex::new(r"^msr\s+daifset\b").unwrap(),
+        Regex::new(r"^mrs\s+\w+,\s*daif$").unwrap(),
+        Regex::new(r"^mrs\s+\w+,\s*tpidr_el1$").unwrap(),
+        Regex::new(r"^msr\s+tpidr_el1\b").unwrap(),
+        Regex::new(r"^hlt\b").unwrap(),
     ];
-    disallowed_regexes.extend(disallowed_instructions.iter().filter_map(|inst| {
-        cu::check!(
+    for inst in disallowed_instructions {
+        let regex = cu::check!(
             Regex::new(inst),
-            "Failed to parse disallowed instruction {}.",
-            inst
-        )
-        .ok()
-    }));
+            "failed to parse disallowed instruction '{inst}'"
+        )?;
+        disallowed_regexes.push(regex);
+    }
 
-    let bad_instructions: Vec<String> = instructions
+    let symbols = disasm::SymbolTable::new(file);
+    let bad_instructions = instructions
         .iter()
-        .filter(|inst| disallowed_regexes.iter().any(|r| r.is_match(&inst.1)))
-        .map(|bad_inst| format!("{}: {}", bad_inst.0, bad_inst.1))
+        .filter_map(|inst| Some((inst.address, inst.to_asm(&symbols)?)))
+        .filter(|(_, asm)| disallowed_regexes.iter().any(|r| r.is_match(asm)))
+        .map(|(address, asm)| format!("{address:x}: {asm}"))
         .collect();
 
     Ok(bad_instructions)
 }
 
-fn parse_objdump_insts(content: String) -> Vec<(String, String)> {
-    // Example
-    // 0000000000000000 <__code_start__>:
-    //        0:	14000008 	b	20 <entrypoint>
-    //        4:	0001a6e0 	.word	0x0001a6e0
-    //        8:	d503201f 	nop
-    //          ^ tab       _^ tab
-    content
-        .lines()
-        .flat_map(|line| {
-            let (addr, bytes_and_asm) = line.split_once(":\t")?;
-            let (_bytes, inst) = bytes_and_asm.split_once(" \t")?;
-            Some((addr.to_string(), inst.to_string()))
-        })
-        .collect()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_symbol_file() {
+        let content = "
+sdk:     file format elf64-little
+
+DYNAMIC SYMBOL TABLE:
+0000000000000000      D  *UND*\t0000000000000000 malloc
+00000000009791e0 g    DO .data\t0000000000000040 _ZTVNSt3__18__c_nodeE
+0000000000183e5c g    DF .text\t00000000000000b0  Base        nnosSleepThread
+0000000000183f00 g    DF .a_very_long_section_name\t0000000000000010 _ZN2nn2sf6detail7ReleaseEv
+";
+        let symbols = parse_objdump_syms(content);
+        let mut symbols = symbols.into_iter().collect::<Vec<_>>();
+        symbols.sort();
+        assert_eq!(
+            symbols,
+            vec![
+                "_ZN2nn2sf6detail7ReleaseEv",
+                "_ZTVNSt3__18__c_nodeE",
+                "malloc",
+                "nnosSleepThread"
+            ]
+        );
+    }
+
+    #[test]
+    fn stack_cache() {
+        let dir = std::env::temp_dir().join(format!("megaton-stack-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let elf = dir.join("test.elf");
+        assert_eq!(StackCache::load(&elf, b"elf"), None);
+        StackCache::save(&elf, b"elf", Some(0x1000)).unwrap();
+        assert_eq!(StackCache::load(&elf, b"elf"), Some(0x1000));
+        // a different ELF does not use the cache
+        assert_eq!(StackCache::load(&elf, b"relinked"), None);
+        // unknown stack usage is not cached
+        StackCache::save(&elf, b"elf", None).unwrap();
+        assert_eq!(StackCache::load(&elf, b"elf"), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use std::path::{Path, PathBuf};
+
+use cu::pre::*;
+
+use crate::buildsys::CleanArgs;
+use crate::buildsys::rust::RustCtx;
+use crate::config::{self, CargoConfig};
+
+/// Remove build artifacts selected by the args
+pub async fn clean(args: CleanArgs) -> cu::Result<()> {
+    let (root_path, manifest_path) = config::get_root_and_manifest(args.config.as_deref())?;
+    let config = config::load(&manifest_path)?;
+    let target_path = {
+        let mut p = config.module.target_path(&root_path);
+        p.push("megaton");
+        p
+    };
+
+    // the profile directories to clean, only ones that exist in the target directory
+    let profile_paths = match &args.profile {
+        Some(profile) => {
+            let profile = config.profile.resolve(profile)?;
+            let profile_path = target_path.join(profile);
+            list_profile_dirs(&target_path)?
+                .into_iter()
+                .filter(|x| *x == profile_path)
+                .collect()
+        }
+        None => list_profile_dirs(&target_path)?,
+    };
+
+    let mut paths = vec![];
+    // without any selection, everything (in the profile) is removed
+    if !args.lib && !args.objects && !args.rust {
+        match &args.profile {
+            None => paths.push(target_path.clone()),
+            Some(_) => paths.extend(profile_paths.iter().cloned()),
+        }
+    }
+    if args.lib {
+        paths.push(target_path.join("lib"));
+    }
+    if args.objects {
+        for profile_path in profile_paths {
+            let target_mod = profile_path.join(&config.module.name);
+            paths.push(target_mod.join("o"));
+            paths.push(target_mod.join("compiledb.cache"));
+            paths.push(target_mod.join("linkcmd.cache"));
+        }
+    }
+
+    let mut freed = 0;
+    for path in paths {
+        freed += remove(&path)?;
+    }
+    if args.rust {
+        freed += clean_rust(&root_path, config.cargo).await?;
+    }
+
+    cu::info!("Clean: removed {}", cu::ByteFormat(freed));
+    Ok(())
+}
+
+/// List the profile directories in the megaton target directory.
+/// Symbolic links are not followed, since they are not created by the build
+fn list_profile_dirs(target_path: &Path) -> cu::Result<Vec<PathBuf>> {
+    if !target_path.exists() {
+        return Ok(vec![]);
+    }
+    let mut profile_paths = vec![];
+    for entry in cu::fs::read_dir(target_path)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() || entry.file_name() == "lib" {
+            continue;
+        }
+        profile_paths.push(entry.path());
+    }
+    Ok(profile_paths)
+}
+
+/// Remove a file or directory, returns the number of bytes freed
+fn remove(path: &Path) -> cu::Result<u64> {
+    if !path.exists() {
+        cu::debug!("Clean: not found {}", path.try_to_rel().display());
+        return Ok(0);
+    }
+    let size = disk_usage(path)?;
+    if path.is_dir() {
+        cu::fs::rec_remove(path)?;
+    } else {
+        cu::fs::remove(path)?;
+    }
+    cu::debug!(
+        "Clean: removed {} ({})",
+        path.try_to_rel().display(),
+        cu::ByteFormat(size)
+    );
+    Ok(size)
+}
+
+/// Total size of the files in the path, in bytes
+fn disk_usage(path: &Path) -> cu::Result<u64> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    if !path.is_dir() {
+        return Ok(path.metadata()?.len());
+    }
+    let mut size = 0;
+    for entry in cu::fs::walk(path)? {
+        size += entry?.metadata()?.len();
+    }
+    Ok(size)
+}
+
+/// Run `cargo clean`, returns the number of bytes freed
+async fn clean_rust(root: &Path, mut cargo: CargoConfig) -> cu::Result<u64> {
+    // the manifest is relative to the root, which may not be the current directory
+    cargo.manifest = Some(
+        root.join(
+            cargo
+                .manifest
+                .take()
+                .unwrap_or_else(CargoConfig::default_manifest_path),
+        ),
+    );
+    let Some(rust_ctx) = RustCtx::from_config(cargo) else {
+        cu::hint!("Rust is not enabled, skipping cargo clean");
+        return Ok(0);
+    };
+    let rust_ctx =
+        rust_ctx.context("Rust is enabled, but cargo context could not be initialized")?;
+    let target_dir = rust_ctx.target_dir().await?;
+    let before = disk_usage(&target_dir)?;
+    rust_ctx.clean().await?;
+    let after = disk_usage(&target_dir)?;
+    cu::debug!("Clean: cleaned {}", target_dir.try_to_rel().display());
+    Ok(before.saturating_sub(after))
+}
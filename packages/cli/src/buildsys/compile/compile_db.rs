@@ -2,13 +2,17 @@
 // Copyright (c) 2026 Megaton contributors
 
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     path::{Path, PathBuf},
     sync::Arc,
+    time::SystemTime,
 };
 
 use cu::pre::*;
+use sha2::{Digest, Sha256};
 
+use crate::buildsys::message::{self, Message};
+use crate::buildsys::timings::{self, TimingKind};
 use crate::env;
 
 type Records = BTreeMap<usize, CompileRecord>;
@@ -28,6 +32,30 @@ pub struct CompileRecord {
     pub args: Vec<String>,
     pub o_path: PathBuf, // -o argument already in args
     pub d_path: PathBuf,
+    /// Content hashes of the source and its dependencies when it was compiled
+    #[serde(default)]
+    pub hashes: BTreeMap<PathBuf, String>,
+}
+
+/// Content hashes of files, computed at most once per build since
+/// headers are usually included by many sources
+#[derive(Default)]
+pub struct FileHashes {
+    hashes: HashMap<PathBuf, Option<String>>,
+}
+
+impl FileHashes {
+    /// Get the SHA-256 of the content of the file, `None` if it cannot be read
+    pub fn get(&mut self, path: &Path) -> Option<&str> {
+        self.hashes
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                let content = std::fs::read(path).ok()?;
+                let hash = Sha256::digest(content);
+                Some(hash.iter().map(|x| format!("{x:02x}")).collect())
+            })
+            .as_deref()
+    }
 }
 
 impl CompileDB {
@@ -55,11 +83,11 @@ impl CompileDB {
     /// Generate a new compiledb with empty record table
     pub fn new() -> Self {
         let env = env::get();
-        // FIXME: dedupe fields
+        // the assembler is the C compiler
         Self {
             records: Records::default(),
             cc_version: env.cc_version().to_string(),
-            cxx_version: env.cc_version().to_string(),
+            cxx_version: env.cxx_version().to_string(),
             asm_version: env.cc_version().to_string(),
         }
     }
@@ -68,7 +96,7 @@ impl CompileDB {
     pub fn is_version_correct(&self) -> bool {
         let env = env::get();
         env.cc_version() == self.cc_version
-            && env.cc_version() == self.cxx_version
+            && env.cxx_version() == self.cxx_version
             && env.cc_version() == self.asm_version
     }
 
@@ -85,34 +113,126 @@ impl CompileDB {
     pub fn update(&mut self, path_hash: usize, record: CompileRecord) {
         self.records.insert(path_hash, record);
     }
+
+    pub fn remove(&mut self, path_hash: usize) {
+        self.records.remove(&path_hash);
+    }
 }
 
 impl CompileRecord {
     pub async fn compile(&self, parent_progress: Arc<cu::ProgressBar>) -> cu::Result<()> {
-        let start_time = cu::fs::Time::now();
+        let _timing = timings::span(
+            TimingKind::Compile,
+            self.source_path.try_to_rel().display().to_string(),
+        );
 
         let progress = parent_progress
             .child(format!("{}", self.source_path.try_to_rel().display()))
             .spawn();
 
-        self.compiler
-            .command()
-            .stdout(cu::lv::T)
-            .stderr(cu::lv::E)
-            .stdin_null()
-            .args(&self.args)
-            .co_wait_nz()
-            .await?;
+        if message::is_json() {
+            self.compile_json().await?;
+        } else {
+            self.compiler
+                .command()
+                .stdout(cu::lv::T)
+                .stderr(cu::lv::E)
+                .stdin_null()
+                .args(&self.args)
+                .co_wait_nz()
+                .await?;
+        }
 
         progress.done();
         cu::debug!("Compile: compiled object {}", self.o_path.display());
 
-        cu::fs::set_mtime(&self.source_path, start_time)?;
-        cu::fs::set_mtime(&self.o_path, start_time)?;
-        if self.d_path.exists() {
-            cu::fs::set_mtime(&self.d_path, start_time)?;
+        Ok(())
+    }
+
+    /// Add the content hashes of the dependencies in the depfile after compiling.
+    ///
+    /// The hashes of the source and the dependencies known from the previous compile
+    /// are computed when configuring, before compiling (`started`). A dependency modified
+    /// after that is recorded with an empty hash, since the compiler may have seen either
+    /// content, so the source is compiled again in the next build
+    pub fn hash_dependencies(
+        &mut self,
+        hashes: &mut FileHashes,
+        started: SystemTime,
+    ) -> cu::Result<()> {
+        if !self.d_path.exists() {
+            return Ok(());
+        }
+        let d_file_contents = cu::fs::read_string(&self.d_path)?;
+        let depfile = match depfile::parse(&d_file_contents) {
+            Ok(depfile) => depfile,
+            Err(pos) => {
+                cu::bail!(
+                    "Error when parsing depfile {} at position {pos}",
+                    self.d_path.display()
+                );
+            }
+        };
+        for dep in depfile.recurse_deps(self.o_path.as_utf8()?) {
+            let dep = PathBuf::from(dep);
+            if dep == self.source_path || self.hashes.contains_key(&dep) {
+                continue;
+            }
+            let modified = std::fs::metadata(&dep).and_then(|x| x.modified());
+            if modified.is_ok_and(|x| x >= started) {
+                cu::debug!(
+                    "Compile: {} was modified while compiling {}",
+                    dep.display(),
+                    self.source_path.display()
+                );
+                self.hashes.insert(dep, String::new());
+                continue;
+            }
+            let hash = hashes
+                .get(&dep)
+                .context(format!("failed to read dependency {}", dep.display()))?;
+            self.hashes.insert(dep, hash.to_string());
         }
+        Ok(())
+    }
 
+    /// Compile with `-fdiagnostics-format=json` and emit the diagnostics as messages
+    ///
+    /// The flag is not recorded in the args, so switching the message format
+    /// does not cause a recompile. Clang does not have JSON diagnostics,
+    /// so they are only in the output (see [`env::Compiler::json_diagnostics_flag`])
+    async fn compile_json(&self) -> cu::Result<()> {
+        message::emit(Message::CompileStarted {
+            source: &self.source_path,
+            object: &self.o_path,
+        });
+        let json_flag = env::get().compiler().json_diagnostics_flag();
+        let (child, _, stderr) = self
+            .compiler
+            .command()
+            .stdout(cu::lv::T)
+            .stderr(cu::pio::string())
+            .stdin_null()
+            .args(&self.args)
+            .args(json_flag)
+            .co_spawn()
+            .await?;
+        let status = child.co_wait().await?;
+        let (diagnostics, output) = message::parse_gcc_diagnostics(&stderr.co_join().await??);
+        if !status.success() {
+            message::emit(Message::CompileFailed {
+                source: &self.source_path,
+                diagnostics,
+                output,
+            });
+            cu::bail!("failed to compile {}: {status}", self.source_path.display());
+        }
+        message::emit(Message::CompileFinished {
+            source: &self.source_path,
+            object: &self.o_path,
+            diagnostics,
+            output,
+        });
         Ok(())
     }
 }
@@ -155,7 +275,9 @@ impl CompileCommands {
         cu::fs::write_json_pretty(path, &vec)
     }
 
+    /// Add the entry, replacing the old entry of the same file (for example when the flags changed)
     pub fn update(&mut self, entry: CompileCommandsEntry) {
+        self.entries.retain(|x| x.file != entry.file);
         self.entries.insert(entry);
     }
 }
@@ -210,3 +332,107 @@ impl From<&CompileRecord> for CompileCommandsEntry {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn set_modified(path: &Path, time: SystemTime) {
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn hash_dependencies() {
+        let dir = std::env::temp_dir().join(format!("megaton-compile-db-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("a.c");
+        let header = dir.join("a.h");
+        let touched = dir.join("b.h");
+        let deleted = dir.join("c.h");
+        for (path, content) in [
+            (&source, "int a;"),
+            (&header, "#pragma once"),
+            (&touched, ""),
+        ] {
+            std::fs::write(path, content).unwrap();
+        }
+        let started = SystemTime::now();
+        set_modified(&header, started - Duration::from_secs(10));
+        set_modified(&touched, started + Duration::from_secs(1));
+
+        let mut hashes = FileHashes::default();
+        let source_hash = hashes.get(&source).unwrap().to_string();
+        let header_hash = hashes.get(&header).unwrap().to_string();
+        assert_ne!(source_hash, header_hash);
+        assert_eq!(hashes.get(&deleted), None);
+
+        let o_path = dir.join("a.o");
+        let d_path = dir.join("a.d");
+        let record = CompileRecord {
+            source_path: source.clone(),
+            compiler: PathBuf::from("cc"),
+            args: vec![],
+            o_path: o_path.clone(),
+            d_path: d_path.clone(),
+            hashes: BTreeMap::from([(source.clone(), source_hash.clone())]),
+        };
+        let depfile = |deps: &[&Path]| {
+            let deps = deps
+                .iter()
+                .map(|x| x.display().to_string())
+                .collect::<Vec<_>>();
+            format!(
+                "{}: {} \\\n {}\n",
+                o_path.display(),
+                source.display(),
+                deps.join(" ")
+            )
+        };
+
+        // no depfile, for example for assembly sources
+        let mut no_depfile = record.clone();
+        no_depfile.hash_dependencies(&mut hashes, started).unwrap();
+        assert_eq!(no_depfile, record);
+
+        // a dependency touched while compiling is recorded without a hash
+        std::fs::write(&d_path, depfile(&[&header, &touched])).unwrap();
+        let mut compiled = record.clone();
+        compiled.hash_dependencies(&mut hashes, started).unwrap();
+        assert_eq!(
+            compiled.hashes,
+            BTreeMap::from([
+                (source.clone(), source_hash.clone()),
+                (header.clone(), header_hash),
+                (touched.clone(), String::new()),
+            ])
+        );
+
+        // the hash computed before compiling is kept
+        let mut known = record.clone();
+        known.hashes.insert(header.clone(), "before".to_string());
+        known.hash_dependencies(&mut hashes, started).unwrap();
+        assert_eq!(known.hashes[&header], "before");
+
+        // a dependency deleted while compiling cannot be hashed
+        std::fs::write(&d_path, depfile(&[&header, &deleted])).unwrap();
+        let mut compiled = record.clone();
+        let error = compiled
+            .hash_dependencies(&mut hashes, started)
+            .unwrap_err();
+        assert!(error.to_string().contains("c.h"), "{error}");
+
+        std::fs::write(&d_path, "not a depfile: \\").unwrap();
+        assert!(
+            record
+                .clone()
+                .hash_dependencies(&mut hashes, started)
+                .is_err()
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,12 +2,15 @@
 // Copyright (c) 2026 Megaton contributors
 
 use std::{
+    collections::BTreeMap,
     path::{Path, PathBuf},
     sync::Arc,
+    time::SystemTime,
 };
 
 use crate::buildsys::compile::{
-    CompileCommands, CompileCommandsEntry, CompileDB, SourceStatus, source,
+    CompileCommands, CompileCommandsEntry, CompileDB, FileHashes, FlagOverrides, Pch, SourceStatus,
+    SourceType, source,
 };
 
 use crate::config::Flags;
@@ -18,15 +21,25 @@ pub struct CompileCtx {
     source_paths: Vec<PathBuf>,
     output_path: Arc<PathBuf>,
     flags: Arc<Flags>,
+    overrides: FlagOverrides,
+    pch: Vec<PathBuf>,
 }
 
 impl CompileCtx {
     /// Initialize a new compilation context
-    pub fn new(source_paths: Vec<PathBuf>, output_path: PathBuf, flags: Flags) -> Self {
+    pub fn new(
+        source_paths: Vec<PathBuf>,
+        output_path: PathBuf,
+        flags: Flags,
+        overrides: FlagOverrides,
+        pch: Vec<PathBuf>,
+    ) -> Self {
         Self {
             source_paths,
             output_path: Arc::new(output_path),
             flags: Arc::new(flags),
+            overrides,
+            pch,
         }
     }
 }
@@ -50,6 +63,7 @@ pub async fn compile_all(
     }
 
     let mut compile_commands = CompileCommands::try_load_or_new(compile_commands_path);
+    let mut hashes = FileHashes::default();
 
     // Even if an object doesn't need compiled, we still need to pass back its path since this is
     // how the linker knows what objects need linked
@@ -60,12 +74,31 @@ pub async fn compile_all(
 
     let mut progress_bar = None;
 
-    // Configure all sources and start compile tasks
+    // Precompiled headers are compiled first, since the sources using them depend on them
+    let mut pch_sets = Vec::with_capacity(contexts.len());
     for ctx in contexts {
+        pch_sets
+            .push(precompile_headers(ctx, &mut compile_db, &mut hashes, configure_only, env).await);
+    }
+    let pch_sets = match pch_sets.into_iter().collect::<cu::Result<Vec<_>>>() {
+        Ok(x) => x,
+        Err(e) => {
+            compile_db.save(compile_db_path)?;
+            return Err(e);
+        }
+    };
+
+    // Configure all sources and start compile tasks
+    for (ctx, pch_set) in contexts.iter().zip(&pch_sets) {
         for src in source::scan(&ctx.source_paths)? {
             let record = compile_db.find_record(src.pathhash);
             let source_hash = src.pathhash;
-            match src.configure(&ctx.flags, &ctx.output_path, record, env)? {
+            let args = src.base_args(&ctx.flags, ctx.overrides.find(&src.path));
+            let pch = match src.source_type() {
+                SourceType::Cpp => pch_set.get(&args),
+                _ => None,
+            };
+            match src.configure(args, pch, &ctx.output_path, record, &mut hashes, env)? {
                 SourceStatus::UpToDate(object) => {
                     if !configure_only {
                         cu::debug!("Compile: object up to date {}", object.display());
@@ -92,9 +125,11 @@ pub async fn compile_all(
                             );
                         }
                         let parent_progress = progress_bar.clone().unwrap().clone();
-                        compile_db.update(source_hash, compile_record.clone());
-                        let handle = pool
-                            .spawn(async move { compile_record.compile(parent_progress).await });
+                        let handle = pool.spawn(async move {
+                            let started = SystemTime::now();
+                            let result = compile_record.compile(parent_progress).await;
+                            (source_hash, started, result.map(|_| compile_record))
+                        });
                         handles.push(handle);
                         total_tasks += 1;
                     }
@@ -117,8 +152,21 @@ pub async fn compile_all(
     let mut num_errors = 0;
     let mut set = cu::co::set(handles);
     while let Some(joined) = set.next().await {
-        if joined?.is_err() {
-            num_errors += 1;
+        let (source_hash, started, result) = joined?;
+        // the record is only saved after a successful compile, with the hashes of the dependencies
+        match result {
+            Ok(mut record) => match record.hash_dependencies(&mut hashes, started) {
+                Ok(()) => compile_db.update(source_hash, record),
+                Err(e) => {
+                    cu::error!("{e:?}");
+                    compile_db.remove(source_hash);
+                    num_errors += 1;
+                }
+            },
+            Err(_) => {
+                compile_db.remove(source_hash);
+                num_errors += 1;
+            }
         }
         completed_tasks += 1;
         cu::progress!(progress_bar = completed_tasks);
@@ -136,3 +184,53 @@ pub async fn compile_all(
         Ok((true, objects))
     }
 }
+
+/// Configure and compile the precompiled headers of the context, one for each set of C++ flags
+/// used by the sources. Returns the precompiled headers by the flags
+async fn precompile_headers(
+    ctx: &CompileCtx,
+    compile_db: &mut CompileDB,
+    hashes: &mut FileHashes,
+    configure_only: bool,
+    env: &'static Environment,
+) -> cu::Result<BTreeMap<Vec<String>, Pch>> {
+    let mut pch_set = BTreeMap::new();
+    if ctx.pch.is_empty() {
+        return Ok(pch_set);
+    }
+    let mut records = vec![];
+    for src in source::scan(&ctx.source_paths)? {
+        if src.source_type() != SourceType::Cpp {
+            continue;
+        }
+        let args = src.base_args(&ctx.flags, ctx.overrides.find(&src.path));
+        if pch_set.contains_key(&args) {
+            continue;
+        }
+        let (pch, record) =
+            Pch::configure(&ctx.pch, &args, &ctx.output_path, compile_db, hashes, env)?;
+        if let Some(record) = record {
+            records.push((pch.key(), record));
+        }
+        pch_set.insert(args, pch);
+    }
+    if configure_only || records.is_empty() {
+        return Ok(pch_set);
+    }
+
+    let progress_bar = cu::progress("Precompile headers")
+        .total(records.len())
+        .eta(false)
+        .spawn();
+    for (key, mut record) in records {
+        let started = SystemTime::now();
+        let result = record.compile(progress_bar.clone()).await;
+        if let Err(e) = result.and_then(|_| record.hash_dependencies(hashes, started)) {
+            compile_db.remove(key);
+            return Err(e.context("failed to compile precompiled header"));
+        }
+        compile_db.update(key, record);
+    }
+    progress_bar.done();
+    Ok(pch_set)
+}
@@ -6,6 +6,10 @@ use source_type::*;
 
 mod compile_db;
 use compile_db::*;
+mod overrides;
+pub use overrides::FlagOverrides;
+mod pch;
+use pch::Pch;
 mod source;
 use source::SourceStatus;
 mod driver;
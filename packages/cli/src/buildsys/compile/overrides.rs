@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use cu::pre::*;
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::config::FlagOverride;
+
+/// The `build.overrides` config, with the patterns compiled
+#[derive(Debug, Default)]
+pub struct FlagOverrides {
+    root: PathBuf,
+    overrides: Vec<(String, GlobMatcher, FlagOverride)>,
+}
+
+impl FlagOverrides {
+    /// Compile the patterns, which are relative to the root of the project
+    pub fn new(root: &Path, config: &BTreeMap<String, FlagOverride>) -> cu::Result<Self> {
+        let mut overrides = vec![];
+        for (pattern, flags) in config {
+            let glob = cu::check!(
+                GlobBuilder::new(pattern).literal_separator(true).build(),
+                "invalid pattern in build.overrides: {pattern}"
+            )?;
+            overrides.push((pattern.clone(), glob.compile_matcher(), flags.clone()));
+        }
+        Ok(Self {
+            root: root.to_path_buf(),
+            overrides,
+        })
+    }
+
+    /// Find the override for the source. If more than one pattern matches,
+    /// the longest one is used
+    pub fn find(&self, source: &Path) -> Option<&FlagOverride> {
+        let path = source.strip_prefix(&self.root).ok()?;
+        // patterns always use `/`
+        let path = path.as_utf8().ok()?.replace('\\', "/");
+        self.overrides
+            .iter()
+            .filter(|(_, glob, _)| glob.is_match(&path))
+            .max_by_key(|(pattern, _, _)| pattern.len())
+            .map(|(_, _, flags)| flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_override() {
+        let flag = |flag: &str| {
+            let mut flags = FlagOverride::default();
+            flags.c = Some(vec![flag.to_string()]);
+            flags
+        };
+        let config = BTreeMap::from([
+            ("vendor/**".to_string(), flag("-w")),
+            ("vendor/zlib/*.c".to_string(), flag("-O2")),
+            ("src/*.c".to_string(), flag("-Wextra")),
+        ]);
+        let overrides = FlagOverrides::new(Path::new("/mod"), &config).unwrap();
+        let find = |path: &str| {
+            overrides
+                .find(Path::new(path))
+                .and_then(|x| x.c.as_ref())
+                .map(|x| x[0].as_str())
+        };
+        assert_eq!(find("/mod/vendor/a/b.c"), Some("-w"));
+        assert_eq!(find("/mod/vendor/zlib/inflate.c"), Some("-O2"));
+        assert_eq!(find("/mod/src/main.c"), Some("-Wextra"));
+        assert_eq!(find("/mod/src/sub/main.c"), None);
+        assert_eq!(find("/other/vendor/a.c"), None);
+    }
+}
@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Precompiled headers (`build.pch`) for C++ sources
+//!
+//! GCC only uses one precompiled header per compilation, so all headers
+//! are included by a generated header, which is precompiled once for each set of
+//! C++ flags. The sources then use it with `-include`
+
+use std::path::{Path, PathBuf};
+
+use cu::pre::*;
+
+use super::compile_db::{CompileDB, CompileRecord, FileHashes};
+
+use crate::buildsys::compile::SourceType;
+use crate::buildsys::miscfile::write_if_changed;
+use crate::env::{Compiler, Environment};
+
+const PCH_NAME: &str = "megaton_pch.hpp";
+
+/// The precompiled header for one set of C++ flags
+#[derive(Debug, Clone)]
+pub struct Pch {
+    /// The generated header that includes all headers in `build.pch`
+    header: PathBuf,
+    /// The precompiled header, next to the generated header so GCC can find it
+    gch: PathBuf,
+}
+
+impl Pch {
+    /// Configure the precompiled header for the C++ flags of a source,
+    /// in a directory under `output_path` for the flags. Returns the record
+    /// to compile it with if it is not up to date
+    pub fn configure(
+        headers: &[PathBuf],
+        args: &[String],
+        output_path: &Path,
+        compile_db: &CompileDB,
+        hashes: &mut FileHashes,
+        env: &'static Environment,
+    ) -> cu::Result<(Self, Option<CompileRecord>)> {
+        let dir = output_path
+            .join("pch")
+            .join(format!("{:016x}", fxhash::hash(args)));
+        cu::fs::make_dir(&dir)?;
+        let header = dir.join(PCH_NAME);
+        let gch = dir.join(format!("{PCH_NAME}.gch"));
+        let d_path = dir.join(format!("{PCH_NAME}.d"));
+
+        let mut content = String::from("// Generated by megaton from build.pch, do not edit\n");
+        for header in headers {
+            content.push_str(&format!("#include \"{}\"\n", header.display()));
+        }
+        write_if_changed(&header, content.as_bytes())?;
+
+        let mut pch_args = args.to_vec();
+        pch_args.extend([
+            "-MMD".to_string(),
+            "-MP".to_string(),
+            "-MF".to_string(),
+            d_path.display().to_string(),
+            "-x".to_string(),
+            "c++-header".to_string(),
+            format!("-o{}", gch.display()),
+            header.display().to_string(),
+        ]);
+
+        let pch = Self { header, gch };
+        let record = compile_db.find_record(pch.key());
+        let up_to_date = record.is_some_and(|record| {
+            record.args == pch_args
+                && pch.gch.exists()
+                && d_path.exists()
+                && record.hashes.contains_key(&pch.header)
+                && record
+                    .hashes
+                    .iter()
+                    .all(|(path, hash)| hashes.get(path) == Some(hash.as_str()))
+        });
+        if up_to_date {
+            cu::debug!(
+                "Compile: precompiled header up to date {}",
+                pch.gch.display()
+            );
+            return Ok((pch, None));
+        }
+        let header_hash = hashes
+            .get(&pch.header)
+            .context(format!("failed to read {}", pch.header.display()))?
+            .to_string();
+        // hash the headers from the previous compile before compiling
+        if let Some(record) = record {
+            for path in record.hashes.keys() {
+                hashes.get(path);
+            }
+        }
+        let record = CompileRecord {
+            source_path: pch.header.clone(),
+            compiler: SourceType::Cpp.get_compiler(env).to_owned(),
+            args: pch_args,
+            o_path: pch.gch.clone(),
+            d_path,
+            hashes: [(pch.header.clone(), header_hash)].into(),
+        };
+        Ok((pch, Some(record)))
+    }
+
+    /// Key of the record in the compile DB
+    pub fn key(&self) -> usize {
+        fxhash::hash(&self.gch)
+    }
+
+    /// Path to the precompiled header. The sources using it depend on its content
+    pub fn gch(&self) -> &Path {
+        &self.gch
+    }
+
+    /// Arguments for the sources to use the precompiled header
+    ///
+    /// The compiler finds the `.gch` next to the header in `-include`. GCC includes the
+    /// headers normally if it cannot be used, and warns with `-Winvalid-pch`.
+    /// Clang does not fall back and fails to compile instead, so the flag is not added
+    pub fn args(&self, compiler: Compiler) -> Vec<String> {
+        let mut args = vec![];
+        if compiler == Compiler::Gcc {
+            args.push("-Winvalid-pch".to_string());
+        }
+        args.push("-include".to_string());
+        args.push(self.header.display().to_string());
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pch_args() {
+        let pch = Pch {
+            header: PathBuf::from("/o/pch/0/megaton_pch.hpp"),
+            gch: PathBuf::from("/o/pch/0/megaton_pch.hpp.gch"),
+        };
+        assert_eq!(
+            pch.args(Compiler::Gcc),
+            ["-Winvalid-pch", "-include", "/o/pch/0/megaton_pch.hpp"]
+        );
+        assert_eq!(
+            pch.args(Compiler::Clang),
+            ["-include", "/o/pch/0/megaton_pch.hpp"]
+        );
+    }
+}
@@ -1,14 +1,16 @@
 // SPDX-License-Identifier: MIT
 // Copyright (c) 2026 Megaton contributors
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use cu::pre::*;
 
-use super::compile_db::CompileRecord;
+use super::compile_db::{CompileRecord, FileHashes};
+use super::pch::Pch;
 
 use crate::buildsys::compile::SourceType;
-use crate::config::Flags;
+use crate::config::{self, FlagOverride, Flags};
 use crate::env::Environment;
 
 /// A source file and its corresponding artifacts
@@ -50,16 +52,36 @@ impl SourceFile {
         })
     }
 
+    pub fn source_type(&self) -> SourceType {
+        self.typ
+    }
+
+    /// Get the flags for compiling the source, with the override applied
+    pub fn base_args(&self, flags: &Flags, flag_override: Option<&FlagOverride>) -> Vec<String> {
+        // FIXME: clone() is expensive to do for every source file
+        match flag_override {
+            Some(flag_override) => config::resolve_override(
+                self.typ.get_flags(flags),
+                self.typ.get_language_range(flags),
+                self.typ.get_override_flags(flag_override),
+            ),
+            None => self.typ.get_flags(flags).clone(),
+        }
+    }
+
     pub fn configure(
         self,
-        flags: &Flags,
+        mut args: Vec<String>,
+        pch: Option<&Pch>,
         output_path: &Path,
         record: Option<&CompileRecord>,
+        hashes: &mut FileHashes,
         env: &'static Environment,
     ) -> cu::Result<SourceStatus> {
         let compiler = self.typ.get_compiler(env);
-        // FIXME: clone() is expensive to do for every source file
-        let mut args = self.typ.get_flags(flags).clone();
+        if let Some(pch) = pch {
+            args.extend(pch.args(env.compiler()));
+        }
 
         let o_path = self.get_o_path(output_path);
         let d_path = self.get_d_path(output_path);
@@ -74,18 +96,37 @@ impl SourceFile {
         args.push(format!("-o{}", o_path.display()));
         args.push(self.path.display().to_string());
 
-        if self.up_to_date(record, output_path, &args)? {
+        if self.up_to_date(record, output_path, &args, hashes) {
             cu::debug!("Compile: object up to date {}", o_path.display());
-            Ok(SourceStatus::UpToDate(o_path))
-        } else {
-            Ok(SourceStatus::CompileNeeded(CompileRecord {
-                source_path: self.path,
-                compiler: compiler.to_owned(),
-                args,
-                o_path,
-                d_path,
-            }))
+            return Ok(SourceStatus::UpToDate(o_path));
         }
+        let source_hash = hashes
+            .get(&self.path)
+            .context(format!("failed to read source {}", self.path.display()))?
+            .to_string();
+        let mut record_hashes = BTreeMap::from([(self.path.clone(), source_hash)]);
+        // hash the dependencies from the previous compile before compiling, so the
+        // hashes recorded after compiling are of the content the compiler read
+        if let Some(record) = record {
+            for path in record.hashes.keys() {
+                hashes.get(path);
+            }
+        }
+        // the sources using the precompiled header are rebuilt when it changes.
+        // it does not exist yet when only configuring
+        if let Some(pch) = pch
+            && let Some(hash) = hashes.get(pch.gch())
+        {
+            record_hashes.insert(pch.gch().to_path_buf(), hash.to_string());
+        }
+        Ok(SourceStatus::CompileNeeded(CompileRecord {
+            compiler: compiler.to_owned(),
+            args,
+            o_path,
+            d_path,
+            hashes: record_hashes,
+            source_path: self.path,
+        }))
     }
 
     fn up_to_date(
@@ -93,18 +134,19 @@ impl SourceFile {
         record: Option<&CompileRecord>,
         output_path: &Path,
         args: &[String],
-    ) -> cu::Result<bool> {
+        hashes: &mut FileHashes,
+    ) -> bool {
         // Chech that record exists
         let record = match record {
             Some(rec) => rec,
             None => {
-                return Ok(false);
+                return false;
             }
         };
 
         // Check that arguments have not changed
         if args != record.args {
-            return Ok(false);
+            return false;
         }
 
         let o_path = self.get_o_path(output_path);
@@ -112,45 +154,20 @@ impl SourceFile {
 
         // Check that artifacts exist
         if !o_path.exists() || (!d_path.exists() && self.typ.uses_depfile()) {
-            return Ok(false);
-        }
-
-        // Check that source time = o time
-        if cu::fs::get_mtime(&o_path)? != cu::fs::get_mtime(&self.path)? {
-            return Ok(false);
-        }
-
-        // Assembly files don't need dependency checks
-        if !self.typ.uses_depfile() {
-            return Ok(true);
-        }
-
-        // Check that source time = d time
-        if cu::fs::get_mtime(&d_path)? != cu::fs::get_mtime(&self.path)? {
-            return Ok(false);
+            return false;
         }
 
-        let d_file_contents = cu::fs::read_string(&d_path)?;
-        let depfile = match depfile::parse(&d_file_contents) {
-            Ok(depfile) => depfile,
-            Err(pos) => {
-                cu::bail!(
-                    "Error when parsing depfile {} at position {pos}",
-                    d_path.display()
-                );
-            }
-        };
-
-        // Check that all dependencies are up to date
-        for dep in depfile.recurse_deps(o_path.as_utf8()?) {
-            if cu::fs::get_mtime(PathBuf::from(dep))?.unwrap()
-                > cu::fs::get_mtime(&self.path)?.unwrap()
-            {
-                return Ok(false);
-            }
+        // Check that the source was hashed (records from older versions don't have hashes)
+        if !record.hashes.contains_key(&self.path) {
+            return false;
         }
 
-        Ok(true)
+        // Check that the source and all dependencies have the same content
+        // as when the source was compiled
+        record
+            .hashes
+            .iter()
+            .all(|(path, hash)| hashes.get(path) == Some(hash.as_str()))
     }
 
     fn get_o_path(&self, output_path: &Path) -> PathBuf {
@@ -193,3 +210,84 @@ pub fn scan(dirs: &[PathBuf]) -> cu::Result<impl Iterator<Item = SourceFile>> {
 
     Ok(walks.into_iter().flatten())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn up_to_date() {
+        let dir = std::env::temp_dir().join(format!("megaton-up-to-date-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let output = dir.join("o");
+        std::fs::create_dir_all(&output).unwrap();
+        let source = SourceFile::from_path(dir.join("a.c")).unwrap();
+        let header = dir.join("a.h");
+        std::fs::write(&source.path, "#include \"a.h\"").unwrap();
+        std::fs::write(&header, "#pragma once").unwrap();
+        let o_path = source.get_o_path(&output);
+        let d_path = source.get_d_path(&output);
+        std::fs::write(&o_path, "").unwrap();
+        std::fs::write(
+            &d_path,
+            format!(
+                "{}: {} {}\n",
+                o_path.display(),
+                source.path.display(),
+                header.display()
+            ),
+        )
+        .unwrap();
+
+        let args = vec!["-O3".to_string()];
+        let mut hashes = FileHashes::default();
+        let record = CompileRecord {
+            source_path: source.path.clone(),
+            compiler: PathBuf::from("cc"),
+            args: args.clone(),
+            o_path: o_path.clone(),
+            d_path: d_path.clone(),
+            hashes: BTreeMap::from([
+                (
+                    source.path.clone(),
+                    hashes.get(&source.path).unwrap().to_string(),
+                ),
+                (header.clone(), hashes.get(&header).unwrap().to_string()),
+            ]),
+        };
+        let up_to_date = |record: Option<&CompileRecord>, args: &[String]| {
+            source.up_to_date(record, &output, args, &mut FileHashes::default())
+        };
+        assert!(up_to_date(Some(&record), &args));
+        assert!(!up_to_date(None, &args));
+        assert!(!up_to_date(Some(&record), &["-O2".to_string()]));
+
+        // records from older versions don't have hashes
+        let mut legacy = record.clone();
+        legacy.hashes.clear();
+        assert!(!up_to_date(Some(&legacy), &args));
+
+        // a dependency touched while compiling is recorded without a hash
+        let mut touched = record.clone();
+        touched.hashes.insert(header.clone(), String::new());
+        assert!(!up_to_date(Some(&touched), &args));
+
+        // touching a file without changing the content does not recompile
+        std::fs::write(&header, "#pragma once").unwrap();
+        assert!(up_to_date(Some(&record), &args));
+        std::fs::write(&header, "#pragma once\n#define A 1").unwrap();
+        assert!(!up_to_date(Some(&record), &args));
+
+        std::fs::remove_file(&header).unwrap();
+        assert!(!up_to_date(Some(&record), &args));
+
+        std::fs::write(&header, "#pragma once").unwrap();
+        assert!(up_to_date(Some(&record), &args));
+        std::fs::remove_file(&d_path).unwrap();
+        assert!(!up_to_date(Some(&record), &args));
+        std::fs::remove_file(&o_path).unwrap();
+        assert!(!up_to_date(Some(&record), &args));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
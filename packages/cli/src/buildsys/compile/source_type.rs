@@ -2,9 +2,10 @@
 // Copyright (c) 2026 Megaton contributors
 
 use std::ffi::OsStr;
+use std::ops::Range;
 use std::path::Path;
 
-use crate::config::Flags;
+use crate::config::{FlagOverride, Flags};
 use crate::env::Environment;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -45,6 +46,25 @@ impl SourceType {
         }
     }
 
+    /// Get where the flags from `build.flags` of the language are in [`Self::get_flags`]
+    #[inline]
+    pub fn get_language_range(self, flags: &Flags) -> Range<usize> {
+        match self {
+            SourceType::C => flags.c_range.clone(),
+            SourceType::Cpp => flags.cxx_range.clone(),
+            SourceType::Assembly => flags.as_range.clone(),
+        }
+    }
+
+    #[inline]
+    pub fn get_override_flags(self, flags: &FlagOverride) -> &Option<Vec<String>> {
+        match self {
+            SourceType::C => &flags.c,
+            SourceType::Cpp => &flags.cxx,
+            SourceType::Assembly => &flags.as_,
+        }
+    }
+
     #[inline]
     pub fn uses_depfile(self) -> bool {
         matches!(self, Self::C | Self::Cpp)
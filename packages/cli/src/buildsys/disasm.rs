@@ -0,0 +1,346 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Disassembling the executable sections of the module
+//!
+//! Instructions are formatted like GNU objdump, so the patterns in
+//! `check.disallowed-instructions` written against the output of objdump still match
+
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use object::{Object as _, ObjectSection as _, ObjectSymbol as _, SectionFlags};
+use regex::{Captures, Regex};
+use yaxpeax_arch::{Decoder as _, U8Reader};
+use yaxpeax_arm::armv8::a64::{InstDecoder, Instruction};
+
+/// System registers that the decoder prints by encoding (`s<op0>_<op1>_c<n>_c<m>_<op2>`)
+/// or as `pstate.<field>`, with the names that objdump prints
+static SYSTEM_REGISTERS: &[(&str, &str)] = &[
+    ("pstate.0x5", "spsel"),
+    ("pstate.0x1e", "daifset"),
+    ("pstate.0x1f", "daifclr"),
+    ("s3_3_c4_c2_1", "daif"),
+    ("s3_3_c4_c2_0", "nzcv"),
+    ("s3_0_c4_c2_2", "currentel"),
+    ("s3_0_c4_c2_0", "spsel"),
+    ("s3_0_c4_c0_0", "spsr_el1"),
+    ("s3_0_c4_c0_1", "elr_el1"),
+    ("s3_0_c4_c1_0", "sp_el0"),
+    ("s3_3_c4_c4_0", "fpcr"),
+    ("s3_3_c4_c4_1", "fpsr"),
+    ("s3_0_c13_c0_4", "tpidr_el1"),
+    ("s3_3_c13_c0_2", "tpidr_el0"),
+    ("s3_3_c13_c0_3", "tpidrro_el0"),
+    ("s3_3_c14_c0_0", "cntfrq_el0"),
+    ("s3_3_c14_c0_1", "cntpct_el0"),
+    ("s3_3_c14_c0_2", "cntvct_el0"),
+];
+
+/// `sys` instructions that objdump prints with an alias:
+/// (operands before the register, alias, if the alias takes the register)
+static SYS_ALIASES: &[(&str, &str, bool)] = &[
+    ("#0x3, c7, c4, #0x1", "dc\tzva", true),
+    ("#0x0, c7, c6, #0x1", "dc\tivac", true),
+    ("#0x3, c7, c10, #0x1", "dc\tcvac", true),
+    ("#0x3, c7, c11, #0x1", "dc\tcvau", true),
+    ("#0x3, c7, c14, #0x1", "dc\tcivac", true),
+    ("#0x3, c7, c5, #0x1", "ic\tivau", true),
+    ("#0x0, c7, c5, #0x0", "ic\tiallu", false),
+    ("#0x0, c8, c7, #0x0", "tlbi\tvmalle1", false),
+];
+
+/// Instructions with immediates that objdump prints in decimal (shift amounts,
+/// bit positions and widths)
+static DECIMAL_IMMEDIATES: &[&str] = &[
+    "lsl", "lsr", "asr", "ror", "ubfx", "sbfx", "ubfiz", "sbfiz", "bfi", "bfxil", "bfc", "extr",
+    "tbz", "tbnz",
+];
+
+/// An immediate printed in hex by the decoder
+static HEX_IMMEDIATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"#(-?)0x([0-9a-f]+)").unwrap());
+/// A memory operand, with the offset of post-index addressing. Offsets are printed
+/// in decimal by objdump
+static MEMORY_OPERAND: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[[^\]]*\]!?(, #-?0x[0-9a-f]+)?").unwrap());
+/// A PC-relative target printed by the decoder
+static PC_RELATIVE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$([+-])0x([0-9a-f]+)").unwrap());
+
+/// Symbols of the module, to name the targets of branches like objdump does
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: BTreeMap<u64, String>,
+}
+
+impl SymbolTable {
+    /// Load the defined symbols of the ELF
+    pub fn new(file: &object::File) -> Self {
+        let mut symbols = BTreeMap::new();
+        for symbol in file.symbols() {
+            if symbol.is_undefined() || symbol.section_index().is_none() {
+                continue;
+            }
+            let Ok(name) = symbol.name() else {
+                continue;
+            };
+            // skip the mapping symbols ($x, $d) that mark code and data
+            if name.is_empty() || name.starts_with('$') {
+                continue;
+            }
+            symbols
+                .entry(symbol.address())
+                .or_insert_with(|| name.to_string());
+        }
+        Self { symbols }
+    }
+
+    /// Format an address like objdump, as `<hex address> <symbol+offset>`
+    fn format_address(&self, address: u64) -> String {
+        match self.symbols.range(..=address).next_back() {
+            Some((start, name)) if *start == address => format!("{address:x} <{name}>"),
+            Some((start, name)) => format!("{address:x} <{name}+0x{:x}>", address - start),
+            None => format!("{address:x}"),
+        }
+    }
+}
+
+/// An instruction in an executable section
+#[derive(Debug, Clone)]
+pub struct DecodedInstruction {
+    /// Address of the instruction in the module
+    pub address: u64,
+    /// The decoded instruction, `None` if the word is not a valid instruction (i.e. data)
+    pub instruction: Option<Instruction>,
+}
+
+impl DecodedInstruction {
+    /// Format the instruction like GNU objdump, as `<mnemonic>\t<operands>`.
+    ///
+    /// Compared to the decoder: system registers and `sys` operations are named,
+    /// PC-relative targets are absolute addresses followed by the symbol, and memory offsets,
+    /// shift amounts and bit positions are in decimal. Comments that objdump adds
+    /// after some instructions (like `// #1` after `mov`) are not included
+    pub fn to_asm(&self, symbols: &SymbolTable) -> Option<String> {
+        let asm = self.instruction.as_ref()?.to_string();
+        let (mnemonic, operands) = asm.split_once(' ').unwrap_or((&asm, ""));
+        if operands.is_empty() {
+            return Some(mnemonic.to_string());
+        }
+        if mnemonic == "sys" {
+            for (prefix, alias, has_register) in SYS_ALIASES {
+                if let Some(register) = operands.strip_prefix(prefix) {
+                    return Some(if *has_register {
+                        format!("{alias}{register}")
+                    } else {
+                        alias.to_string()
+                    });
+                }
+            }
+        }
+
+        let mut operands = operands.to_string();
+        for (encoding, name) in SYSTEM_REGISTERS {
+            // the encoding can be followed by `,` or the end of the instruction
+            if let Some(start) = operands.find(encoding) {
+                let end = start + encoding.len();
+                if operands[end..].chars().next().is_none_or(|c| c == ',') {
+                    operands.replace_range(start..end, name);
+                }
+            }
+        }
+        let mut operands = MEMORY_OPERAND
+            .replace_all(&operands, |x: &Captures| to_decimal(&x[0]))
+            .into_owned();
+        if DECIMAL_IMMEDIATES.contains(&mnemonic) {
+            operands = to_decimal(&operands);
+        }
+        let operands = PC_RELATIVE.replace_all(&operands, |x: &Captures| {
+            let offset = u64::from_str_radix(&x[2], 16).unwrap_or_default();
+            // adrp is relative to the page of the instruction
+            let base = if mnemonic == "adrp" {
+                self.address & !0xfff
+            } else {
+                self.address
+            };
+            let target = if &x[1] == "-" {
+                base.wrapping_sub(offset)
+            } else {
+                base.wrapping_add(offset)
+            };
+            symbols.format_address(target)
+        });
+        Some(format!("{mnemonic}\t{operands}"))
+    }
+}
+
+/// Convert the hex immediates to decimal
+fn to_decimal(text: &str) -> String {
+    HEX_IMMEDIATE
+        .replace_all(text, |x: &Captures| {
+            let value = u64::from_str_radix(&x[2], 16).unwrap_or_default();
+            format!("#{}{value}", &x[1])
+        })
+        .into_owned()
+}
+
+/// Decode all instructions in the executable sections of the ELF
+pub fn disassemble(file: &object::File) -> Vec<DecodedInstruction> {
+    let decoder = InstDecoder::default();
+    let mut out = vec![];
+    for section in file.sections() {
+        let SectionFlags::Elf { sh_flags } = section.flags() else {
+            continue;
+        };
+        if sh_flags & u64::from(object::elf::SHF_EXECINSTR) == 0 {
+            continue;
+        }
+        let Ok(data) = section.data() else {
+            continue;
+        };
+        let start = section.address();
+        for (i, word) in data.chunks_exact(4).enumerate() {
+            let mut reader = U8Reader::new(word);
+            out.push(DecodedInstruction {
+                address: start + (i as u64) * 4,
+                instruction: decoder.decode(&mut reader).ok(),
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asm_at(word: u32, address: u64, symbols: &SymbolTable) -> Option<String> {
+        let bytes = word.to_le_bytes();
+        let mut reader = U8Reader::new(&bytes);
+        DecodedInstruction {
+            address,
+            instruction: InstDecoder::default().decode(&mut reader).ok(),
+        }
+        .to_asm(symbols)
+    }
+
+    fn asm(word: u32) -> Option<String> {
+        asm_at(word, 0, &SymbolTable::default())
+    }
+
+    #[test]
+    fn system_registers() {
+        assert_eq!(asm(0xd50041bf).unwrap(), "msr\tspsel, #0x1");
+        assert_eq!(asm(0xd50342df).unwrap(), "msr\tdaifset, #0x2");
+        assert_eq!(asm(0xd53b4220).unwrap(), "mrs\tx0, daif");
+        assert_eq!(asm(0xd538d080).unwrap(), "mrs\tx0, tpidr_el1");
+        assert_eq!(asm(0xd518d080).unwrap(), "msr\ttpidr_el1, x0");
+        assert_eq!(asm(0xd53bd040).unwrap(), "mrs\tx0, tpidr_el0");
+        assert_eq!(asm(0xd503201f).unwrap(), "nop");
+        assert_eq!(asm(0x0001a6e0), None);
+    }
+
+    /// (address, word, output of GNU objdump -d without the comments)
+    static OBJDUMP: &[(u64, u32, &str)] = &[
+        (0x100, 0xa9bf7bfd, "stp\tx29, x30, [sp, #-16]!"),
+        (0x104, 0x910003fd, "mov\tx29, sp"),
+        (0x108, 0x97fffffe, "bl\t100 <foo>"),
+        (0x10c, 0x94000010, "bl\t14c <foo+0x4c>"),
+        (0x110, 0xb4000040, "cbz\tx0, 118 <foo+0x18>"),
+        (0x114, 0x54000040, "b.eq\t11c <foo+0x1c>"),
+        (0x118, 0x36000040, "tbz\tw0, #0, 120 <foo+0x20>"),
+        (0x11c, 0xb7f80040, "tbnz\tx0, #63, 124 <foo+0x24>"),
+        (0x120, 0x17fffff8, "b\t100 <foo>"),
+        (0x124, 0xf0000000, "adrp\tx0, 3000 <bar>"),
+        (0x128, 0x58000040, "ldr\tx0, 130 <foo+0x30>"),
+        (0x200, 0x10000040, "adr\tx0, 208 <baz+0x8>"),
+        (0, 0xf9400820, "ldr\tx0, [x1, #16]"),
+        (0, 0xb9000fe0, "str\tw0, [sp, #12]"),
+        (0, 0xb85fc000, "ldur\tw0, [x0, #-4]"),
+        (0, 0xf81f0fe0, "str\tx0, [sp, #-16]!"),
+        (0, 0xa8c17bfd, "ldp\tx29, x30, [sp], #16"),
+        (0, 0xad7f0440, "ldp\tq0, q1, [x2, #-32]"),
+        (0, 0xb8617800, "ldr\tw0, [x0, x1, lsl #2]"),
+        (0, 0xf9400000, "ldr\tx0, [x0]"),
+        (0, 0xc85f7c20, "ldxr\tx0, [x1]"),
+        (0, 0x91004020, "add\tx0, x1, #0x10"),
+        (0, 0x91400420, "add\tx0, x1, #0x1, lsl #12"),
+        (0, 0xd10083ff, "sub\tsp, sp, #0x20"),
+        (0, 0x52800020, "mov\tw0, #0x1"),
+        (0, 0xf2a24680, "movk\tx0, #0x1234, lsl #16"),
+        (0, 0x12001c00, "and\tw0, w0, #0xff"),
+        (0, 0x7100041f, "cmp\tw0, #0x1"),
+        (0, 0x8b000820, "add\tx0, x1, x0, lsl #2"),
+        (0, 0xd37cec20, "lsl\tx0, x1, #4"),
+        (0, 0x13017c20, "asr\tw0, w1, #1"),
+        (0, 0xd3441c20, "ubfx\tx0, x1, #4, #4"),
+        (0, 0xb3401c20, "bfxil\tx0, x1, #0, #8"),
+        (0, 0x937c7c20, "sbfiz\tx0, x1, #4, #32"),
+        (0, 0x1a9f07e0, "cset\tw0, ne"),
+        (0, 0xd65f03c0, "ret"),
+        (0, 0xd63f0020, "blr\tx1"),
+        (0, 0xd4000001, "svc\t#0x0"),
+        (0, 0xd4400000, "hlt\t#0x0"),
+        (0, 0xd4200000, "brk\t#0x0"),
+        (0, 0xd5033bbf, "dmb\tish"),
+        (0, 0xd50b7b20, "dc\tcvau, x0"),
+        (0, 0xd508871f, "tlbi\tvmalle1"),
+        (0, 0xd50041bf, "msr\tspsel, #0x1"),
+        (0, 0xd50342df, "msr\tdaifset, #0x2"),
+        (0, 0xd53b4220, "mrs\tx0, daif"),
+        (0, 0xd538d080, "mrs\tx0, tpidr_el1"),
+        (0, 0xd518d080, "msr\ttpidr_el1, x0"),
+        (0, 0xd53bd060, "mrs\tx0, tpidrro_el0"),
+    ];
+
+    #[test]
+    fn objdump_syntax() {
+        let symbols = SymbolTable {
+            symbols: [(0x100, "foo"), (0x200, "baz"), (0x3000, "bar")]
+                .into_iter()
+                .map(|(address, name)| (address, name.to_string()))
+                .collect(),
+        };
+        for (address, word, expected) in OBJDUMP {
+            assert_eq!(
+                asm_at(*word, *address, &symbols).as_deref(),
+                Some(*expected),
+                "{word:08x}"
+            );
+        }
+    }
+
+    #[test]
+    fn objdump_patterns_match() {
+        // the default patterns when the check used the output of objdump
+        let patterns = [
+            r"^msr\s*spsel",
+            r"^msr\s*daifset",
+            r"^mrs\.*daif",
+            r"^mrs\.*tpidr_el1",
+            r"^msr\s*tpidr_el1",
+            r"^hlt",
+        ]
+        .map(|x| Regex::new(x).unwrap());
+        let symbols = SymbolTable::default();
+        let mut matched = vec![];
+        for (address, word, objdump) in OBJDUMP {
+            let asm = asm_at(*word, *address, &symbols).unwrap();
+            let is_match = |text: &str| patterns.iter().any(|r| r.is_match(text));
+            assert_eq!(is_match(&asm), is_match(objdump), "{asm}");
+            if is_match(&asm) {
+                matched.push(asm);
+            }
+        }
+        assert_eq!(
+            matched,
+            [
+                "hlt\t#0x0",
+                "msr\tspsel, #0x1",
+                "msr\tdaifset, #0x2",
+                "msr\ttpidr_el1, x0"
+            ]
+        );
+    }
+}
@@ -1,15 +1,78 @@
 // SPDX-License-Identifier: MIT
 // Copyright (c) 2026 Megaton contributors
 
+use std::path::{Path, PathBuf};
+
 use cu::pre::*;
 
-use crate::buildsys::compile::CompileCtx;
+use crate::buildsys::compile::{CompileCtx, FlagOverrides};
+use crate::buildsys::message::{self, Message};
 use crate::buildsys::rust::RustCtx;
-use crate::buildsys::{self, BuildArgs, check, compile, link, miscfile};
-use crate::config::{self, BASE_PROFILE, Flags};
+use crate::buildsys::timings::{self, TimingKind};
+use crate::buildsys::{self, BuildArgs, check, compile, embed, link, miscfile};
+use crate::config::{self, BASE_PROFILE, Flags, Module};
 use crate::env;
 
-pub async fn run(args: BuildArgs) -> cu::Result<()> {
+/// Outputs of a successful build
+#[derive(Debug, Clone)]
+pub struct BuildOutput {
+    /// The resolved profile that was built
+    pub profile: String,
+    /// Path to the module's output directory in the target directory
+    pub target_mod: PathBuf,
+    /// Path to the module NSO file
+    pub nso: PathBuf,
+    /// Path to the main.npdm file
+    pub npdm: PathBuf,
+}
+
+/// Get the output directory of the module for a profile,
+/// i.e. `<target>/megaton/<profile>/<module>`
+pub fn module_target_path(root: &Path, module: &Module, profile: &str) -> PathBuf {
+    let mut p = module.target_path(root);
+    p.extend(["megaton", profile, &module.name]);
+    p
+}
+
+/// Get the path to the linked ELF of the module for a profile
+pub fn elf_path(root: &Path, module: &Module, profile: &str) -> PathBuf {
+    module_target_path(root, module, profile).join(format!("{}.elf", module.name))
+}
+
+/// Get the path to the link map of the module for a profile
+pub fn map_path(root: &Path, module: &Module, profile: &str) -> PathBuf {
+    module_target_path(root, module, profile).join(format!("{}.map", module.name))
+}
+
+/// Get the path to the NSO of the module for a profile
+pub fn nso_path(root: &Path, module: &Module, profile: &str) -> PathBuf {
+    module_target_path(root, module, profile).join(format!("{}.nso", module.name))
+}
+
+/// Run the build. Returns `None` if only configuring
+pub async fn run(args: BuildArgs) -> cu::Result<Option<BuildOutput>> {
+    message::set_message_format(args.message_format);
+    if args.timings {
+        timings::enable();
+    }
+    let result = run_internal(args).await;
+    if let Err(e) = timings::finish() {
+        cu::warn!("failed to write timing report: {e:?}");
+    }
+    let (nso, npdm) = match &result {
+        Ok(Some(output)) => (Some(output.nso.as_path()), Some(output.npdm.as_path())),
+        _ => (None, None),
+    };
+    message::emit(Message::BuildFinished {
+        success: result.is_ok(),
+        nso,
+        npdm,
+        error: result.as_ref().err().map(|e| format!("{e:#}")),
+    });
+    result
+}
+
+async fn run_internal(args: BuildArgs) -> cu::Result<Option<BuildOutput>> {
     let env = env::get();
 
     ////////// Load config //////////
@@ -24,8 +87,23 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
     if profile != BASE_PROFILE {
         cu::info!("building profile '{profile}'");
     }
+    message::emit(Message::ConfigLoaded {
+        manifest: &manifest_path,
+        profile,
+        module: &config.module.name,
+        title_id: config.module.title_id_hex(),
+    });
+    if message::is_json() && env.compiler() == env::Compiler::Clang {
+        cu::warn!(
+            "clang does not have JSON diagnostics, so they are only in the output of the compile messages"
+        );
+    }
     let build_config = config.build.get_profile(profile);
-    let mut build_flags = Flags::from_config(&build_config.flags);
+    let mut build_flags = Flags::from_config(&build_config.flags, env.compiler());
+    if config.check.is_some() {
+        // the stack usage is only collected when the stack is checked
+        build_flags.add_stack_usage();
+    }
     let target_path = {
         let mut p = config.module.target_path(&root_path);
         p.push("megaton");
@@ -37,6 +115,7 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
     let lib_unpack_task = {
         let lib_unpack_path = lib_unpack_path.clone();
         cu::co::spawn(async move {
+            let _timing = timings::span(TimingKind::Phase, "unpack-lib");
             if lib_enabled {
                 buildsys::unpack_megaton_lib(&lib_unpack_path).await?
             }
@@ -45,10 +124,11 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
     };
     let profile_target_path = target_path.join(profile);
     cu::fs::make_dir(&profile_target_path)?;
+    timings::set_report_dir(&profile_target_path);
 
     // Set up target paths
     // TODO: probably don't need to do them at this time?
-    let target_mod = profile_target_path.join(&config.module.name);
+    let target_mod = module_target_path(&root_path, &config.module, profile);
     let target_mod_src = target_mod.join("src");
     let target_mod_include = target_mod.join("include");
     let target_mod_o = target_mod.join("o");
@@ -58,9 +138,22 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
     cu::fs::make_dir(&target_mod_o)?;
 
     if !args.configure {
-        miscfile::make_npdm(&target_mod, &config.module.title_id_hex()).await?;
+        let _timing = timings::span(TimingKind::Phase, "npdm");
+        let npdm_config = config.npdm.get_profile(profile);
+        miscfile::make_npdm(&target_mod, &npdm_config, config.module.title_id)?;
     }
 
+    let embed_output = {
+        let _timing = timings::span(TimingKind::Phase, "embed");
+        embed::generate(
+            &root_path,
+            &build_config.embed,
+            &target_mod,
+            &target_mod_include,
+        )
+        .context("Failed to generate embedded assets")?
+    };
+
     let mut static_libs = vec![];
     let mut need_link = false;
 
@@ -71,11 +164,13 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
     let rust_ctx = RustCtx::from_config(config.cargo);
     let rust_enabled = rust_ctx.is_some();
     if lib_enabled && let Some(rust_ctx) = rust_ctx {
-        let rust_ctx =
+        let mut rust_ctx =
             rust_ctx.context("Rust is enabled, but cargo context could not be initialized")?;
+        rust_ctx.set_env(embed::RUST_ENV, embed_output.rust_path.clone());
         rust_ctx.check_cxx_version()?;
 
         if !args.configure {
+            let _timing = timings::span(TimingKind::Phase, "cargo");
             need_link |= rust_ctx
                 .build(&build_flags.cargoflags, &build_flags.rustflags, false)
                 .await?;
@@ -87,11 +182,13 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
             );
         } else if rust_ctx.has_build_script() {
             // run cargo check which calls build script before configuring
+            let _timing = timings::span(TimingKind::Phase, "cargo-check");
             rust_ctx
                 .build(&build_flags.cargoflags, &build_flags.rustflags, true)
                 .await?;
         }
 
+        let _timing = timings::span(TimingKind::Phase, "cxxbridge");
         need_link |= rust_ctx
             .gen_cxxbridge(&target_mod_src, &target_mod_include)
             .await
@@ -134,6 +231,8 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
             vec![lib_unpack_path.join("src")],
             target_mod_o.clone(),
             lib_flags,
+            FlagOverrides::default(),
+            vec![],
         );
         contexts.push(lib_ctx);
     }
@@ -147,8 +246,16 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
     }
 
     let mut build_sources = vec![
-        target_mod_src, // cxxbridge src
+        target_mod_src,       // cxxbridge src
+        embed_output.src_dir, // embedded assets
     ];
+    if !lib_enabled && env.compiler() == env::Compiler::Clang {
+        // libmegaton has the module name, and lld cannot add it
+        let module_name_dir = target_mod.join("module_name");
+        cu::fs::make_dir(&module_name_dir)?;
+        miscfile::make_module_name(&module_name_dir.join("module_name.s"), &config.module.name)?;
+        build_sources.push(module_name_dir);
+    }
     for source in build_config.sources {
         build_sources.push(source.normalize_exists()?);
     }
@@ -156,11 +263,24 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
     let mut module_flags = build_flags.clone();
     module_flags.add_includes(build_includes);
 
-    let mod_ctx = compile::CompileCtx::new(build_sources, target_mod_o.clone(), module_flags);
+    // overrides only apply to the module, not libmegaton
+    let overrides = FlagOverrides::new(&root_path, &build_config.overrides)?;
+    let mut pch = vec![];
+    for header in build_config.pch {
+        pch.push(header.normalize_exists()?);
+    }
+    let mod_ctx = compile::CompileCtx::new(
+        build_sources,
+        target_mod_o.clone(),
+        module_flags,
+        overrides,
+        pch,
+    );
     contexts.push(mod_ctx);
 
     // Compile both contexts
     let compile_commands_path = config.module.compdb_path(&root_path);
+    let compile_timing = timings::span(TimingKind::Phase, "compile");
     let (compiled, mut objects) = compile::compile_all(
         &contexts,
         &compile_db_path,
@@ -169,12 +289,16 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
         env,
     )
     .await?;
+    drop(compile_timing);
     need_link |= compiled;
 
     ////////// Link & Check //////////
     if args.configure {
         cu::info!("Configured build");
-        return Ok(());
+        return Ok(None);
+    }
+    if env.compiler() == env::Compiler::Clang {
+        link::check_no_tls(&objects)?;
     }
 
     let mut libpaths = vec![];
@@ -196,6 +320,7 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
     miscfile::make_verfile(&verfile_path, entry)?;
     build_flags.set_init(entry);
     build_flags.set_version_script(verfile_path.into_utf8()?);
+    build_flags.set_map_file(map_path(&root_path, &config.module, profile).into_utf8()?);
     build_flags.add_ldscripts(ldscripts);
     build_flags.add_libraries(build_config.libraries);
 
@@ -203,7 +328,14 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
         objects.push(obj.normalize_exists()?);
     }
 
-    let elf_path = target_mod.join(format!("{}.elf", config.module.name));
+    let elf_path = elf_path(&root_path, &config.module, profile);
+    // used by the check to find where missing symbols are referenced
+    let link_inputs = objects
+        .iter()
+        .chain(&static_libs)
+        .cloned()
+        .collect::<Vec<_>>();
+    let link_timing = timings::span(TimingKind::Phase, "link");
     let linked = link::build_elf(
         need_link,
         objects,
@@ -213,29 +345,49 @@ pub async fn run(args: BuildArgs) -> cu::Result<()> {
         &target_mod.join("linkcmd.cache"),
     )
     .await?;
-
-    let nso_path = target_mod.join(format!("{}.nso", config.module.name));
-    if linked || !nso_path.exists() {
+    drop(link_timing);
+    message::emit(Message::Link {
+        elf: &elf_path,
+        linked,
+    });
+
+    let nso_path = nso_path(&root_path, &config.module, profile);
+    let need_nso = linked || !nso_path.exists();
+    if let Some(check_config) = config.check {
+        let check_config = check_config.get_profile(profile);
+        let npdm_config = config.npdm.get_profile(profile);
         // TODO: check while building nso, delete nso afterwards if check fails
-        if let Some(check_config) = config.check {
-            let check_config = check_config.get_profile(profile);
-            let mut symbol_files = vec![];
-            for symbol_file in check_config.symbols {
-                symbol_files.push(symbol_file.normalize_exists()?);
-            }
-            check::check_all(
+        if need_nso {
+            check::check_module(
+                &root_path,
+                &check_config,
+                &npdm_config,
                 &elf_path,
-                &check_config.ignore,
-                &check_config.disallowed_instructions,
-                &symbol_files,
+                &link_inputs,
+            )
+        } else {
+            // the ELF is already checked, but the budgets are checked on every build
+            check::check_budgets(
+                &root_path,
+                &check_config,
+                &npdm_config,
+                &elf_path,
+                &link_inputs,
             )
-            .await
-            .context("Check failed")?;
         }
-        link::build_nso(&elf_path, &nso_path).await?;
+        .context("Check failed")?;
+    }
+    if need_nso {
+        let _timing = timings::span(TimingKind::Phase, "nso");
+        link::build_nso(&elf_path, &nso_path)?;
     } else {
         cu::info!("Up to date")
     }
 
-    Ok(())
+    Ok(Some(BuildOutput {
+        profile: profile.to_string(),
+        npdm: target_mod.join("main.npdm"),
+        nso: nso_path,
+        target_mod,
+    }))
 }
@@ -0,0 +1,386 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Embedding binary assets (`[build.embed]`) into the module
+//!
+//! Each asset is included with `.incbin` in a generated assembly source,
+//! which is compiled with the other sources of the module. The SHA-256 of the asset
+//! is written in the generated source, so the object is rebuilt by the compile DB
+//! when the asset changes
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use cu::pre::*;
+use globset::GlobBuilder;
+use sha2::{Digest, Sha256};
+
+use crate::buildsys::miscfile::write_if_changed;
+use crate::config::Embed;
+
+/// Name of the generated C header, in the include directory of the module
+pub const HEADER_NAME: &str = "megaton_assets.h";
+/// Environment variable set for cargo, with the path to the generated Rust accessors
+pub const RUST_ENV: &str = "MEGATON_ASSETS_RS";
+
+/// An asset to embed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asset {
+    /// Name of the asset, used in the symbols `megaton_asset_<name>`
+    pub name: String,
+    /// Absolute path to the asset
+    pub path: PathBuf,
+    /// Path to display, relative to the root
+    pub display: String,
+}
+
+impl Asset {
+    pub fn symbol(&self) -> String {
+        format!("megaton_asset_{}", self.name)
+    }
+}
+
+/// Paths to the files generated for the embedded assets
+#[derive(Debug, Clone)]
+pub struct EmbedOutput {
+    /// Directory with the generated assembly sources
+    pub src_dir: PathBuf,
+    /// Generated Rust accessors
+    pub rust_path: PathBuf,
+}
+
+/// Generate the sources, C header and Rust accessors for the assets.
+///
+/// The assembly sources are put in `<target_mod>/embed`, the header in `include_dir`
+/// and the Rust accessors in `<target_mod>/megaton_assets.rs`.
+/// Files are only written if they change
+pub fn generate(
+    root: &Path,
+    config: &Embed,
+    target_mod: &Path,
+    include_dir: &Path,
+) -> cu::Result<EmbedOutput> {
+    let assets = find_assets(root, &config.files)?;
+    let src_dir = target_mod.join("embed");
+    cu::fs::make_dir(&src_dir)?;
+
+    let mut sources = vec![];
+    for asset in &assets {
+        let content = cu::check!(
+            cu::fs::read(&asset.path),
+            "failed to read asset {}",
+            asset.display
+        )?;
+        let hash = Sha256::digest(content)
+            .iter()
+            .map(|x| format!("{x:02x}"))
+            .collect::<String>();
+        let source = src_dir.join(format!("{}.s", asset.name));
+        if write_if_changed(&source, asm_source(asset, &hash, config.align()).as_bytes())? {
+            cu::debug!("Embed: updated {}", source.display());
+        }
+        sources.push(source);
+    }
+
+    // remove the sources of assets that are no longer embedded
+    let mut stale = vec![];
+    for entry in cu::fs::walk(&src_dir)? {
+        let path = entry?.path().to_path_buf();
+        if !sources.contains(&path) {
+            stale.push(path);
+        }
+    }
+    for path in stale {
+        cu::debug!("Embed: removing {}", path.display());
+        cu::fs::remove(&path)?;
+    }
+
+    write_if_changed(&include_dir.join(HEADER_NAME), c_header(&assets).as_bytes())?;
+    let rust_path = target_mod.join("megaton_assets.rs");
+    write_if_changed(&rust_path, rust_source(&assets).as_bytes())?;
+
+    Ok(EmbedOutput { src_dir, rust_path })
+}
+
+/// Expand the files and glob patterns, which are relative to the root
+fn find_assets(root: &Path, files: &[String]) -> cu::Result<Vec<Asset>> {
+    let mut paths = BTreeMap::<PathBuf, String>::new();
+    for pattern in files {
+        if !is_glob(pattern) {
+            let path = cu::check!(
+                root.join(pattern).normalize_exists(),
+                "cannot find asset {pattern} in build.embed.files"
+            )?;
+            paths.insert(path, pattern.clone());
+            continue;
+        }
+        let glob = cu::check!(
+            GlobBuilder::new(pattern).literal_separator(true).build(),
+            "invalid pattern in build.embed.files: {pattern}"
+        )?
+        .compile_matcher();
+        // only walk the part of the pattern without glob characters
+        let base = asset_watch_paths(std::slice::from_ref(pattern));
+        let base = root.join(&base[0]);
+        if !base.exists() {
+            cu::warn!("no asset matches {pattern} in build.embed.files");
+            continue;
+        }
+        let mut found = false;
+        for entry in cu::fs::walk(&base)? {
+            let path = entry?.path().to_path_buf();
+            let Ok(rel_path) = path.strip_prefix(root) else {
+                continue;
+            };
+            // patterns always use `/`
+            let rel_path = rel_path
+                .components()
+                .filter(|x| !matches!(x, Component::CurDir))
+                .map(|x| x.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if glob.is_match(&rel_path) {
+                found = true;
+                paths.insert(path.normalize()?, rel_path);
+            }
+        }
+        if !found {
+            cu::warn!("no asset matches {pattern} in build.embed.files");
+        }
+    }
+
+    let mut names = BTreeMap::<String, Asset>::new();
+    for (path, display) in paths {
+        let name = asset_name(&path);
+        if RUST_NON_RAW_NAMES.contains(&name.as_str()) {
+            cu::bail!(
+                "asset {display} is named '{name}', which cannot be a function in Rust; rename it"
+            );
+        }
+        if let Some(other) = names.get(&name) {
+            cu::bail!(
+                "assets {} and {display} have the same name '{name}'; rename one of them",
+                other.display
+            );
+        }
+        names.insert(
+            name.clone(),
+            Asset {
+                name,
+                path,
+                display,
+            },
+        );
+    }
+    Ok(names.into_values().collect())
+}
+
+/// Get the paths to watch for changes of the assets: the files, or the directories
+/// before the first glob character of the patterns. Relative to the root
+pub fn asset_watch_paths(files: &[String]) -> Vec<PathBuf> {
+    files
+        .iter()
+        .map(|pattern| {
+            Path::new(pattern)
+                .components()
+                .take_while(|x| !is_glob(&x.as_os_str().to_string_lossy()))
+                .collect::<PathBuf>()
+        })
+        .collect()
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// Keywords and reserved words in Rust, which are emitted as raw identifiers (`r#type`)
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Names that cannot be identifiers in Rust, even as raw identifiers
+const RUST_NON_RAW_NAMES: &[&str] = &["_", "crate", "self", "super"];
+
+/// Make the name of the asset from the file name, for example `Font-12.ttf`
+/// becomes `font_12_ttf`
+fn asset_name(path: &Path) -> String {
+    let file_name = path
+        .file_name()
+        .map(|x| x.to_string_lossy())
+        .unwrap_or_default();
+    let mut name = file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+fn asm_source(asset: &Asset, hash: &str, align: u64) -> String {
+    let symbol = asset.symbol();
+    let path = asset
+        .path
+        .display()
+        .to_string()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    format!(
+        r#"/* Generated by megaton from build.embed, do not edit */
+/* {display} sha256:{hash} */
+    .section .rodata.{symbol}, "a", %progbits
+    .global {symbol}
+    .hidden {symbol}
+    .type {symbol}, %object
+    .balign {align}
+{symbol}:
+    .incbin "{path}"
+.L{symbol}_end:
+    .size {symbol}, .L{symbol}_end - {symbol}
+
+    .section .rodata.{symbol}_size, "a", %progbits
+    .global {symbol}_size
+    .hidden {symbol}_size
+    .type {symbol}_size, %object
+    .balign 8
+{symbol}_size:
+    .quad .L{symbol}_end - {symbol}
+    .size {symbol}_size, 8
+
+    .section .note.GNU-stack, "", %progbits
+"#,
+        display = asset.display,
+    )
+}
+
+fn c_header(assets: &[Asset]) -> String {
+    let mut out = String::from(
+        "// Generated by megaton from build.embed, do not edit\n#pragma once\n#include <stdint.h>\n\n#ifdef __cplusplus\nextern \"C\" {\n#endif\n",
+    );
+    for asset in assets {
+        let symbol = asset.symbol();
+        out.push_str(&format!(
+            "\n// {}\nextern const uint8_t {symbol}[];\nextern const uint64_t {symbol}_size;\n",
+            asset.display
+        ));
+    }
+    out.push_str("\n#ifdef __cplusplus\n}\n#endif\n");
+    out
+}
+
+fn rust_source(assets: &[Asset]) -> String {
+    let mut out = String::from("// Generated by megaton from build.embed, do not edit\n");
+    for asset in assets {
+        let symbol = asset.symbol();
+        out.push_str(&format!(
+            r#"
+/// Content of `{display}`
+pub fn {name}() -> &'static [u8] {{
+    unsafe extern "C" {{
+        static {symbol}: [u8; 0];
+        static {symbol}_size: u64;
+    }}
+    unsafe {{ core::slice::from_raw_parts({symbol}.as_ptr(), {symbol}_size as usize) }}
+}}
+"#,
+            display = asset.display,
+            name = rust_ident(&asset.name),
+        ));
+    }
+    out
+}
+
+/// Get the identifier of the asset in Rust, raw if the name is a keyword
+fn rust_ident(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_and_generate() {
+        let root = std::env::temp_dir().join(format!("megaton-embed-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("assets/levels")).unwrap();
+        std::fs::write(root.join("assets/Font-12.ttf"), b"font").unwrap();
+        std::fs::write(root.join("assets/levels/1.bin"), b"level1").unwrap();
+        std::fs::write(root.join("assets/levels/2.bin"), b"level2").unwrap();
+        std::fs::write(root.join("assets/levels/readme.txt"), b"").unwrap();
+
+        let files = ["assets/Font-12.ttf", "assets/levels/*.bin"].map(String::from);
+        let assets = find_assets(&root, &files).unwrap();
+        let names = assets.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["_1_bin", "_2_bin", "font_12_ttf"]);
+        assert_eq!(assets[0].display, "assets/levels/1.bin");
+
+        // same file name in different directories
+        let files = ["assets/**/*.bin", "other/1.bin"].map(String::from);
+        std::fs::create_dir_all(root.join("other")).unwrap();
+        std::fs::write(root.join("other/1.bin"), b"").unwrap();
+        assert!(find_assets(&root, &files).is_err());
+
+        let mut config = Embed::default();
+        config.files = vec!["assets/*.ttf".to_string()];
+        let target = root.join("target");
+        let include = target.join("include");
+        std::fs::create_dir_all(&include).unwrap();
+        std::fs::create_dir_all(target.join("embed")).unwrap();
+        std::fs::write(target.join("embed/stale.s"), b"").unwrap();
+        let output = generate(&root, &config, &target, &include).unwrap();
+        let source = std::fs::read_to_string(output.src_dir.join("font_12_ttf.s")).unwrap();
+        assert!(source.contains(".incbin"));
+        assert!(source.contains("megaton_asset_font_12_ttf_size:"));
+        assert!(!target.join("embed/stale.s").exists());
+        let header = std::fs::read_to_string(include.join(HEADER_NAME)).unwrap();
+        assert!(header.contains("extern const uint8_t megaton_asset_font_12_ttf[];"));
+        let rust = std::fs::read_to_string(&output.rust_path).unwrap();
+        assert!(rust.contains("pub fn font_12_ttf() -> &'static [u8]"));
+
+        // changing the asset changes the generated source
+        std::fs::write(root.join("assets/Font-12.ttf"), b"font2").unwrap();
+        generate(&root, &config, &target, &include).unwrap();
+        let new_source = std::fs::read_to_string(output.src_dir.join("font_12_ttf.s")).unwrap();
+        assert_ne!(source, new_source);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn rust_keywords() {
+        let root =
+            std::env::temp_dir().join(format!("megaton-embed-keywords-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        for name in ["type", "Match", "fn.bin", "self", "-"] {
+            std::fs::write(root.join(name), b"").unwrap();
+        }
+        let files = ["type", "Match", "fn.bin"].map(String::from);
+        let assets = find_assets(&root, &files).unwrap();
+        let rust = rust_source(&assets);
+        assert!(rust.contains("pub fn fn_bin() -> &'static [u8]"));
+        assert!(rust.contains("pub fn r#match() -> &'static [u8]"));
+        assert!(rust.contains("pub fn r#type() -> &'static [u8]"));
+        assert!(rust.contains("static megaton_asset_type: [u8; 0];"));
+        for file in ["self", "-"] {
+            assert!(find_assets(&root, &[file.to_string()]).is_err(), "{file}");
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! The hermit syscalls (`sys_*`) that Rust `std` calls on the hermit target,
+//! and which of them are implemented by libmegaton
+
+/// A hermit syscall
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HermitSyscall {
+    /// Symbol name, like `sys_spawn2`
+    pub name: &'static str,
+    /// The `std` functionality that needs the syscall
+    pub used_by: &'static str,
+    /// How libmegaton supports the syscall
+    pub support: Support,
+}
+
+/// How libmegaton supports a hermit syscall
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Support {
+    /// Not defined by libmegaton
+    Unsupported,
+    /// Defined and implemented by libmegaton
+    Implemented,
+    /// Defined by libmegaton so the module links, but panics (and aborts) when called
+    Panics,
+}
+
+macro_rules! syscalls {
+    ($($name:literal => $used_by:literal $(, $support:ident)?;)*) => {
+        &[$(HermitSyscall {
+            name: $name,
+            used_by: $used_by,
+            support: syscalls!(@support $($support)?),
+        }),*]
+    };
+    (@support) => { Support::Unsupported };
+    (@support implemented) => { Support::Implemented };
+    (@support panics) => { Support::Panics };
+}
+
+/// The syscalls declared by `hermit-abi`, sorted by name
+static SYSCALLS: &[HermitSyscall] = syscalls! {
+    "sys_abort" => "std::process::abort", implemented;
+    "sys_accept" => "std::net::TcpListener::accept";
+    "sys_add_queue" => "std::thread::park";
+    "sys_alloc" => "the global allocator";
+    "sys_alloc_zeroed" => "the global allocator";
+    "sys_available_parallelism" => "std::thread::available_parallelism";
+    "sys_bind" => "std::net::TcpListener::bind and std::net::UdpSocket::bind";
+    "sys_block_current_task" => "std::thread::park";
+    "sys_block_current_task_with_timeout" => "std::thread::park_timeout";
+    "sys_clock_gettime" => "std::time::Instant and std::time::SystemTime";
+    "sys_close" => "closing files (std::fs::File)", implemented;
+    "sys_connect" => "std::net::TcpStream::connect";
+    "sys_dealloc" => "the global allocator";
+    "sys_destroy_queue" => "std::thread::park";
+    "sys_dup" => "std::fs::File::try_clone";
+    "sys_errno" => "std::io::Error::last_os_error";
+    "sys_eventfd" => "event file descriptors";
+    "sys_exit" => "std::process::exit";
+    "sys_fcntl" => "set_nonblocking on sockets";
+    "sys_free" => "the global allocator", implemented;
+    "sys_freeaddrinfo" => "std::net::ToSocketAddrs";
+    "sys_fstat" => "std::fs::File::metadata", implemented;
+    "sys_futex_wait" => "std::sync (Mutex, Condvar, Once, ...)", implemented;
+    "sys_futex_wake" => "std::sync (Mutex, Condvar, Once, ...)", implemented;
+    "sys_get_errno" => "std::io::Error::last_os_error";
+    "sys_get_priority" => "thread priorities";
+    "sys_get_processor_count" => "std::thread::available_parallelism";
+    "sys_getaddrbyname" => "std::net::ToSocketAddrs";
+    "sys_getaddrinfo" => "std::net::ToSocketAddrs";
+    "sys_getdents64" => "std::fs::read_dir";
+    "sys_getpagesize" => "memory mapping";
+    "sys_getpeername" => "std::net::TcpStream::peer_addr";
+    "sys_getpid" => "std::process::id";
+    "sys_getsockname" => "local_addr on sockets";
+    "sys_getsockopt" => "socket options (std::net)";
+    "sys_init_queue" => "std::thread::park";
+    "sys_ioctl" => "set_nonblocking on sockets";
+    "sys_join" => "std::thread::JoinHandle::join";
+    "sys_listen" => "std::net::TcpListener::bind";
+    "sys_lseek" => "std::io::Seek for std::fs::File";
+    "sys_lstat" => "std::fs::symlink_metadata";
+    "sys_malloc" => "the global allocator", implemented;
+    "sys_mkdir" => "std::fs::create_dir";
+    "sys_mmap" => "memory mapping";
+    "sys_mprotect" => "memory mapping";
+    "sys_munmap" => "memory mapping";
+    "sys_nanosleep" => "std::thread::sleep";
+    "sys_network_init" => "std::net";
+    "sys_notify" => "std::thread::Thread::unpark";
+    "sys_open" => "std::fs::File::open and std::fs::File::create", implemented;
+    "sys_opendir" => "std::fs::read_dir";
+    "sys_poll" => "timeouts on sockets (std::net)";
+    "sys_read" => "reading files and stdin (std::io::Read)", implemented;
+    "sys_read_entropy" => "random seeds, like the default hasher of std::collections::HashMap";
+    "sys_readv" => "std::io::Read::read_vectored";
+    "sys_realloc" => "the global allocator", implemented;
+    "sys_recv" => "receiving from sockets (std::net)";
+    "sys_recvfrom" => "std::net::UdpSocket::recv_from";
+    "sys_rmdir" => "std::fs::remove_dir";
+    "sys_sem_destroy" => "semaphores";
+    "sys_sem_init" => "semaphores";
+    "sys_sem_post" => "semaphores";
+    "sys_sem_timedwait" => "semaphores";
+    "sys_sem_trywait" => "semaphores";
+    "sys_send" => "sending to sockets (std::net)";
+    "sys_sendto" => "std::net::UdpSocket::send_to";
+    "sys_set_priority" => "thread priorities";
+    "sys_setsockopt" => "socket options (std::net)";
+    "sys_shutdown" => "std::net::TcpStream::shutdown";
+    "sys_shutdown_socket" => "std::net::TcpStream::shutdown";
+    "sys_socket" => "std::net";
+    "sys_spawn" => "std::thread::spawn";
+    "sys_spawn2" => "std::thread::spawn";
+    "sys_stat" => "std::fs::metadata", implemented;
+    "sys_unlink" => "std::fs::remove_file", implemented;
+    "sys_usleep" => "std::thread::sleep";
+    "sys_wait" => "waiting for threads";
+    // the link name in hermit-abi has a typo
+    "sys_wakeup_taskt" => "std::thread::Thread::unpark";
+    "sys_write" => "writing files, stdout and stderr (std::io::Write, println!)", implemented;
+    "sys_writev" => "std::io::Write::write_vectored", panics;
+    "sys_yield" => "std::thread::yield_now";
+};
+
+/// Get the hermit syscall by its symbol name
+pub fn syscall(name: &str) -> Option<&'static HermitSyscall> {
+    SYSCALLS
+        .binary_search_by_key(&name, |x| x.name)
+        .ok()
+        .map(|i| &SYSCALLS[i])
+}
+
+/// Explain why the hermit syscall is missing from the module
+pub fn explain_missing(name: &str) -> Option<String> {
+    let syscall = syscall(name)?;
+    let message = match syscall.support {
+        Support::Implemented => format!(
+            "{} is implemented by libmegaton, but libmegaton is not linked; make sure `megaton.custom-entry` is not set",
+            syscall.used_by
+        ),
+        Support::Panics => format!(
+            "{} is not supported by megaton yet, and aborts when called even if libmegaton is linked",
+            syscall.used_by
+        ),
+        Support::Unsupported => format!("{} is not supported by megaton yet", syscall.used_by),
+    };
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn table_is_sorted() {
+        assert!(SYSCALLS.windows(2).all(|x| x[0].name < x[1].name));
+        assert_eq!(
+            explain_missing("sys_spawn2").unwrap(),
+            "std::thread::spawn is not supported by megaton yet"
+        );
+        assert_eq!(
+            explain_missing("sys_writev").unwrap(),
+            "std::io::Write::write_vectored is not supported by megaton yet, and aborts when called even if libmegaton is linked"
+        );
+        assert_eq!(explain_missing("sys_foo"), None);
+    }
+
+    /// Find the `sys_*` functions defined in the sources of libmegaton
+    fn find_implemented(dir: &Path, out: &mut BTreeSet<String>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                find_implemented(&path, out);
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            for line in content.lines() {
+                // definitions are like `extern "C" ... sys_open(` or `pub fn sys_close(`
+                if !line.starts_with("extern \"C\"") && !line.starts_with("pub ") {
+                    continue;
+                }
+                let Some(start) = line.find("sys_") else {
+                    continue;
+                };
+                let name = &line[start..];
+                if let Some(end) = name.find('(') {
+                    out.insert(name[..end].to_string());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn implemented_matches_lib() {
+        let lib_src = Path::new(env!("CARGO_MANIFEST_DIR")).join("../lib/src");
+        let mut defined = BTreeSet::new();
+        find_implemented(&lib_src, &mut defined);
+        let implemented = SYSCALLS
+            .iter()
+            // syscalls that panic are also defined, so the module links
+            .filter(|x| x.support != Support::Unsupported)
+            .map(|x| x.name.to_string())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(implemented, defined);
+    }
+}
@@ -0,0 +1,537 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Checking the segments, sections and relocations of the module for things
+//! the loader (rtld) does not support
+
+use std::collections::BTreeMap;
+
+use object::elf;
+use object::read::elf::{ElfFile64, ProgramHeader as _};
+use object::{Endianness, Object as _, ObjectSection as _, RelocationFlags, SectionFlags};
+
+use crate::buildsys::link_map::LinkMap;
+
+/// Name and expected permissions of the loadable segments, from `PHDRS` in `link.ld`
+const SEGMENTS: [(&str, u32); 3] = [
+    ("text", elf::PF_R | elf::PF_X),
+    ("rodata", elf::PF_R),
+    ("data", elf::PF_R | elf::PF_W),
+];
+
+/// Relocation types rtld can apply
+const SUPPORTED_RELOCATIONS: [u32; 5] = [
+    elf::R_AARCH64_NONE,
+    elf::R_AARCH64_ABS64,
+    elf::R_AARCH64_GLOB_DAT,
+    elf::R_AARCH64_JUMP_SLOT,
+    elf::R_AARCH64_RELATIVE,
+];
+
+/// A problem in the layout of the module, with the object files that caused it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutProblem {
+    pub message: String,
+    pub files: Vec<String>,
+}
+
+impl std::fmt::Display for LayoutProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.files.is_empty() {
+            write!(f, " (from {})", self.files.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A loadable segment
+struct Segment {
+    name: &'static str,
+    start: u64,
+    end: u64,
+    flags: u32,
+}
+
+impl Segment {
+    fn contains(&self, start: u64, end: u64) -> bool {
+        self.start <= start && end <= self.end
+    }
+}
+
+/// Check the program headers, sections and dynamic relocations of the ELF
+///
+/// The link map is used to find the object files that caused the problems
+pub fn check_layout(data: &[u8], map: &LinkMap) -> cu::Result<Vec<LayoutProblem>> {
+    let file = ElfFile64::<Endianness>::parse(data).map_err(|e| cu::fmterr!("invalid ELF: {e}"))?;
+    let endian = file.endian();
+    if file.elf_header().e_machine.get(endian) != elf::EM_AARCH64 {
+        cu::bail!("not an AArch64 ELF");
+    }
+    let mut problems = vec![];
+    let mut problem = |message: String, files: Vec<&str>| {
+        problems.push(LayoutProblem {
+            message,
+            files: files.into_iter().map(String::from).collect(),
+        });
+    };
+
+    let headers = file.elf_program_headers();
+    let loads = headers
+        .iter()
+        .filter(|x| x.p_type(endian) == elf::PT_LOAD)
+        .collect::<Vec<_>>();
+    if loads.len() != SEGMENTS.len() {
+        problem(
+            format!(
+                "expected 3 loadable segments (text, rodata, data), found {}",
+                loads.len()
+            ),
+            vec![],
+        );
+    }
+    let segments = loads
+        .iter()
+        .enumerate()
+        .map(|(i, load)| {
+            let start = load.p_vaddr(endian);
+            Segment {
+                name: SEGMENTS.get(i).map(|x| x.0).unwrap_or("extra"),
+                start,
+                end: start + load.p_memsz(endian),
+                flags: load.p_flags(endian),
+            }
+        })
+        .collect::<Vec<_>>();
+    for (segment, (_, expected)) in segments.iter().zip(SEGMENTS) {
+        if segment.flags & elf::PF_W != 0 && segment.flags & elf::PF_X != 0 {
+            problem(
+                format!("{} segment is writable and executable", segment.name),
+                vec![],
+            );
+        } else if segment.flags != expected {
+            problem(
+                format!(
+                    "{} segment has permissions {}, expected {}",
+                    segment.name,
+                    permissions(segment.flags),
+                    permissions(expected)
+                ),
+                vec![],
+            );
+        }
+    }
+    match headers.iter().find(|x| x.p_type(endian) == elf::PT_DYNAMIC) {
+        None => problem("missing dynamic segment".to_string(), vec![]),
+        Some(dynamic) => {
+            let start = dynamic.p_vaddr(endian);
+            let end = start + dynamic.p_memsz(endian);
+            if !segments
+                .iter()
+                .any(|x| x.flags & elf::PF_W != 0 && x.contains(start, end))
+            {
+                problem(
+                    "dynamic segment is not in the data segment".to_string(),
+                    vec![],
+                );
+            }
+        }
+    }
+
+    for section in file.sections() {
+        let SectionFlags::Elf { sh_flags } = section.flags() else {
+            continue;
+        };
+        if sh_flags & u64::from(elf::SHF_ALLOC) == 0 || section.size() == 0 {
+            continue;
+        }
+        let Ok(name) = section.name() else {
+            continue;
+        };
+        let files = map.files_in(name);
+        if sh_flags & u64::from(elf::SHF_TLS) != 0 {
+            problem(
+                format!("thread-local storage ({name}) is not supported"),
+                files,
+            );
+            continue;
+        }
+        if matches!(name, ".ctors" | ".dtors") || name.starts_with(".ctors.") {
+            problem(
+                format!("{name} is not run by the loader; use .init_array or .fini_array"),
+                files,
+            );
+            continue;
+        }
+        let writable = sh_flags & u64::from(elf::SHF_WRITE) != 0;
+        let executable = sh_flags & u64::from(elf::SHF_EXECINSTR) != 0;
+        if writable && executable {
+            problem(format!("{name} is writable and executable"), files);
+            continue;
+        }
+        let start = section.address();
+        let end = start + section.size();
+        let Some(segment) = segments.iter().find(|x| x.contains(start, end)) else {
+            problem(format!("{name} is not in a loadable segment"), files);
+            continue;
+        };
+        if writable && segment.flags & elf::PF_W == 0 {
+            problem(
+                format!(
+                    "{name} is writable, but placed in the {} segment",
+                    segment.name
+                ),
+                files,
+            );
+        } else if executable && segment.flags & elf::PF_X == 0 {
+            problem(
+                format!(
+                    "{name} is executable, but placed in the {} segment",
+                    segment.name
+                ),
+                files,
+            );
+        }
+    }
+
+    // group the relocations by problem and object file, since there can be many
+    let mut relocation_problems = BTreeMap::<String, Vec<&str>>::new();
+    for (offset, relocation) in file.dynamic_relocations().into_iter().flatten() {
+        let RelocationFlags::Elf { r_type } = relocation.flags() else {
+            continue;
+        };
+        let section = file
+            .sections()
+            .find(|x| x.address() <= offset && offset < x.address() + x.size());
+        let section_name = section.as_ref().and_then(|x| x.name().ok());
+        let object_file = section_name.and_then(|x| map.section_at(x, offset));
+        let message = if !SUPPORTED_RELOCATIONS.contains(&r_type) {
+            format!("unsupported relocation {}", relocation_name(r_type))
+        } else if let Some(segment) = segments.iter().find(|x| x.contains(offset, offset + 1))
+            && segment.flags & elf::PF_W == 0
+        {
+            format!(
+                "relocation in read-only {}",
+                section_name.unwrap_or(segment.name)
+            )
+        } else {
+            continue;
+        };
+        let files = relocation_problems.entry(message).or_default();
+        if let Some(object_file) = object_file
+            && !files.contains(&object_file.file.as_str())
+        {
+            files.push(&object_file.file);
+        }
+    }
+    for (message, files) in relocation_problems {
+        problem(message, files);
+    }
+
+    Ok(problems)
+}
+
+fn permissions(flags: u32) -> String {
+    let mut out = String::new();
+    for (flag, c) in [(elf::PF_R, 'R'), (elf::PF_W, 'W'), (elf::PF_X, 'X')] {
+        if flags & flag != 0 {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn relocation_name(r_type: u32) -> String {
+    let name = match r_type {
+        elf::R_AARCH64_ABS32 => "R_AARCH64_ABS32",
+        elf::R_AARCH64_ABS16 => "R_AARCH64_ABS16",
+        elf::R_AARCH64_PREL64 => "R_AARCH64_PREL64",
+        elf::R_AARCH64_PREL32 => "R_AARCH64_PREL32",
+        elf::R_AARCH64_COPY => "R_AARCH64_COPY",
+        elf::R_AARCH64_TLS_DTPMOD => "R_AARCH64_TLS_DTPMOD",
+        elf::R_AARCH64_TLS_DTPREL => "R_AARCH64_TLS_DTPREL",
+        elf::R_AARCH64_TLS_TPREL => "R_AARCH64_TLS_TPREL",
+        elf::R_AARCH64_TLSDESC => "R_AARCH64_TLSDESC",
+        elf::R_AARCH64_IRELATIVE => "R_AARCH64_IRELATIVE",
+        _ => return format!("type {r_type}"),
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buildsys::link_map::InputSection;
+
+    /// A module linked with the Megaton linker script, see `testdata/module.s`
+    static MODULE_ELF: &[u8] = include_bytes!("testdata/module.elf");
+
+    const RX: u32 = elf::PF_R | elf::PF_X;
+    const RW: u32 = elf::PF_R | elf::PF_W;
+    const ALLOC: u32 = elf::SHF_ALLOC;
+
+    /// Make an ELF with 3 loadable segments of 0x1000 bytes at 0, 0x1000 and 0x2000,
+    /// a dynamic segment at the start of the third one, the given sections
+    /// and a `.rela.dyn` in the second one with the given relocations
+    ///
+    /// Sections are (name, type, flags, address, size), and their data is at
+    /// `0x1000 + address` in the file
+    fn make_elf(
+        segments: [u32; 3],
+        sections: &[(&str, u32, u32, u64, u64)],
+        relocations: &[(u64, u32)],
+    ) -> Vec<u8> {
+        let mut elf = vec![0u8; 0x4000];
+        let put = |elf: &mut Vec<u8>, offset: usize, bytes: &[u8]| {
+            if elf.len() < offset + bytes.len() {
+                elf.resize(offset + bytes.len(), 0);
+            }
+            elf[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        let mut sections = sections.to_vec();
+        let rela_size = relocations.len() as u64 * 24;
+        sections.push((".rela.dyn", elf::SHT_RELA, ALLOC, 0x1800, rela_size));
+        for (i, (offset, r_type)) in relocations.iter().enumerate() {
+            let entry = 0x2800 + i * 24;
+            put(&mut elf, entry, &offset.to_le_bytes());
+            put(&mut elf, entry + 8, &u64::from(*r_type).to_le_bytes());
+        }
+
+        // file header
+        let section_headers = 0x4000;
+        put(&mut elf, 0, b"\x7fELF\x02\x01\x01");
+        put(&mut elf, 0x10, &elf::ET_DYN.to_le_bytes());
+        put(&mut elf, 0x12, &elf::EM_AARCH64.to_le_bytes());
+        put(&mut elf, 0x14, &1u32.to_le_bytes());
+        put(&mut elf, 0x20, &0x40u64.to_le_bytes());
+        put(&mut elf, 0x28, &(section_headers as u64).to_le_bytes());
+        put(&mut elf, 0x34, &0x40u16.to_le_bytes());
+        put(&mut elf, 0x36, &56u16.to_le_bytes());
+        put(&mut elf, 0x38, &4u16.to_le_bytes());
+        put(&mut elf, 0x3A, &64u16.to_le_bytes());
+        put(&mut elf, 0x3C, &(sections.len() as u16 + 2).to_le_bytes());
+        put(&mut elf, 0x3E, &(sections.len() as u16 + 1).to_le_bytes());
+
+        // (type, flags, address, size)
+        let headers = [
+            (elf::PT_LOAD, segments[0], 0u64, 0x1000u64),
+            (elf::PT_LOAD, segments[1], 0x1000, 0x1000),
+            (elf::PT_LOAD, segments[2], 0x2000, 0x1000),
+            (elf::PT_DYNAMIC, RW, 0x2000, 0x10),
+        ];
+        for (i, (ty, flags, address, size)) in headers.iter().enumerate() {
+            let header = 0x40 + i * 56;
+            put(&mut elf, header, &ty.to_le_bytes());
+            put(&mut elf, header + 4, &flags.to_le_bytes());
+            put(&mut elf, header + 8, &(0x1000 + address).to_le_bytes());
+            put(&mut elf, header + 0x10, &address.to_le_bytes());
+            put(&mut elf, header + 0x20, &size.to_le_bytes());
+            put(&mut elf, header + 0x28, &size.to_le_bytes());
+        }
+
+        let mut names = b"\0.shstrtab\0".to_vec();
+        let string_table = section_headers + (sections.len() + 2) * 64;
+        for (i, (name, ty, flags, address, size)) in sections.iter().enumerate() {
+            let header = section_headers + (i + 1) * 64;
+            put(&mut elf, header, &(names.len() as u32).to_le_bytes());
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+            put(&mut elf, header + 4, &ty.to_le_bytes());
+            put(&mut elf, header + 8, &u64::from(*flags).to_le_bytes());
+            put(&mut elf, header + 0x10, &address.to_le_bytes());
+            put(&mut elf, header + 0x18, &(0x1000 + address).to_le_bytes());
+            put(&mut elf, header + 0x20, &size.to_le_bytes());
+            if *ty == elf::SHT_RELA {
+                put(&mut elf, header + 0x38, &24u64.to_le_bytes());
+            }
+        }
+        let header = section_headers + (sections.len() + 1) * 64;
+        put(&mut elf, header, &1u32.to_le_bytes());
+        put(&mut elf, header + 4, &elf::SHT_STRTAB.to_le_bytes());
+        put(
+            &mut elf,
+            header + 0x18,
+            &(string_table as u64).to_le_bytes(),
+        );
+        put(&mut elf, header + 0x20, &(names.len() as u64).to_le_bytes());
+        put(&mut elf, string_table, &names);
+        elf
+    }
+
+    /// The sections of a module without problems
+    fn sections() -> Vec<(&'static str, u32, u32, u64, u64)> {
+        vec![
+            (
+                ".text",
+                elf::SHT_PROGBITS,
+                ALLOC | elf::SHF_EXECINSTR,
+                0,
+                0x10,
+            ),
+            (".rodata", elf::SHT_PROGBITS, ALLOC, 0x1000, 0x10),
+            (
+                ".data",
+                elf::SHT_PROGBITS,
+                ALLOC | elf::SHF_WRITE,
+                0x2010,
+                0x10,
+            ),
+        ]
+    }
+
+    fn messages(problems: &[LayoutProblem]) -> Vec<&str> {
+        problems.iter().map(|x| x.message.as_str()).collect()
+    }
+
+    #[test]
+    fn module_elf() {
+        let problems = check_layout(MODULE_ELF, &LinkMap::default()).unwrap();
+        assert_eq!(problems, vec![]);
+    }
+
+    #[test]
+    fn clean_elf() {
+        let elf = make_elf(
+            [RX, elf::PF_R, RW],
+            &sections(),
+            &[(0x2010, elf::R_AARCH64_RELATIVE)],
+        );
+        let problems = check_layout(&elf, &LinkMap::default()).unwrap();
+        assert_eq!(problems, vec![]);
+    }
+
+    #[test]
+    fn writable_and_executable() {
+        let mut sections = sections();
+        sections.push((
+            ".rwx",
+            elf::SHT_PROGBITS,
+            ALLOC | elf::SHF_WRITE | elf::SHF_EXECINSTR,
+            0x20,
+            0x10,
+        ));
+        let elf = make_elf([RX | elf::PF_W, elf::PF_R, elf::PF_R], &sections, &[]);
+        let problems = check_layout(&elf, &LinkMap::default()).unwrap();
+        assert_eq!(
+            messages(&problems),
+            [
+                "text segment is writable and executable",
+                "data segment has permissions R, expected RW",
+                "dynamic segment is not in the data segment",
+                ".data is writable, but placed in the data segment",
+                ".rwx is writable and executable",
+            ][..]
+        );
+    }
+
+    #[test]
+    fn sections_in_wrong_places() {
+        let mut sections = sections();
+        sections.extend([
+            (
+                ".tdata",
+                elf::SHT_PROGBITS,
+                ALLOC | elf::SHF_WRITE | elf::SHF_TLS,
+                0x2020,
+                0x8,
+            ),
+            (
+                ".ctors",
+                elf::SHT_PROGBITS,
+                ALLOC | elf::SHF_WRITE,
+                0x2028,
+                0x8,
+            ),
+            (
+                ".bss.late",
+                elf::SHT_NOBITS,
+                ALLOC | elf::SHF_WRITE,
+                0x3000,
+                0x8,
+            ),
+            (
+                ".text.cold",
+                elf::SHT_PROGBITS,
+                ALLOC | elf::SHF_EXECINSTR,
+                0x1010,
+                0x10,
+            ),
+        ]);
+        let elf = make_elf([RX, elf::PF_R, RW], &sections, &[]);
+        let map = LinkMap {
+            sections: vec![InputSection {
+                output: ".tdata".to_string(),
+                name: ".tdata.counter".to_string(),
+                address: 0x2020,
+                size: 0x8,
+                file: "build/counter.o".to_string(),
+            }],
+        };
+        let problems = check_layout(&elf, &map).unwrap();
+        assert_eq!(
+            messages(&problems),
+            [
+                "thread-local storage (.tdata) is not supported",
+                ".ctors is not run by the loader; use .init_array or .fini_array",
+                ".bss.late is not in a loadable segment",
+                ".text.cold is executable, but placed in the rodata segment",
+            ][..]
+        );
+        assert_eq!(problems[0].files, ["build/counter.o"]);
+        assert!(problems[1].files.is_empty());
+    }
+
+    #[test]
+    fn relocations() {
+        let elf = make_elf(
+            [RX, elf::PF_R, RW],
+            &sections(),
+            &[
+                (0x2010, elf::R_AARCH64_ABS32),
+                (0x2018, elf::R_AARCH64_ABS32),
+                (0x1008, elf::R_AARCH64_RELATIVE),
+                (0x2010, elf::R_AARCH64_RELATIVE),
+            ],
+        );
+        let map = LinkMap {
+            sections: vec![
+                InputSection {
+                    output: ".data".to_string(),
+                    name: ".data.a".to_string(),
+                    address: 0x2010,
+                    size: 0x8,
+                    file: "build/a.o".to_string(),
+                },
+                InputSection {
+                    output: ".data".to_string(),
+                    name: ".data.b".to_string(),
+                    address: 0x2018,
+                    size: 0x8,
+                    file: "build/b.o".to_string(),
+                },
+            ],
+        };
+        let problems = check_layout(&elf, &map).unwrap();
+        assert_eq!(
+            problems,
+            vec![
+                LayoutProblem {
+                    message: "relocation in read-only .rodata".to_string(),
+                    files: vec![],
+                },
+                LayoutProblem {
+                    message: "unsupported relocation R_AARCH64_ABS32".to_string(),
+                    files: vec!["build/a.o".to_string(), "build/b.o".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn not_aarch64() {
+        let mut elf = make_elf([RX, elf::PF_R, RW], &sections(), &[]);
+        elf[0x12..0x14].copy_from_slice(&elf::EM_X86_64.to_le_bytes());
+        assert!(check_layout(&elf, &LinkMap::default()).is_err());
+        assert!(check_layout(b"not an elf", &LinkMap::default()).is_err());
+    }
+}
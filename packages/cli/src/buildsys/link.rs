@@ -5,7 +5,10 @@
 use std::path::{Path, PathBuf};
 
 use cu::pre::*;
+use object::{Object as _, ObjectSymbol as _, SymbolKind};
 
+use crate::buildsys::Nso;
+use crate::buildsys::message::{self, Message};
 use crate::env;
 
 /// Link a list of artifacts into an elf file.
@@ -43,13 +46,30 @@ pub async fn build_elf(
         }
     }
 
-    link_cmd.execute().await?;
+    if message::is_json() {
+        link_cmd.execute_json(out_path).await?;
+    } else {
+        link_cmd.execute().await?;
+    }
     link_cmd.save(link_cmd_path)?;
     cu::debug!("Link: built elf {}", out_path.display());
 
     Ok(true)
 }
 
+/// Load the object files and static libraries of the last link from the link command cache
+pub fn load_link_inputs(link_cmd_path: &Path) -> cu::Result<Vec<PathBuf>> {
+    let link_cmd = LinkCmd::try_load(link_cmd_path)?;
+    // objects and static libs are passed before the flags
+    let inputs = link_cmd
+        .args
+        .into_iter()
+        .take_while(|arg| !arg.starts_with('-'))
+        .map(PathBuf::from)
+        .collect();
+    Ok(inputs)
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
 struct LinkCmd {
     pub linker: PathBuf,
@@ -86,24 +106,87 @@ impl LinkCmd {
         spinner.done();
         Ok(())
     }
+
+    /// Execute the link command, and emit the output of the linker as a message on failure
+    async fn execute_json(&self, out_path: &Path) -> cu::Result<()> {
+        let (child, _, output) = self
+            .linker
+            .command()
+            .args(&self.args)
+            .stdin_null()
+            .stdout(cu::lv::D)
+            .stderr(cu::pio::string())
+            .co_spawn()
+            .await?;
+        let status = child.co_wait().await?;
+        let output = output.co_join().await??;
+        if !status.success() {
+            message::emit(Message::LinkFailed {
+                elf: out_path,
+                output,
+            });
+            cu::bail!("failed to link {}: {status}", out_path.display());
+        }
+        Ok(())
+    }
 }
 
-pub async fn build_nso(elf_path: &Path, nso_path: &Path) -> cu::Result<()> {
-    let elf2nso = env::get().elf2nso();
-    let res = elf2nso
-        .command()
-        .args([elf_path, nso_path])
-        .stdin_null()
-        .stdout(cu::lv::D)
-        .stderr(cu::lv::E)
-        .co_wait_nz()
-        .await;
+/// Check that the objects do not use thread-local variables, when compiling with clang.
+///
+/// GCC from devkitA64 reads the thread pointer with `-mtp=soft`, which has no equivalent
+/// in clang: `tpidr_el0` is not set up by the OS, and `tpidrro_el0` points to the
+/// thread-local region instead of the TLS block, so the code would compile but be wrong
+pub fn check_no_tls(objects: &[PathBuf]) -> cu::Result<()> {
+    let mut found = vec![];
+    for path in objects {
+        let data = cu::fs::read(path)?;
+        let file = cu::check!(
+            object::File::parse(data.as_slice()),
+            "failed to parse object {}",
+            path.display()
+        )?;
+        for symbol in file.symbols() {
+            if symbol.kind() == SymbolKind::Tls
+                && let Ok(name) = symbol.name()
+            {
+                found.push(format!("{name} (in {})", path.display()));
+            }
+        }
+    }
+    if found.is_empty() {
+        return Ok(());
+    }
+    for x in &found {
+        cu::error!("thread-local variable: {x}");
+    }
+    cu::hint!(
+        "thread-local variables (thread_local, _Thread_local, __thread) in C/C++ are not supported with clang"
+    );
+    cu::hint!("- compile with gcc (MEGATON_TOOLCHAIN=gcc), which uses -mtp=soft");
+    cu::bail!("found {} thread-local variable(s)", found.len());
+}
+
+pub fn build_nso(elf_path: &Path, nso_path: &Path) -> cu::Result<()> {
+    let elf = cu::fs::read(elf_path)?;
+    let nso =
+        Nso::from_elf(&elf).context(format!("failed to convert {} to NSO", elf_path.display()))?;
+    cu::fs::write(nso_path, nso.to_bytes())?;
     cu::debug!("Link: converted to nso {}", nso_path.display());
     cu::info!("Created NSO: {}", nso_path.try_to_rel().display());
-    res
+    Ok(())
 }
 
-// #[cfg(test)]
-// mod tests {
-//
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_tls() {
+        let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/buildsys/testdata");
+        assert!(check_no_tls(&[testdata.join("module.elf")]).is_ok());
+        let error = check_no_tls(&[testdata.join("module.elf"), testdata.join("tls.o")])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "found 1 thread-local variable(s)");
+    }
+}
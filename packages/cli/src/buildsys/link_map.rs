@@ -0,0 +1,312 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Parsing the link map (`-Wl,-Map`) generated by `ld` or `lld`
+
+use std::path::Path;
+
+/// An input section from an object file, placed in an output section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputSection {
+    /// Name of the output section, like `.text`
+    pub output: String,
+    /// Name of the input section, like `.text._ZN3foo3barEv`
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    /// Path of the object file, relative to the root of the project if possible.
+    /// Archive members are like `lib.a(member.o)`
+    pub file: String,
+}
+
+impl InputSection {
+    pub fn contains(&self, address: u64) -> bool {
+        self.address <= address && address < self.address + self.size
+    }
+}
+
+/// The input sections in the link map
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkMap {
+    pub sections: Vec<InputSection>,
+}
+
+impl LinkMap {
+    /// Parse the link map. Object paths are made relative to the root of the project
+    pub fn parse(content: &str, root: &Path) -> Self {
+        match Self::parse_lld(content, root) {
+            Some(map) => map,
+            None => Self::parse_ld(content, root),
+        }
+    }
+
+    /// Parse the link map generated by GNU `ld`
+    fn parse_ld(content: &str, root: &Path) -> Self {
+        let mut sections = vec![];
+        // discarded sections are listed before the memory map
+        let lines = content
+            .lines()
+            .skip_while(|line| !line.starts_with("Linker script and memory map"));
+        let mut output = "";
+        // an input section with a long name has the rest on the next line
+        let mut pending_name = None;
+        for line in lines {
+            if line.is_empty() {
+                continue;
+            }
+            if !line.starts_with(' ') {
+                // output section, or other commands like LOAD
+                output = line.split_whitespace().next().unwrap_or_default();
+                pending_name = None;
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let is_input_section = !line.starts_with("  ") && !line.starts_with(" *");
+            let name = if is_input_section {
+                parts.next().unwrap_or_default()
+            } else {
+                match pending_name.take() {
+                    Some(name) => name,
+                    // symbols and patterns
+                    None => continue,
+                }
+            };
+            let (Some(address), Some(size)) = (parts.next(), parts.next()) else {
+                if is_input_section {
+                    pending_name = Some(name);
+                }
+                continue;
+            };
+            let file = parts.collect::<Vec<_>>().join(" ");
+            let (Some(address), Some(size)) = (parse_hex(address), parse_hex(size)) else {
+                continue;
+            };
+            if size == 0 || file.is_empty() {
+                continue;
+            }
+            sections.push(InputSection {
+                output: output.to_string(),
+                name: name.to_string(),
+                address,
+                size,
+                file: shorten_object_path(&file, root),
+            });
+        }
+        Self { sections }
+    }
+
+    /// Parse the link map generated by `lld`, which is a table of
+    /// `VMA LMA Size Align Out In Symbol`. Returns `None` if the content is not in this format
+    ///
+    /// Output sections, input sections and symbols are in the `Out`, `In` and `Symbol`
+    /// columns. Input sections are like `path/to/a.o:(.text.foo)`
+    fn parse_lld(content: &str, root: &Path) -> Option<Self> {
+        let mut lines = content.lines();
+        let header = lines.next()?;
+        let columns = header.split_whitespace().collect::<Vec<_>>();
+        if columns != ["VMA", "LMA", "Size", "Align", "Out", "In", "Symbol"] {
+            return None;
+        }
+        let out_column = header.find(" Out ")? + 1;
+        let in_column = header.find(" In ")? + 1;
+        let mut sections = vec![];
+        let mut output = "";
+        for line in lines {
+            let (Some(numbers), Some(rest)) = (line.get(..out_column), line.get(out_column..))
+            else {
+                continue;
+            };
+            let mut numbers = numbers.split_whitespace();
+            let (Some(address), Some(size)) = (numbers.next(), numbers.nth(1)) else {
+                continue;
+            };
+            let entry = rest.trim_start();
+            if rest.len() == entry.len() {
+                // output section, or assignments like `. = ALIGN(8)`
+                output = entry;
+                continue;
+            }
+            if out_column + rest.len() - entry.len() != in_column {
+                // symbols
+                continue;
+            }
+            // sections made by the linker are `<internal>:(.got)`
+            let Some((file, name)) = entry.rsplit_once(":(") else {
+                continue;
+            };
+            let (Some(name), Some(address), Some(size)) =
+                (name.strip_suffix(')'), parse_hex(address), parse_hex(size))
+            else {
+                continue;
+            };
+            if size == 0 || file.starts_with('<') {
+                continue;
+            }
+            sections.push(InputSection {
+                output: output.to_string(),
+                name: name.to_string(),
+                address,
+                size,
+                file: shorten_object_path(file, root),
+            });
+        }
+        Some(Self { sections })
+    }
+
+    /// Get the input section at the address in the output section
+    pub fn section_at(&self, output: &str, address: u64) -> Option<&InputSection> {
+        self.sections
+            .iter()
+            .find(|x| x.output == output && x.contains(address))
+    }
+
+    /// Get the object files that have input sections in the output section, without duplicates
+    pub fn files_in(&self, output: &str) -> Vec<&str> {
+        let mut files = vec![];
+        for section in self.sections.iter().filter(|x| x.output == output) {
+            if !files.contains(&section.file.as_str()) {
+                files.push(section.file.as_str());
+            }
+        }
+        files
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+}
+
+/// Make object paths relative to the root, including archive members like `lib.a(member.o)`
+pub fn shorten_object_path(path: &str, root: &Path) -> String {
+    let (archive, member) = match path.strip_suffix(')').and_then(|x| x.split_once('(')) {
+        Some((archive, member)) => (archive, Some(member)),
+        None => (path, None),
+    };
+    let archive = Path::new(archive);
+    let archive = archive
+        .strip_prefix(root)
+        .unwrap_or(archive)
+        .display()
+        .to_string();
+    match member {
+        Some(member) => format!("{archive}({member})"),
+        None => archive,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_map() {
+        let map = r#"Discarded input sections
+
+ .text.unused   0x0000000000000000       0x40 /mod/o/a.o
+
+Linker script and memory map
+
+LOAD /mod/o/a.o
+.text           0x0000000000000000      0x100
+ *(.text .text.*)
+ .text          0x0000000000000000       0x20 /mod/o/a.o
+                0x0000000000000000                main
+ .text._ZN3foo3barEv
+                0x0000000000000020       0x30 /mod/o/b.o
+ .text          0x0000000000000050       0x10 /mod/o/a.o
+ *fill*         0x0000000000000060       0x10
+ .text          0x0000000000000070       0x90 /mod/libfoo.a(foo.o)
+
+.comment        0x0000000000000000       0x12
+ .comment       0x0000000000000000       0x12 /mod/o/a.o
+
+.bss            0x0000000000000100       0x10
+ COMMON         0x0000000000000100       0x10 /mod/o/b.o
+"#;
+        let map = LinkMap::parse(map, Path::new("/mod"));
+        let sections = map
+            .sections
+            .iter()
+            .map(|x| {
+                (
+                    x.output.as_str(),
+                    x.name.as_str(),
+                    x.address,
+                    x.file.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            vec![
+                (".text", ".text", 0, "o/a.o"),
+                (".text", ".text._ZN3foo3barEv", 0x20, "o/b.o"),
+                (".text", ".text", 0x50, "o/a.o"),
+                (".text", ".text", 0x70, "libfoo.a(foo.o)"),
+                (".comment", ".comment", 0, "o/a.o"),
+                (".bss", "COMMON", 0x100, "o/b.o"),
+            ]
+        );
+        assert_eq!(map.section_at(".text", 0x4f).unwrap().file, "o/b.o");
+        assert_eq!(map.section_at(".text", 0x60), None);
+        assert_eq!(
+            map.files_in(".text"),
+            vec!["o/a.o", "o/b.o", "libfoo.a(foo.o)"]
+        );
+    }
+
+    /// The map of `testdata/module.elf`, linked with `lld -Map=module.map`
+    static MODULE_MAP: &str = include_str!("testdata/module.map");
+
+    #[test]
+    fn parse_lld_map() {
+        let map = LinkMap::parse(MODULE_MAP, Path::new("/mod"));
+        let sections = map
+            .sections
+            .iter()
+            .map(|x| {
+                (
+                    x.output.as_str(),
+                    x.name.as_str(),
+                    x.address,
+                    x.size,
+                    x.file.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            vec![
+                (".text", ".text.crt0", 0, 4, "module.o"),
+                (".text", ".text", 4, 0x1c, "module.o"),
+                (
+                    ".nx-module-name",
+                    ".nx-module-name",
+                    0x1000,
+                    0x11,
+                    "module.o"
+                ),
+                (".rodata", ".rodata.message", 0x1018, 0x13, "module.o"),
+                (".data", ".data", 0x2000, 0x10, "module.o"),
+                (".bss", ".bss", 0x3000, 0x2345, "module.o"),
+            ]
+        );
+        assert_eq!(map.section_at(".text", 0x10).unwrap().name, ".text");
+
+        let map = r#"             VMA              LMA     Size Align Out     In      Symbol
+               0                0       a0     4 .text
+               0                0       20     4         /mod/o/a.o:(.text)
+               0                0        0     1                 main
+              20               20       80     4         /mod/libfoo.a(foo.o):(.text._ZN3foo3barEv)
+              a0               a0       10     8 .got
+              a0               a0       10     8         <internal>:(.got)
+"#;
+        let map = LinkMap::parse(map, Path::new("/mod"));
+        assert_eq!(map.files_in(".text"), vec!["o/a.o", "libfoo.a(foo.o)"]);
+        assert_eq!(
+            map.section_at(".text", 0x9f).unwrap().name,
+            ".text._ZN3foo3barEv"
+        );
+        assert_eq!(map.files_in(".got"), Vec::<&str>::new());
+    }
+}
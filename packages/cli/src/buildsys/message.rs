@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Machine-readable build messages, enabled with `--message-format json`
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use cu::pre::*;
+
+static JSON_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Format of the output of the build
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MessageFormat {
+    /// Human-readable log lines
+    #[default]
+    Human,
+    /// One JSON object per line on stdout. Logs are disabled
+    Json,
+}
+
+/// Set the message format for the rest of the process
+pub fn set_message_format(format: MessageFormat) {
+    JSON_ENABLED.store(format == MessageFormat::Json, Ordering::Relaxed);
+}
+
+/// Check if JSON messages should be emitted
+#[inline]
+pub fn is_json() -> bool {
+    JSON_ENABLED.load(Ordering::Relaxed)
+}
+
+/// A message emitted during the build. The kind of the message is in the `reason` field
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum Message<'a> {
+    /// The config is loaded and the profile is resolved
+    ConfigLoaded {
+        manifest: &'a Path,
+        profile: &'a str,
+        module: &'a str,
+        title_id: String,
+    },
+    /// A source started compiling
+    CompileStarted { source: &'a Path, object: &'a Path },
+    /// A source finished compiling. Diagnostics can contain warnings
+    CompileFinished {
+        source: &'a Path,
+        object: &'a Path,
+        diagnostics: Vec<json::Value>,
+        output: String,
+    },
+    /// A source failed to compile
+    CompileFailed {
+        source: &'a Path,
+        diagnostics: Vec<json::Value>,
+        output: String,
+    },
+    /// A message from `cargo --message-format=json`, as-is
+    Cargo { message: json::Value },
+    /// The ELF is linked, or is already up to date if `linked` is false
+    Link { elf: &'a Path, linked: bool },
+    /// The ELF failed to link, with the output of the linker
+    LinkFailed { elf: &'a Path, output: String },
+    /// Result of checking the ELF
+    Check {
+        elf: &'a Path,
+        success: bool,
+        missing_symbols: &'a [String],
+        disallowed_instructions: &'a [String],
+        layout_problems: &'a [String],
+        /// Worst-case stack usage, `None` if there is no stack usage information
+        stack_usage: Option<u64>,
+        max_stack: u64,
+    },
+    /// The build is done
+    BuildFinished {
+        success: bool,
+        nso: Option<&'a Path>,
+        npdm: Option<&'a Path>,
+        error: Option<String>,
+    },
+}
+
+/// Print the message to stdout if JSON messages are enabled
+pub fn emit(message: Message<'_>) {
+    if !is_json() {
+        return;
+    }
+    match json::stringify(&message) {
+        Ok(line) => println!("{line}"),
+        Err(e) => cu::debug!("failed to serialize build message: {e:?}"),
+    }
+}
+
+/// Emit a failed `build-finished` message, for errors before the build starts
+pub fn emit_build_failed(format: MessageFormat, error: &cu::Error) {
+    set_message_format(format);
+    emit(Message::BuildFinished {
+        success: false,
+        nso: None,
+        npdm: None,
+        error: Some(format!("{error:#}")),
+    });
+}
+
+/// Parse the output of gcc with `-fdiagnostics-format=json`
+///
+/// Returns the diagnostics and the rest of the output that is not JSON
+pub fn parse_gcc_diagnostics(output: &str) -> (Vec<json::Value>, String) {
+    let mut diagnostics = vec![];
+    let mut rest = String::new();
+    for line in output.lines() {
+        if line.starts_with('[')
+            && let Ok(json::Value::Array(values)) = json::parse::<json::Value>(line)
+        {
+            diagnostics.extend(values);
+            continue;
+        }
+        rest.push_str(line);
+        rest.push('\n');
+    }
+    (diagnostics, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_diagnostics() {
+        let output = r#"In file included from main.cpp:1:
+[{"kind": "warning", "message": "unused variable 'x'", "option": "-Wunused-variable", "children": [], "locations": [{"caret": {"file": "main.cpp", "line": 3, "column": 9}}]}]
+"#;
+        let (diagnostics, rest) = parse_gcc_diagnostics(output);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["kind"], "warning");
+        assert_eq!(diagnostics[0]["locations"][0]["caret"]["line"], 3);
+        assert_eq!(rest, "In file included from main.cpp:1:\n");
+    }
+}
@@ -5,26 +5,18 @@ use std::path::Path;
 
 use cu::pre::*;
 
-use crate::env;
+use crate::buildsys::npdm;
+use crate::config::Npdm;
 
-pub async fn make_npdm(output_dir: &Path, title_id_hex: &str) -> cu::Result<()> {
-    let mut npdm_data: json::Value = json::parse(include_str!("../../template.npdm.json"))?;
-    npdm_data["title_id"] = json!(format!("0x{}", title_id_hex));
-
-    let main_npdm_json = output_dir.join("main.npdm.json");
+pub fn make_npdm(output_dir: &Path, npdm: &Npdm, title_id: u64) -> cu::Result<()> {
     let main_npdm = output_dir.join("main.npdm");
-
-    cu::fs::co_write_json_pretty(&main_npdm_json, &npdm_data).await?;
-
-    env::get()
-        .npdmtool()
-        .command()
-        .add(cu::args![&main_npdm_json, &main_npdm])
-        .all_null()
-        .co_wait_nz()
-        .await?;
-
-    cu::debug!("created npdm: {}", main_npdm.try_to_rel().display());
+    npdm.validate_resolved()?;
+    let npdm_data = npdm::build_npdm(npdm, title_id);
+    if write_if_changed(&main_npdm, &npdm_data)? {
+        cu::debug!("created npdm: {}", main_npdm.try_to_rel().display());
+    } else {
+        cu::debug!("npdm up to date");
+    }
     Ok(())
 }
 
@@ -40,7 +32,35 @@ pub fn make_verfile(path: &Path, entry: &str) -> cu::Result<()> {
     Ok(())
 }
 
-fn write_if_changed(path: &Path, bytes: &[u8]) -> cu::Result<bool> {
+/// Generate the assembly source for the `.nx-module-name` section, which has the name
+/// of the module for crash reports. The linker of devkitA64 adds it with
+/// `--nx-module-name`, but `lld` does not have the option
+pub fn make_module_name(path: &Path, name: &str) -> cu::Result<()> {
+    if write_if_changed(path, module_name_source(name).as_bytes())? {
+        cu::debug!("Cmd_build: updated module name source");
+    } else {
+        cu::debug!("Cmd_build: module name source up to date");
+    }
+    Ok(())
+}
+
+fn module_name_source(name: &str) -> String {
+    // module names only have alphanumeric characters, - and _, so no escaping is needed.
+    // "R" keeps the section with --gc-sections
+    format!(
+        r#"/* Generated by megaton, do not edit */
+    .section .nx-module-name, "aR", %progbits
+    .word 0
+    .word {len}
+    .asciz "{name}"
+
+    .section .note.GNU-stack, "", %progbits
+"#,
+        len = name.len(),
+    )
+}
+
+pub fn write_if_changed(path: &Path, bytes: &[u8]) -> cu::Result<bool> {
     let changed = match cu::fs::read(path) {
         Ok(existing) => existing != bytes,
         Err(_) => true,
@@ -50,3 +70,23 @@ fn write_if_changed(path: &Path, bytes: &[u8]) -> cu::Result<bool> {
     }
     Ok(changed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_name() {
+        assert_eq!(
+            module_name_source("test_mod"),
+            r#"/* Generated by megaton, do not edit */
+    .section .nx-module-name, "aR", %progbits
+    .word 0
+    .word 8
+    .asciz "test_mod"
+
+    .section .note.GNU-stack, "", %progbits
+"#
+        );
+    }
+}
@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Explaining missing symbols: which inputs of the link reference them,
+//! and how they can be fixed
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use object::read::archive::ArchiveFile;
+use object::{Object as _, ObjectSymbol as _};
+
+use crate::buildsys::hermit;
+use crate::buildsys::link_map::shorten_object_path;
+use crate::buildsys::size::demangle;
+
+/// Group for symbols not referenced by any object file or static library,
+/// for example from libraries linked with `-l`
+const UNKNOWN_GROUP: &str = "other inputs";
+
+/// Map the missing symbols to the object files and Rust crates referencing them
+///
+/// The inputs are the object files and static libraries passed to the linker.
+/// Returns the referenced symbols for each group, like `o/main.cpp.o`, `lib.a(foo.o)`
+/// or `crate std`. Symbols not found in any input are put in a separate group
+pub fn find_references(
+    inputs: &[PathBuf],
+    root: &Path,
+    missing_symbols: &[String],
+) -> BTreeMap<String, BTreeSet<String>> {
+    let missing = missing_symbols
+        .iter()
+        .map(String::as_str)
+        .collect::<BTreeSet<_>>();
+    let mut groups = BTreeMap::<String, BTreeSet<String>>::new();
+    for input in inputs {
+        let data = match cu::fs::read(input) {
+            Ok(data) => data,
+            Err(e) => {
+                cu::debug!("Check: cannot read link input {}: {e}", input.display());
+                continue;
+            }
+        };
+        let path = shorten_object_path(&input.display().to_string(), root);
+        match ArchiveFile::parse(data.as_slice()) {
+            Ok(archive) => {
+                for member in archive.members().flatten() {
+                    let name = String::from_utf8_lossy(member.name());
+                    let group = match rust_crate_of_member(&name) {
+                        Some(krate) => format!("crate {krate}"),
+                        None => format!("{path}({name})"),
+                    };
+                    if let Ok(member_data) = member.data(data.as_slice()) {
+                        add_references(&mut groups, group, member_data, &missing);
+                    }
+                }
+            }
+            Err(_) => add_references(&mut groups, path, &data, &missing),
+        }
+    }
+    let referenced = groups.values().flatten().cloned().collect::<BTreeSet<_>>();
+    let unreferenced = missing
+        .iter()
+        .filter(|x| !referenced.contains(**x))
+        .map(|x| x.to_string())
+        .collect::<BTreeSet<_>>();
+    if !unreferenced.is_empty() {
+        groups.insert(UNKNOWN_GROUP.to_string(), unreferenced);
+    }
+    groups
+}
+
+fn add_references(
+    groups: &mut BTreeMap<String, BTreeSet<String>>,
+    group: String,
+    data: &[u8],
+    missing: &BTreeSet<&str>,
+) {
+    let Ok(file) = object::File::parse(data) else {
+        return;
+    };
+    let symbols = file
+        .symbols()
+        .filter(|x| x.is_undefined())
+        .filter_map(|x| x.name().ok())
+        .filter(|x| missing.contains(x))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    if !symbols.is_empty() {
+        groups.entry(group).or_default().extend(symbols);
+    }
+}
+
+/// Get the crate of an archive member generated by rustc,
+/// like `core-0123456789abcdef.core.a1b2c3d4-cgu.0.rcgu.o`
+fn rust_crate_of_member(member: &str) -> Option<&str> {
+    if !member.ends_with(".rcgu.o") {
+        return None;
+    }
+    let (krate, _) = member.split_once('-')?;
+    Some(krate)
+}
+
+/// Format the missing symbols grouped by where they are referenced,
+/// with demangled names and a hint for how to fix them if possible
+pub fn format_report(groups: &BTreeMap<String, BTreeSet<String>>) -> String {
+    let mut out = String::new();
+    for (group, symbols) in groups {
+        out.push_str(&format!("  referenced by {group}:\n"));
+        for symbol in symbols {
+            out.push_str(&format!("    {}\n", demangle(symbol)));
+            if let Some(hint) = hint(symbol) {
+                out.push_str(&format!("      hint: {hint}\n"));
+            }
+        }
+    }
+    out
+}
+
+/// Suggest a fix for the missing symbol
+fn hint(symbol: &str) -> Option<String> {
+    if symbol.starts_with("sys_") {
+        let hint = hermit::explain_missing(symbol).unwrap_or_else(|| {
+            "this is a hermit `sys_*` syscall not implemented by libmegaton".to_string()
+        });
+        return Some(hint);
+    }
+    if symbol.starts_with("__aarch64_") {
+        return Some(
+            "this is an outline atomics helper from libgcc; compile with `-mno-outline-atomics`"
+                .to_string(),
+        );
+    }
+    if symbol == "__tls_get_addr" {
+        return Some("thread-local storage is not supported".to_string());
+    }
+    if matches!(
+        symbol,
+        "__cxa_throw" | "__cxa_allocate_exception" | "__cxa_begin_catch" | "__cxa_end_catch"
+    ) || symbol.starts_with("_Unwind_")
+    {
+        return Some(
+            "C++ exceptions are not supported; compile with `-fno-exceptions`".to_string(),
+        );
+    }
+    if symbol.starts_with("_ZN2nn") {
+        return Some(
+            "this is an SDK function; add symbols of the game with `check.symbols` (see `megaton symbols extract`)"
+                .to_string(),
+        );
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        assert_eq!(
+            rust_crate_of_member("std-0123456789abcdef.std.a1b2c3d4-cgu.0.rcgu.o"),
+            Some("std")
+        );
+        assert_eq!(rust_crate_of_member("foo.cpp.o"), None);
+
+        let mut groups = BTreeMap::new();
+        groups.insert(
+            "crate std".to_string(),
+            BTreeSet::from(["sys_spawn2".to_string(), "sys_foo".to_string()]),
+        );
+        groups.insert(
+            "o/main.cpp.o".to_string(),
+            BTreeSet::from([
+                "_ZN2nn2os11SleepThreadENS_8TimeSpanE".to_string(),
+                "foo".to_string(),
+            ]),
+        );
+        assert_eq!(
+            format_report(&groups),
+            "  referenced by crate std:
+    sys_foo
+      hint: this is a hermit `sys_*` syscall not implemented by libmegaton
+    sys_spawn2
+      hint: std::thread::spawn is not supported by megaton yet
+  referenced by o/main.cpp.o:
+    nn::os::SleepThread(nn::TimeSpan)
+      hint: this is an SDK function; add symbols of the game with `check.symbols` (see `megaton symbols extract`)
+    foo
+"
+        );
+    }
+}
@@ -5,11 +5,32 @@ mod args;
 pub use args::*;
 mod driver;
 pub use driver::*;
+mod clean;
+pub use clean::*;
+mod package;
+pub use package::*;
+mod message;
+mod timings;
+pub use message::{MessageFormat, emit_build_failed};
 
 mod check;
+pub use check::check_elf;
+mod disasm;
+mod embed;
+pub use embed::asset_watch_paths;
+mod nso;
+pub use nso::*;
+mod size;
+mod stack;
+pub use size::{SizeFormat, size_module};
 mod compile;
+mod hermit;
+mod layout;
 mod lib_unpack;
 mod link;
+mod link_map;
+mod missing;
 mod rust;
 use lib_unpack::unpack_megaton_lib;
 mod miscfile;
+mod npdm;
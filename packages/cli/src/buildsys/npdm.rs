@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Writing the NPDM format (META, ACID and ACI0)
+//!
+//! See https://switchbrew.org/wiki/NPDM
+
+use crate::config::Npdm;
+
+const META_SIZE: usize = 0x80;
+const ACID_HEADER_SIZE: usize = 0x240;
+const ACI0_HEADER_SIZE: usize = 0x40;
+/// Size of the FilesystemAccessControl in ACID
+const FAC_SIZE: usize = 0x2C;
+/// Size of the FilesystemAccessHeader in ACI0
+const FAH_SIZE: usize = 0x1C;
+
+/// Title IDs the ACID allows
+const TITLE_ID_RANGE: (u64, u64) = (0x0100000000010000, 0x01ffffffffffffff);
+/// 64-bit, with 39-bit address space
+const MMU_FLAGS: u8 = 0b0111;
+/// Retail, with the application pool partition
+const ACID_FLAGS: u32 = 0b1;
+
+/// Serialize the NPDM for a module with the title ID.
+/// The config should be validated with [`Npdm::validate_resolved`]
+pub fn build_npdm(npdm: &Npdm, title_id: u64) -> Vec<u8> {
+    let sac = service_access_control(npdm);
+    let kac = kernel_access_control(npdm);
+
+    let mut acid = vec![0u8; ACID_HEADER_SIZE];
+    let fac_offset = append_aligned(&mut acid, &filesystem_access_control(npdm));
+    let sac_offset = append_aligned(&mut acid, &sac);
+    let kac_offset = append_aligned(&mut acid, &kac);
+    acid[0x200..0x204].copy_from_slice(b"ACID");
+    // the signed size does not include the signature
+    let signed_size = acid.len() - 0x100;
+    put_u32(&mut acid, 0x204, signed_size as u32);
+    put_u32(&mut acid, 0x20C, ACID_FLAGS);
+    put_u64(&mut acid, 0x210, TITLE_ID_RANGE.0);
+    put_u64(&mut acid, 0x218, TITLE_ID_RANGE.1);
+    put_extent(&mut acid, 0x220, fac_offset, FAC_SIZE);
+    put_extent(&mut acid, 0x228, sac_offset, sac.len());
+    put_extent(&mut acid, 0x230, kac_offset, kac.len());
+
+    let mut aci0 = vec![0u8; ACI0_HEADER_SIZE];
+    let fah_offset = append_aligned(&mut aci0, &filesystem_access_header(npdm));
+    let sac_offset = append_aligned(&mut aci0, &sac);
+    let kac_offset = append_aligned(&mut aci0, &kac);
+    aci0[0..4].copy_from_slice(b"ACI0");
+    put_u64(&mut aci0, 0x10, title_id);
+    put_extent(&mut aci0, 0x20, fah_offset, FAH_SIZE);
+    put_extent(&mut aci0, 0x28, sac_offset, sac.len());
+    put_extent(&mut aci0, 0x30, kac_offset, kac.len());
+
+    let mut meta = vec![0u8; META_SIZE];
+    meta[0..4].copy_from_slice(b"META");
+    meta[0x0C] = MMU_FLAGS;
+    meta[0x0E] = npdm.main_thread_priority();
+    meta[0x0F] = npdm.default_cpu_id();
+    put_u32(&mut meta, 0x1C, npdm.main_thread_stack_size());
+    let name = npdm.name().as_bytes();
+    meta[0x20..0x20 + name.len()].copy_from_slice(name);
+    let acid_size = acid.len();
+    let aci0_size = aci0.len();
+    let acid_offset = append_aligned(&mut meta, &acid);
+    let aci0_offset = append_aligned(&mut meta, &aci0);
+    put_extent(&mut meta, 0x70, aci0_offset, aci0_size);
+    put_extent(&mut meta, 0x78, acid_offset, acid_size);
+    meta
+}
+
+fn filesystem_access_control(npdm: &Npdm) -> [u8; FAC_SIZE] {
+    let mut fac = [0u8; FAC_SIZE];
+    fac[0] = 1; // version
+    put_u64(&mut fac, 0x4, npdm.filesystem_permissions());
+    fac
+}
+
+fn filesystem_access_header(npdm: &Npdm) -> [u8; FAH_SIZE] {
+    let mut fah = [0u8; FAH_SIZE];
+    fah[0] = 1; // version
+    put_u64(&mut fah, 0x4, npdm.filesystem_permissions());
+    // no content or save data owner info
+    put_u32(&mut fah, 0xC, FAH_SIZE as u32);
+    put_u32(&mut fah, 0x14, FAH_SIZE as u32);
+    fah
+}
+
+fn service_access_control(npdm: &Npdm) -> Vec<u8> {
+    let mut sac = vec![];
+    let services = npdm
+        .service_host()
+        .into_iter()
+        .map(|x| (x, 0x80))
+        .chain(npdm.service_access().into_iter().map(|x| (x, 0)));
+    for (name, host_bit) in services {
+        sac.push(host_bit | (name.len() - 1) as u8);
+        sac.extend_from_slice(name.as_bytes());
+    }
+    sac
+}
+
+/// Encode the kernel capabilities. Each capability is a u32, where the type is
+/// the number of trailing 1 bits
+fn kernel_access_control(npdm: &Npdm) -> Vec<u8> {
+    let mut caps = vec![];
+
+    let (highest_priority, lowest_priority) = npdm.thread_priority();
+    let (lowest_cpu, highest_cpu) = npdm.cpu_id();
+    caps.push(
+        u32::from(highest_cpu) << 24
+            | u32::from(lowest_cpu) << 16
+            | u32::from(lowest_priority) << 10
+            | u32::from(highest_priority) << 4
+            | 0b111,
+    );
+
+    // each syscall mask has 24 syscalls, and the index of the mask in the top 3 bits
+    let mut masks = [0u32; 8];
+    for id in npdm.syscall_ids() {
+        masks[usize::from(id / 24)] |= 1 << (id % 24);
+    }
+    for (index, mask) in masks.into_iter().enumerate() {
+        if mask != 0 {
+            caps.push((index as u32) << 29 | mask << 5 | 0b1111);
+        }
+    }
+
+    caps.push(u32::from(npdm.application_type()) << 14 | 0x1FFF);
+    caps.push(u32::from(npdm.min_kernel_version()) << 15 | 0x3FFF);
+    caps.push(u32::from(npdm.handle_table_size()) << 16 | 0x7FFF);
+    caps.push(u32::from(npdm.force_debug()) << 18 | u32::from(npdm.allow_debug()) << 17 | 0xFFFF);
+
+    caps.into_iter().flat_map(u32::to_le_bytes).collect()
+}
+
+/// Append the data at the next 0x10-byte boundary, and return the offset
+fn append_aligned(out: &mut Vec<u8>, data: &[u8]) -> usize {
+    let offset = out.len().next_multiple_of(0x10);
+    out.resize(offset, 0);
+    out.extend_from_slice(data);
+    offset
+}
+
+fn put_extent(out: &mut [u8], at: usize, offset: usize, size: usize) {
+    put_u32(out, at, offset as u32);
+    put_u32(out, at + 4, size as u32);
+}
+
+fn put_u32(out: &mut [u8], at: usize, value: u32) {
+    out[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(out: &mut [u8], at: usize, value: u64) {
+    out[at..at + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn default_npdm() {
+        let data = build_npdm(&Npdm::default(), 0x01007ef00011e000);
+        assert_eq!(&data[0..4], b"META");
+        assert_eq!(data[0x0E], 44);
+        assert_eq!(read_u32(&data, 0x1C), 0x100000);
+        assert_eq!(&data[0x20..0x2C], b"Application\0");
+
+        let aci0 = read_u32(&data, 0x70) as usize;
+        let acid = read_u32(&data, 0x78) as usize;
+        assert_eq!(acid, 0x80);
+        assert_eq!(&data[acid + 0x200..acid + 0x204], b"ACID");
+        assert_eq!(&data[aci0..aci0 + 4], b"ACI0");
+        assert_eq!(
+            &data[aci0 + 0x10..aci0 + 0x18],
+            &0x01007ef00011e000u64.to_le_bytes()
+        );
+        assert_eq!(data.len(), aci0 + read_u32(&data, 0x74) as usize);
+
+        // SAC allows all services
+        let sac = aci0 + read_u32(&data, aci0 + 0x28) as usize;
+        assert_eq!(read_u32(&data, aci0 + 0x2C), 2);
+        assert_eq!(&data[sac..sac + 2], b"\0*");
+
+        let kac = aci0 + read_u32(&data, aci0 + 0x30) as usize;
+        let kac_size = read_u32(&data, aci0 + 0x34) as usize;
+        let caps = data[kac..kac + kac_size]
+            .chunks_exact(4)
+            .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            caps,
+            vec![
+                0x030073b7, // kernel flags
+                0x1fffffef, // syscalls 0x00-0x17
+                0x3fffffef, // syscalls 0x18-0x2f
+                0x47e607ef, // syscalls 0x30-0x47
+                0x7fffffef, // syscalls 0x48-0x5f
+                0x9ff7ffef, // syscalls 0x60-0x77
+                0xa0001fef, // syscalls 0x78-0x7f
+                0x00005fff, // application type
+                0x00303fff, // min kernel version
+                0x02007fff, // handle table size
+                0x0002ffff, // debug flags
+            ]
+        );
+        // ACID has the same kernel capabilities
+        let acid_kac = acid + read_u32(&data, acid + 0x230) as usize;
+        assert_eq!(
+            data[acid_kac..acid_kac + kac_size],
+            data[kac..kac + kac_size]
+        );
+    }
+
+    #[test]
+    fn services_and_syscalls() {
+        let mut npdm = Npdm::default();
+        npdm.service_access = Some(vec!["fsp-srv".to_string(), "hid".to_string()]);
+        npdm.service_host = Some(vec!["mgtn".to_string()]);
+        npdm.syscalls = Some(vec!["svcSleepThread".to_string(), "svcBreak".to_string()]);
+        assert_eq!(
+            service_access_control(&npdm),
+            b"\x83mgtn\x06fsp-srv\x02hid".to_vec()
+        );
+        let kac = kernel_access_control(&npdm);
+        assert_eq!(read_u32(&kac, 4), 1 << (5 + 0x0b) | 0b1111);
+        assert_eq!(read_u32(&kac, 8), 1 << 29 | 1 << (5 + 0x26 - 24) | 0b1111);
+    }
+}
@@ -0,0 +1,569 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Reading and writing the NSO format, and converting ELF to NSO
+//!
+//! See https://switchbrew.org/wiki/NSO
+
+use cu::pre::*;
+use object::read::elf::{ElfFile64, ProgramHeader as _};
+use object::{Endianness, Object as _, ObjectSection as _};
+use sha2::{Digest, Sha256};
+
+/// Size of the NSO header. The text segment starts after it
+pub const NSO_HEADER_SIZE: usize = 0x100;
+/// Segments are loaded at page boundaries
+const PAGE_SIZE: u64 = 0x1000;
+
+/// Header of a segment in the NSO
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NsoSegmentHeader {
+    /// Offset of the segment in the file
+    pub file_offset: u32,
+    /// Offset of the segment when loaded
+    pub memory_offset: u32,
+    /// Size of the segment when loaded (decompressed)
+    pub size: u32,
+    /// Size of the segment in the file, which can be compressed
+    pub file_size: u32,
+    /// If the segment is compressed with LZ4
+    pub compressed: bool,
+    /// If the loader should check the hash of the segment
+    pub check_hash: bool,
+    /// SHA-256 of the decompressed segment
+    pub hash: [u8; 0x20],
+}
+
+impl NsoSegmentHeader {
+    /// Offset of the end of the segment when loaded
+    pub fn memory_end(&self) -> u64 {
+        u64::from(self.memory_offset) + u64::from(self.size)
+    }
+
+    /// Check if an offset in the module is in this segment
+    pub fn contains(&self, offset: u64) -> bool {
+        u64::from(self.memory_offset) <= offset && offset < self.memory_end()
+    }
+}
+
+/// An extent (offset and size) relative to the start of the rodata segment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RodataExtent {
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// The NSO header
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NsoHeader {
+    pub text: NsoSegmentHeader,
+    pub rodata: NsoSegmentHeader,
+    pub data: NsoSegmentHeader,
+    pub bss_size: u32,
+    /// Build ID of the module
+    pub module_id: [u8; 0x20],
+    /// The `.api_info` section in rodata
+    pub api_info: RodataExtent,
+    /// The `.dynstr` section in rodata
+    pub dynstr: RodataExtent,
+    /// The `.dynsym` section in rodata
+    pub dynsym: RodataExtent,
+}
+
+impl NsoHeader {
+    /// Parse the header at the start of the data
+    pub fn parse(data: &[u8]) -> cu::Result<Self> {
+        if data.len() < NSO_HEADER_SIZE || &data[0..4] != b"NSO0" {
+            cu::bail!("not a NSO file");
+        }
+        let read_u32 = |offset: usize| {
+            let bytes: [u8; 4] = data[offset..offset + 4].try_into().unwrap();
+            u32::from_le_bytes(bytes)
+        };
+        let flags = read_u32(0x0C);
+        // segment headers are (file offset, memory offset, size),
+        // and the compressed sizes and hashes are stored separately
+        let segment = |index: usize| NsoSegmentHeader {
+            file_offset: read_u32(0x10 + index * 0x10),
+            memory_offset: read_u32(0x14 + index * 0x10),
+            size: read_u32(0x18 + index * 0x10),
+            file_size: read_u32(0x60 + index * 4),
+            compressed: flags & (1 << index) != 0,
+            check_hash: flags & (1 << (index + 3)) != 0,
+            hash: data[0xA0 + index * 0x20..0xC0 + index * 0x20]
+                .try_into()
+                .unwrap(),
+        };
+        let extent = |offset: usize| RodataExtent {
+            offset: read_u32(offset),
+            size: read_u32(offset + 4),
+        };
+        Ok(Self {
+            text: segment(0),
+            rodata: segment(1),
+            data: segment(2),
+            bss_size: read_u32(0x3C),
+            module_id: data[0x40..0x60].try_into().unwrap(),
+            api_info: extent(0x88),
+            dynstr: extent(0x90),
+            dynsym: extent(0x98),
+        })
+    }
+
+    /// Write the header into the first [`NSO_HEADER_SIZE`] bytes
+    fn write(&self, out: &mut [u8]) {
+        let mut write_u32 = |offset: usize, value: u32| {
+            out[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        let mut flags = 0;
+        for (index, segment) in self.segments().into_iter().enumerate() {
+            if segment.compressed {
+                flags |= 1 << index;
+            }
+            if segment.check_hash {
+                flags |= 1 << (index + 3);
+            }
+            write_u32(0x10 + index * 0x10, segment.file_offset);
+            write_u32(0x14 + index * 0x10, segment.memory_offset);
+            write_u32(0x18 + index * 0x10, segment.size);
+            write_u32(0x60 + index * 4, segment.file_size);
+        }
+        write_u32(0x0C, flags);
+        write_u32(0x3C, self.bss_size);
+        for (offset, extent) in [
+            (0x88, self.api_info),
+            (0x90, self.dynstr),
+            (0x98, self.dynsym),
+        ] {
+            write_u32(offset, extent.offset);
+            write_u32(offset + 4, extent.size);
+        }
+        out[0..4].copy_from_slice(b"NSO0");
+        out[0x40..0x60].copy_from_slice(&self.module_id);
+        for (index, segment) in self.segments().into_iter().enumerate() {
+            out[0xA0 + index * 0x20..0xC0 + index * 0x20].copy_from_slice(&segment.hash);
+        }
+    }
+
+    fn segments(&self) -> [&NsoSegmentHeader; 3] {
+        [&self.text, &self.rodata, &self.data]
+    }
+
+    /// Size of the module when loaded, including bss, aligned to pages
+    pub fn memory_size(&self) -> u64 {
+        (self.data.memory_end() + u64::from(self.bss_size)).next_multiple_of(PAGE_SIZE)
+    }
+}
+
+/// An NSO with the segments decompressed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nso {
+    pub header: NsoHeader,
+    pub text: Vec<u8>,
+    pub rodata: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+impl Nso {
+    /// Parse the NSO, decompress the segments, and check the hashes of the segments
+    pub fn parse(data: &[u8]) -> cu::Result<Self> {
+        let header = NsoHeader::parse(data)?;
+        let text = read_segment(data, &header.text).context("failed to read text segment")?;
+        let rodata = read_segment(data, &header.rodata).context("failed to read rodata segment")?;
+        let data = read_segment(data, &header.data).context("failed to read data segment")?;
+        Ok(Self {
+            header,
+            text,
+            rodata,
+            data,
+        })
+    }
+
+    /// Convert a linked ELF to NSO
+    ///
+    /// The ELF must have 3 loadable segments for text, rodata and data,
+    /// like the ones linked with the Megaton linker script
+    pub fn from_elf(elf: &[u8]) -> cu::Result<Self> {
+        let file =
+            ElfFile64::<Endianness>::parse(elf).map_err(|e| cu::fmterr!("invalid ELF: {e}"))?;
+        let endian = file.endian();
+        let loads = file
+            .elf_program_headers()
+            .iter()
+            .filter(|x| x.p_type(endian) == object::elf::PT_LOAD)
+            .collect::<Vec<_>>();
+        let [text_load, rodata_load, data_load] = loads[..] else {
+            cu::bail!(
+                "expected 3 loadable segments (text, rodata, data) in the ELF, found {}",
+                loads.len()
+            );
+        };
+
+        let mut header = NsoHeader::default();
+        let segment = |load: &object::elf::ProgramHeader64<Endianness>,
+                       name: &str,
+                       expected_flags: u32,
+                       header: &mut NsoSegmentHeader|
+         -> cu::Result<Vec<u8>> {
+            let flags = load.p_flags(endian);
+            if flags != expected_flags {
+                cu::bail!("unexpected permissions of {name} segment: 0x{flags:x}");
+            }
+            let address = load.p_vaddr(endian);
+            if address % PAGE_SIZE != 0 {
+                cu::bail!("{name} segment is not page-aligned: 0x{address:x}");
+            }
+            let bytes = load
+                .data(endian, elf)
+                .map_err(|_| cu::fmterr!("{name} segment is out of bounds of the file"))?;
+            header.memory_offset = cu::check!(
+                u32::try_from(address),
+                "{name} segment is out of range: 0x{address:x}"
+            )?;
+            header.size = cu::check!(
+                u32::try_from(bytes.len()),
+                "{name} segment is too large: 0x{:x}",
+                bytes.len()
+            )?;
+            Ok(bytes.to_vec())
+        };
+        use object::elf::{PF_R, PF_W, PF_X};
+        let text = segment(text_load, "text", PF_R | PF_X, &mut header.text)?;
+        let rodata = segment(rodata_load, "rodata", PF_R, &mut header.rodata)?;
+        let data = segment(data_load, "data", PF_R | PF_W, &mut header.data)?;
+
+        // bss is the rest of the data segment, until the end of the page
+        let data_start = data_load.p_vaddr(endian);
+        let bss_start = data_start + data_load.p_filesz(endian);
+        let bss_end = (data_start + data_load.p_memsz(endian)).next_multiple_of(PAGE_SIZE);
+        let bss_size = bss_end - bss_start;
+        header.bss_size = cu::check!(u32::try_from(bss_size), "bss is too large: 0x{bss_size:x}")?;
+
+        match file.build_id() {
+            Ok(Some(build_id)) => {
+                let len = build_id.len().min(header.module_id.len());
+                header.module_id[..len].copy_from_slice(&build_id[..len]);
+            }
+            _ => cu::warn!("the ELF does not have a build ID"),
+        }
+
+        let rodata_start = u64::from(header.rodata.memory_offset);
+        let rodata_end = header.rodata.memory_end();
+        let extent = |name: &str| -> cu::Result<RodataExtent> {
+            let Some(section) = file.section_by_name(name) else {
+                return Ok(RodataExtent::default());
+            };
+            let start = section.address();
+            let end = start + section.size();
+            if start < rodata_start || end > rodata_end {
+                cu::bail!("{name} is not in the rodata segment");
+            }
+            Ok(RodataExtent {
+                offset: (start - rodata_start) as u32,
+                size: section.size() as u32,
+            })
+        };
+        header.api_info = extent(".api_info")?;
+        header.dynstr = extent(".dynstr")?;
+        header.dynsym = extent(".dynsym")?;
+
+        Ok(Self {
+            header,
+            text,
+            rodata,
+            data,
+        })
+    }
+
+    /// Write the NSO, with the segments compressed and hashed
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = self.header.clone();
+        let mut out = vec![0u8; NSO_HEADER_SIZE];
+        for (segment, bytes) in [
+            (&mut header.text, &self.text),
+            (&mut header.rodata, &self.rodata),
+            (&mut header.data, &self.data),
+        ] {
+            let compressed = lz4_flex::block::compress(bytes);
+            segment.file_offset = out.len() as u32;
+            segment.size = bytes.len() as u32;
+            segment.file_size = compressed.len() as u32;
+            segment.compressed = true;
+            segment.check_hash = true;
+            segment.hash = Sha256::digest(bytes).into();
+            out.extend_from_slice(&compressed);
+        }
+        header.write(&mut out[..NSO_HEADER_SIZE]);
+        out
+    }
+
+    /// Get the `.dynsym` section
+    pub fn dynsym(&self) -> cu::Result<&[u8]> {
+        self.rodata_extent(self.header.dynsym)
+            .context("invalid .dynsym in the NSO")
+    }
+
+    /// Get the `.dynstr` section
+    pub fn dynstr(&self) -> cu::Result<&[u8]> {
+        self.rodata_extent(self.header.dynstr)
+            .context("invalid .dynstr in the NSO")
+    }
+
+    fn rodata_extent(&self, extent: RodataExtent) -> cu::Result<&[u8]> {
+        let start = extent.offset as usize;
+        let end = start + extent.size as usize;
+        match self.rodata.get(start..end) {
+            Some(x) => Ok(x),
+            None => cu::bail!(
+                "extent 0x{start:x}..0x{end:x} is out of bounds of rodata (0x{:x})",
+                self.rodata.len()
+            ),
+        }
+    }
+}
+
+fn read_segment(data: &[u8], segment: &NsoSegmentHeader) -> cu::Result<Vec<u8>> {
+    let start = segment.file_offset as usize;
+    let end = start + segment.file_size as usize;
+    let Some(bytes) = data.get(start..end) else {
+        cu::bail!("segment is out of bounds of the file");
+    };
+    let bytes = if segment.compressed {
+        lz4_flex::block::decompress(bytes, segment.size as usize)
+            .map_err(|e| cu::fmterr!("failed to decompress: {e}"))?
+    } else {
+        bytes.to_vec()
+    };
+    if segment.check_hash && Sha256::digest(&bytes)[..] != segment.hash {
+        cu::bail!("hash mismatch");
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_compressed_segments() {
+        let text = [0x1f, 0x20, 0x03, 0xd5].repeat(64);
+        let rodata = b"\0main\0".repeat(16);
+        let data = vec![7u8; 32];
+        let compressed_text = lz4_flex::block::compress(&text);
+
+        let mut file = vec![0u8; NSO_HEADER_SIZE];
+        file[0..4].copy_from_slice(b"NSO0");
+        let put = |file: &mut Vec<u8>, offset: usize, value: usize| {
+            file[offset..offset + 4].copy_from_slice(&(value as u32).to_le_bytes());
+        };
+        // only text is compressed
+        put(&mut file, 0x0C, 1);
+        let segments = [
+            (&compressed_text, text.len(), 0),
+            (&rodata, rodata.len(), 0x1000),
+            (&data, data.len(), 0x2000),
+        ];
+        let mut file_offset = NSO_HEADER_SIZE;
+        for (i, (bytes, size, memory_offset)) in segments.iter().enumerate() {
+            put(&mut file, 0x10 + i * 0x10, file_offset);
+            put(&mut file, 0x14 + i * 0x10, *memory_offset);
+            put(&mut file, 0x18 + i * 0x10, *size);
+            put(&mut file, 0x60 + i * 4, bytes.len());
+            file_offset += bytes.len();
+        }
+        put(&mut file, 0x3C, 0x10);
+        put(&mut file, 0x90, 1);
+        put(&mut file, 0x94, 4);
+        for (bytes, _, _) in segments {
+            file.extend_from_slice(bytes);
+        }
+
+        let nso = Nso::parse(&file).unwrap();
+        assert_eq!(nso.text, text);
+        assert_eq!(nso.rodata, rodata);
+        assert_eq!(nso.data, data);
+        assert_eq!(nso.dynstr().unwrap(), b"main");
+        assert!(nso.header.text.contains(0x10));
+        assert!(!nso.header.text.contains(0x100));
+        assert_eq!(nso.header.memory_size(), 0x3000);
+    }
+
+    /// Make an ELF with 3 segments, a `.dynstr`, a `.dynsym` and a build ID
+    fn make_elf() -> Vec<u8> {
+        let mut elf = vec![0u8; 0x4000];
+        let put = |elf: &mut Vec<u8>, offset: usize, bytes: &[u8]| {
+            elf[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        // file header
+        put(&mut elf, 0, b"\x7fELF\x02\x01\x01");
+        put(&mut elf, 0x10, &object::elf::ET_DYN.to_le_bytes());
+        put(&mut elf, 0x12, &object::elf::EM_AARCH64.to_le_bytes());
+        put(&mut elf, 0x14, &1u32.to_le_bytes());
+        put(&mut elf, 0x20, &0x40u64.to_le_bytes());
+        put(&mut elf, 0x28, &0x3800u64.to_le_bytes());
+        put(&mut elf, 0x34, &0x40u16.to_le_bytes());
+        put(&mut elf, 0x36, &56u16.to_le_bytes());
+        put(&mut elf, 0x38, &3u16.to_le_bytes());
+        put(&mut elf, 0x3A, &64u16.to_le_bytes());
+        put(&mut elf, 0x3C, &5u16.to_le_bytes());
+        put(&mut elf, 0x3E, &4u16.to_le_bytes());
+
+        // (flags, file offset, address, file size, memory size)
+        let loads = [
+            (5u32, 0x1000u64, 0u64, 0x10u64, 0x10u64),
+            (4, 0x2000, 0x1000, 0x80, 0x80),
+            (6, 0x3000, 0x2000, 0x8, 0x100),
+        ];
+        for (i, (flags, offset, address, file_size, memory_size)) in loads.iter().enumerate() {
+            let header = 0x40 + i * 56;
+            put(&mut elf, header, &object::elf::PT_LOAD.to_le_bytes());
+            put(&mut elf, header + 4, &flags.to_le_bytes());
+            put(&mut elf, header + 8, &offset.to_le_bytes());
+            put(&mut elf, header + 0x10, &address.to_le_bytes());
+            put(&mut elf, header + 0x20, &file_size.to_le_bytes());
+            put(&mut elf, header + 0x28, &memory_size.to_le_bytes());
+        }
+        put(&mut elf, 0x1000, &[0x1f, 0x20, 0x03, 0xd5].repeat(4));
+        // rodata: .dynstr at 0x1000, .dynsym at 0x1010, build ID note at 0x1040
+        put(&mut elf, 0x2000, b"\0main\0");
+        put(&mut elf, 0x2010 + 24, &1u32.to_le_bytes());
+        put(&mut elf, 0x2010 + 24 + 4, &[0x12]);
+        put(&mut elf, 0x2010 + 24 + 6, &1u16.to_le_bytes());
+        put(&mut elf, 0x2040, &4u32.to_le_bytes());
+        put(&mut elf, 0x2044, &20u32.to_le_bytes());
+        put(
+            &mut elf,
+            0x2048,
+            &object::elf::NT_GNU_BUILD_ID.to_le_bytes(),
+        );
+        put(&mut elf, 0x204C, b"GNU\0");
+        put(&mut elf, 0x2050, &[0xab; 20]);
+        put(&mut elf, 0x3000, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        put(
+            &mut elf,
+            0x3700,
+            b"\0.dynstr\0.dynsym\0.note.gnu.build-id\0.shstrtab\0",
+        );
+
+        // (name, type, address, offset, size, link, entry size)
+        let sections = [
+            (
+                1u32,
+                object::elf::SHT_STRTAB,
+                0x1000u64,
+                0x2000u64,
+                6u64,
+                0u32,
+                0u64,
+            ),
+            (9, object::elf::SHT_DYNSYM, 0x1010, 0x2010, 48, 1, 24),
+            (17, object::elf::SHT_NOTE, 0x1040, 0x2040, 36, 0, 0),
+            (36, object::elf::SHT_STRTAB, 0, 0x3700, 46, 0, 0),
+        ];
+        for (i, (name, ty, address, offset, size, link, entry_size)) in sections.iter().enumerate()
+        {
+            let header = 0x3800 + (i + 1) * 64;
+            put(&mut elf, header, &name.to_le_bytes());
+            put(&mut elf, header + 4, &ty.to_le_bytes());
+            if *address != 0 {
+                let flags = u64::from(object::elf::SHF_ALLOC);
+                put(&mut elf, header + 8, &flags.to_le_bytes());
+            }
+            put(&mut elf, header + 0x10, &address.to_le_bytes());
+            put(&mut elf, header + 0x18, &offset.to_le_bytes());
+            put(&mut elf, header + 0x20, &size.to_le_bytes());
+            put(&mut elf, header + 0x28, &link.to_le_bytes());
+            put(&mut elf, header + 0x38, &entry_size.to_le_bytes());
+        }
+        elf
+    }
+
+    #[test]
+    fn elf_roundtrip() {
+        let elf = make_elf();
+        let nso = Nso::from_elf(&elf).unwrap();
+        assert_eq!(nso.text, elf[0x1000..0x1010]);
+        assert_eq!(nso.rodata, elf[0x2000..0x2080]);
+        assert_eq!(nso.data, elf[0x3000..0x3008]);
+        assert_eq!(nso.header.rodata.memory_offset, 0x1000);
+        assert_eq!(nso.header.data.memory_offset, 0x2000);
+        // bss is until the end of the page
+        assert_eq!(nso.header.bss_size, 0x1000 - 0x8);
+        assert_eq!(nso.header.module_id[..20], [0xab; 20]);
+        assert_eq!(nso.header.module_id[20..], [0; 12]);
+        assert_eq!(nso.dynstr().unwrap(), b"\0main\0");
+        assert_eq!(nso.dynsym().unwrap().len(), 48);
+
+        let bytes = nso.to_bytes();
+        let parsed = Nso::parse(&bytes).unwrap();
+        assert_eq!(parsed.text, nso.text);
+        assert_eq!(parsed.rodata, nso.rodata);
+        assert_eq!(parsed.data, nso.data);
+        assert_eq!(parsed.header.bss_size, nso.header.bss_size);
+        assert_eq!(parsed.header.module_id, nso.header.module_id);
+        assert_eq!(parsed.header.dynsym, nso.header.dynsym);
+        assert!(parsed.header.text.compressed && parsed.header.text.check_hash);
+        // writing again gives the same file
+        assert_eq!(parsed.to_bytes(), bytes);
+
+        let mut corrupted = bytes;
+        corrupted[0xA0] ^= 1;
+        assert!(Nso::parse(&corrupted).is_err());
+    }
+
+    /// A module assembled from `testdata/module.s` and linked with `lld` and the
+    /// Megaton linker script
+    static MODULE_ELF: &[u8] = include_bytes!("testdata/module.elf");
+
+    #[test]
+    fn linked_module() {
+        let nso = Nso::from_elf(MODULE_ELF).unwrap();
+        // segments are at the file offsets of the program headers
+        assert_eq!(nso.header.text.memory_offset, 0);
+        assert_eq!(nso.text, MODULE_ELF[0x1000..0x1050]);
+        assert_eq!(nso.header.rodata.memory_offset, 0x1000);
+        assert_eq!(nso.rodata, MODULE_ELF[0x2000..0x2524]);
+        assert_eq!(nso.header.data.memory_offset, 0x2000);
+        assert_eq!(nso.data, MODULE_ELF[0x3000..0x3180]);
+        assert_eq!(nso.data[..8], 0x1122334455667788u64.to_le_bytes());
+        // .nx-module-name is at the start of rodata
+        assert_eq!(nso.rodata[..8], [0, 0, 0, 0, 9, 0, 0, 0]);
+        assert_eq!(&nso.rodata[8..17], b"test_mod\0");
+        // bss (0x2345 bytes at 0x3000) is rounded up to the page, and starts
+        // at the end of the data in the file
+        assert_eq!(nso.header.bss_size, 0x6000 - 0x2180);
+        assert_eq!(nso.header.memory_size(), 0x6000);
+        // module ID is the build ID, padded with zeros
+        let build_id = [
+            0xa1, 0xd8, 0x40, 0x26, 0x8e, 0xf9, 0x12, 0x14, 0x35, 0xd1, 0x7d, 0x59, 0x6f, 0xb5,
+            0xf8, 0xec, 0xd5, 0xee, 0x24, 0x13,
+        ];
+        assert_eq!(nso.header.module_id[..20], build_id);
+        assert_eq!(nso.header.module_id[20..], [0; 12]);
+        assert_eq!(
+            nso.header.dynsym,
+            RodataExtent {
+                offset: 0x180,
+                size: 0x210
+            }
+        );
+        assert_eq!(
+            nso.header.dynstr,
+            RodataExtent {
+                offset: 0x390,
+                size: 0x140
+            }
+        );
+        assert!(nso.dynstr().unwrap().windows(4).any(|x| x == b"main"));
+
+        let parsed = Nso::parse(&nso.to_bytes()).unwrap();
+        assert_eq!(parsed.text, nso.text);
+        assert_eq!(parsed.rodata, nso.rodata);
+        assert_eq!(parsed.data, nso.data);
+        assert_eq!(parsed.header.text.memory_offset, 0);
+        assert_eq!(parsed.header.rodata.memory_offset, 0x1000);
+        assert_eq!(parsed.header.data.memory_offset, 0x2000);
+        assert_eq!(parsed.header.bss_size, nso.header.bss_size);
+        assert_eq!(parsed.header.module_id, nso.header.module_id);
+    }
+}
@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use cu::pre::*;
+
+use crate::buildsys::{self, BuildArgs, BuildOutput, MessageFormat, PackageArgs};
+use crate::config::{self, Config, Module, Package, PackageLayout};
+
+/// Build the module and package it into mod folders
+pub async fn package(args: PackageArgs) -> cu::Result<()> {
+    // resolve the manifest before building, since the build changes the current directory
+    let (_, manifest_path) = config::get_root_and_manifest(args.config.as_deref())?;
+    let config = config::load(&manifest_path)?;
+    let out_path = args.out.map(std::path::absolute).transpose()?;
+    let build_args = BuildArgs {
+        profile: args.profile,
+        configure: false,
+        message_format: MessageFormat::Human,
+        timings: false,
+        config: Some(manifest_path.as_utf8()?.to_string()),
+    };
+    let Some(output) = buildsys::run(build_args).await? else {
+        cu::bail!("build did not produce any output");
+    };
+    package_output(&config, &output, args.layouts, args.zip, out_path)
+}
+
+/// Package the output of a build into mod folders
+///
+/// Layouts and zip fall back to the `[package]` config if not specified
+pub fn package_output(
+    config: &Config,
+    output: &BuildOutput,
+    layouts: Vec<PackageLayout>,
+    zip: bool,
+    out_path: Option<PathBuf>,
+) -> cu::Result<()> {
+    let layouts = if layouts.is_empty() {
+        config.package.layouts.clone()
+    } else {
+        layouts
+    };
+    if layouts.is_empty() {
+        cu::warn!("no package layout specified, nothing to do");
+        cu::hint!("- please set package.layouts in Megaton.toml, or specify --layout");
+        return Ok(());
+    }
+    // the default output directory is owned by megaton, but --out can be
+    // an existing mod folder or the root of the SD card with other files
+    let (out_path, owned) = match out_path {
+        Some(out) => (out, false),
+        None => (output.target_mod.join("package"), true),
+    };
+    let zip = zip || config.package.zip;
+
+    for layout in layouts {
+        let layout_path = out_path.join(layout.to_string());
+        let exefs_path = exefs_path(layout, &config.module, &config.package)?;
+        if owned {
+            // remove stale files from previous packages
+            cu::fs::make_dir_empty(&layout_path)?;
+        }
+        cu::fs::make_dir(layout_path.join(&exefs_path))?;
+        // only the files of the module are written
+        let files = [
+            (&output.nso, exefs_path.join(&config.package.subsdk)),
+            (&output.npdm, exefs_path.join("main.npdm")),
+        ];
+        for (from, to) in &files {
+            cu::fs::copy(from, layout_path.join(to))?;
+        }
+        cu::info!("packaged {layout}: {}", layout_path.try_to_rel().display());
+
+        if zip {
+            let zip_path = out_path.join(format!("{}-{layout}.zip", config.module.name));
+            let files = files.map(|(_, path)| path);
+            make_zip(&layout_path, &files, &zip_path)?;
+            cu::info!("packaged {layout}: {}", zip_path.try_to_rel().display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the path of the exefs directory in the layout, relative to the layout root
+pub fn exefs_path(
+    layout: PackageLayout,
+    module: &Module,
+    package: &Package,
+) -> cu::Result<PathBuf> {
+    let title_id = format!("{:016X}", module.title_id);
+    let mod_name = package.mod_name.as_deref().unwrap_or(&module.name);
+    let mut path = PathBuf::new();
+    match layout {
+        PackageLayout::Atmosphere => {
+            path.extend(["atmosphere", "contents", &title_id]);
+        }
+        PackageLayout::SimpleModManager => {
+            let Some(game) = &package.game else {
+                cu::error!("the {layout} layout requires the game name");
+                cu::hint!("- please set package.game in Megaton.toml");
+                cu::bail!("failed to resolve package layout");
+            };
+            path.extend(["mods", game, mod_name, "contents", &title_id]);
+        }
+        PackageLayout::Emulator => {
+            path.extend([&title_id, mod_name]);
+        }
+    }
+    path.push("exefs");
+    Ok(path)
+}
+
+/// Archive files into a zip file, with their paths relative to `dir`
+fn make_zip(dir: &Path, files: &[PathBuf], zip_path: &Path) -> cu::Result<()> {
+    let mut zip = zip::ZipWriter::new(cu::fs::writer(zip_path)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    // keep the archive deterministic
+    let mut files = files.to_vec();
+    files.sort();
+    for rel_path in files {
+        let name = rel_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        cu::check!(
+            zip.start_file(name, options),
+            "failed to add file to zip: {}",
+            rel_path.display()
+        )?;
+        zip.write_all(&cu::fs::read(dir.join(&rel_path))?)?;
+    }
+    cu::check!(zip.finish(), "failed to write zip: {}", zip_path.display())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    #[test]
+    fn exefs_path_for_layouts() -> cu::Result<()> {
+        let mut module = Module::default();
+        module.name = "my-mod".to_string();
+        module.title_id = 0x01007ef00011e000;
+        let mut package = Package::default();
+        assert_eq!(
+            exefs_path(PackageLayout::Atmosphere, &module, &package)?,
+            Path::new("atmosphere/contents/01007EF00011E000/exefs")
+        );
+        assert_eq!(
+            exefs_path(PackageLayout::Emulator, &module, &package)?,
+            Path::new("01007EF00011E000/my-mod/exefs")
+        );
+        assert!(exefs_path(PackageLayout::SimpleModManager, &module, &package).is_err());
+        package.game = Some("Some Game".to_string());
+        package.mod_name = Some("My Mod".to_string());
+        assert_eq!(
+            exefs_path(PackageLayout::SimpleModManager, &module, &package)?,
+            Path::new("mods/Some Game/My Mod/contents/01007EF00011E000/exefs")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn package_into_existing_folder() -> cu::Result<()> {
+        let dir = std::env::temp_dir().join(format!("megaton-package-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let target_mod = dir.join("target");
+        let out = dir.join("sd");
+        std::fs::create_dir_all(&target_mod)?;
+        std::fs::write(target_mod.join("my-mod.nso"), b"nso")?;
+        std::fs::write(target_mod.join("main.npdm"), b"npdm")?;
+        // another mod already on the SD card
+        let other = out.join("atmosphere/atmosphere/contents/0100000000001000/exefs/subsdk9");
+        std::fs::create_dir_all(other.parent().unwrap())?;
+        std::fs::write(&other, b"other")?;
+
+        let mut config = Config::default();
+        config.module.name = "my-mod".to_string();
+        config.module.title_id = 0x01007ef00011e000;
+        config.package.subsdk = "subsdk9".to_string();
+        let output = BuildOutput {
+            profile: "none".to_string(),
+            nso: target_mod.join("my-mod.nso"),
+            npdm: target_mod.join("main.npdm"),
+            target_mod,
+        };
+        package_output(
+            &config,
+            &output,
+            vec![PackageLayout::Atmosphere],
+            true,
+            Some(out.clone()),
+        )?;
+
+        let exefs = out.join("atmosphere/atmosphere/contents/01007EF00011E000/exefs");
+        assert_eq!(std::fs::read(exefs.join("subsdk9"))?, b"nso");
+        assert_eq!(std::fs::read(exefs.join("main.npdm"))?, b"npdm");
+        assert_eq!(std::fs::read(&other)?, b"other");
+        // the zip only has the files of the module
+        let zip_file = std::fs::File::open(out.join("my-mod-atmosphere.zip"))?;
+        let archive = zip::ZipArchive::new(zip_file).unwrap();
+        assert_eq!(
+            archive.file_names().collect::<BTreeSet<_>>(),
+            BTreeSet::from([
+                "atmosphere/contents/01007EF00011E000/exefs/main.npdm",
+                "atmosphere/contents/01007EF00011E000/exefs/subsdk9",
+            ])
+        );
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
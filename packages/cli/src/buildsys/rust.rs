@@ -9,6 +9,7 @@ use std::{
 use cargo_metadata::{MetadataCommand, semver::Version};
 use cu::pre::*;
 
+use crate::buildsys::message::{self, Message};
 use crate::config::CargoConfig;
 use crate::env;
 
@@ -17,6 +18,8 @@ pub struct RustCtx {
     pub manifest: PathBuf,
     source_paths: Vec<PathBuf>,
     header_suffix: String,
+    /// Environment variables set for cargo
+    envs: Vec<(&'static str, PathBuf)>,
 }
 
 impl RustCtx {
@@ -64,6 +67,7 @@ impl RustCtx {
             manifest,
             source_paths,
             header_suffix,
+            envs: vec![],
         })
     }
 
@@ -102,6 +106,11 @@ impl RustCtx {
         Ok(())
     }
 
+    /// Set an environment variable with a path for cargo, like the generated asset accessors
+    pub fn set_env(&mut self, key: &'static str, path: PathBuf) {
+        self.envs.push((key, path));
+    }
+
     pub fn has_build_script(&self) -> bool {
         let script = self.manifest.parent().unwrap().join("build.rs");
         script.exists()
@@ -122,29 +131,49 @@ impl RustCtx {
 
         let cargo = cu::which("cargo")
             .context("Cargo executable not found: ensure rust is properly installed")?;
-        let command = if check { "check" } else { "build" };
+        let subcommand = if check { "check" } else { "build" };
+        let env = env::get();
         let mut command = cargo
             .command()
             .add(cu::args![
                 "+megaton",
-                command,
+                subcommand,
                 "--manifest-path",
                 &self.manifest,
             ])
-            .preset(cu::pio::cargo(if check {
+            .args(cargoflags)
+            .env("RUSTFLAGS", rustflags)
+            .env("CC", env.cc())
+            .env("CXX", env.cxx())
+            .env("AR", env.ar());
+        for (key, path) in &self.envs {
+            command = command.env(key, path);
+        }
+
+        if message::is_json() {
+            let (child, mut lines) = command
+                .arg("--message-format=json")
+                .stdout(cu::pio::co_lines())
+                .stderr(cu::lv::D)
+                .stdin_null()
+                .co_spawn()
+                .await?;
+            while let Some(line) = lines.next().await {
+                let line = line?;
+                match json::parse::<json::Value>(&line) {
+                    Ok(message) => message::emit(Message::Cargo { message }),
+                    Err(_) => cu::debug!("cargo: {line}"),
+                }
+            }
+            child.co_wait_nz().await?;
+        } else {
+            let command = command.preset(cu::pio::cargo(if check {
                 "Check rust crate"
             } else {
                 "Build rust crate"
             }));
-
-        let env = env::get();
-        command = command.args(cargoflags);
-        command = command.env("RUSTFLAGS", rustflags);
-        command = command.env("CC", env.cc());
-        command = command.env("CXX", env.cxx());
-        command = command.env("AR", env.ar());
-
-        command.co_spawn().await?.0.co_wait_nz().await?;
+            command.co_spawn().await?.0.co_wait_nz().await?;
+        }
         if check {
             return Ok(false);
         }
@@ -156,10 +185,8 @@ impl RustCtx {
         Ok(new_mtime != old_mtime)
     }
 
-    /// Gets the path to the static lib compiled by cargo
-    /// Error if the Cargo manifest can't be parsed, filename
-    /// can't be parsed, or if file doesn't exist
-    pub async fn get_output(&self) -> cu::Result<PathBuf> {
+    /// Gets the cargo target directory of the workspace the crate is in
+    pub async fn target_dir(&self) -> cu::Result<PathBuf> {
         let cargo = cu::which("cargo")
             .context("Cargo executable not found: ensure rust is properly installed")?;
         let (child, stdout_handle) = cargo
@@ -179,10 +206,29 @@ impl RustCtx {
 
         let workspace_root = PathBuf::from(stdout_handle.co_join().await??);
 
-        let workspace_path = workspace_root
-            .parent()
-            .unwrap()
-            .join("target")
+        Ok(workspace_root.parent().unwrap().join("target"))
+    }
+
+    /// Run `cargo clean` for the crate
+    pub async fn clean(&self) -> cu::Result<()> {
+        let cargo = cu::which("cargo")
+            .context("Cargo executable not found: ensure rust is properly installed")?;
+        cargo
+            .command()
+            .add(cu::args!["clean", "--manifest-path", &self.manifest])
+            .stdin_null()
+            .stdoe(cu::lv::D)
+            .co_wait_nz()
+            .await
+    }
+
+    /// Gets the path to the static lib compiled by cargo
+    /// Error if the Cargo manifest can't be parsed, filename
+    /// can't be parsed, or if file doesn't exist
+    pub async fn get_output(&self) -> cu::Result<PathBuf> {
+        let workspace_path = self
+            .target_dir()
+            .await?
             .join("aarch64-unknown-hermit")
             .join("release");
 
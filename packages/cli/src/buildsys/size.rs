@@ -0,0 +1,653 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Analyzing the size of the built module
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use cu::pre::*;
+use object::{Object as _, ObjectSection as _, ObjectSymbol as _};
+
+use crate::buildsys::link_map::LinkMap;
+use crate::buildsys::nso::{NsoHeader, NsoSegmentHeader};
+use crate::buildsys::{self, SizeArgs};
+use crate::config::{self, SizeBudget};
+
+/// Kind of an allocated section, which is what the size budget is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SectionKind {
+    Text,
+    Rodata,
+    Data,
+    Bss,
+}
+
+impl SectionKind {
+    const ALL: [Self; 4] = [Self::Text, Self::Rodata, Self::Data, Self::Bss];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Rodata => "rodata",
+            Self::Data => "data",
+            Self::Bss => "bss",
+        }
+    }
+}
+
+/// A section in the linked ELF
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SectionSize {
+    pub name: String,
+    pub kind: SectionKind,
+    pub size: u64,
+}
+
+/// Size of a symbol, an object file or a crate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedSize {
+    pub name: String,
+    /// Kind of the section the symbol is in. Not set for object files and crates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<SectionKind>,
+    pub size: u64,
+}
+
+/// A segment in the NSO
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SegmentSize {
+    /// Size when loaded
+    pub memory_size: u64,
+    /// Size in the file, which can be compressed
+    pub file_size: u64,
+}
+
+/// Sizes read from the NSO header
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NsoSize {
+    pub file_size: u64,
+    /// Size of the module when loaded, including bss, aligned to pages
+    pub memory_size: u64,
+    pub text: SegmentSize,
+    pub rodata: SegmentSize,
+    pub data: SegmentSize,
+    pub bss: u64,
+}
+
+/// The report of `megaton size`, also saved to `size.json` to diff against later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeReport {
+    pub elf: PathBuf,
+    pub sections: Vec<SectionSize>,
+    pub totals: BTreeMap<SectionKind, u64>,
+    pub nso: Option<NsoSize>,
+    /// Symbols, from largest to smallest
+    pub symbols: Vec<NamedSize>,
+    /// Object files from the link map, from largest to smallest
+    pub objects: Vec<NamedSize>,
+    /// Rust crates from the symbol paths, from largest to smallest
+    pub crates: Vec<NamedSize>,
+}
+
+/// Change in size of one entry since the baseline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SizeChange {
+    pub name: String,
+    pub old: u64,
+    pub new: u64,
+}
+
+/// Changes since the baseline, from the largest change to the smallest
+#[derive(Debug, Serialize)]
+struct SizeChanges {
+    sections: Vec<SizeChange>,
+    symbols: Vec<SizeChange>,
+    objects: Vec<SizeChange>,
+    crates: Vec<SizeChange>,
+}
+
+/// Output of `megaton size --format json`
+#[derive(Debug, Serialize)]
+struct SizeOutput<'a> {
+    #[serde(flatten)]
+    report: &'a SizeReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changes: Option<SizeChanges>,
+}
+
+/// Run the `size` command on the built module
+pub fn size_module(args: SizeArgs) -> cu::Result<()> {
+    let (root_path, manifest_path) = config::get_root_and_manifest(args.config.as_deref())?;
+    let config = config::load(&manifest_path)?;
+    let profile = config.profile.resolve(&args.profile)?;
+    let elf_path = buildsys::elf_path(&root_path, &config.module, profile);
+    if !elf_path.exists() {
+        cu::bail!(
+            "'{}' does not exist; please build the module first",
+            elf_path.display()
+        );
+    }
+    let report_path =
+        buildsys::module_target_path(&root_path, &config.module, profile).join("size.json");
+    let baseline_path = match args.baseline {
+        Some(path) => Some(path),
+        None if args.diff => Some(report_path.clone()),
+        None => None,
+    };
+    let baseline = match baseline_path {
+        Some(path) => {
+            if !path.exists() {
+                cu::hint!("a report is saved every time `megaton size` runs");
+                cu::bail!("no size report to compare with at '{}'", path.display());
+            }
+            let report = json::parse::<SizeReport>(&cu::fs::read_string(&path)?)
+                .context(format!("failed to read size report '{}'", path.display()))?;
+            Some(report)
+        }
+        None => None,
+    };
+
+    let map_path = buildsys::map_path(&root_path, &config.module, profile);
+    let nso_path = buildsys::nso_path(&root_path, &config.module, profile);
+    let report = analyze(&root_path, &elf_path, &map_path, &nso_path)?;
+    cu::fs::write_json_pretty(&report_path, &report)?;
+
+    let budget = match &config.check {
+        Some(check) => check.get_profile(profile).max_size,
+        None => SizeBudget::default(),
+    };
+    let changes = baseline.map(|baseline| SizeChanges {
+        sections: diff_sizes(
+            baseline.sections.iter().map(|x| (&x.name, x.size)),
+            report.sections.iter().map(|x| (&x.name, x.size)),
+        ),
+        symbols: diff_named(&baseline.symbols, &report.symbols),
+        objects: diff_named(&baseline.objects, &report.objects),
+        crates: diff_named(&baseline.crates, &report.crates),
+    });
+
+    // printed as-is so the output can be piped to other tools
+    cu::lv::disable_print_time();
+    match args.format {
+        SizeFormat::Text => print_report(&report, changes.as_ref(), &budget, args.top),
+        SizeFormat::Json => {
+            let output = SizeOutput {
+                report: &report,
+                changes,
+            };
+            println!("{}", json::stringify_pretty(&output)?);
+        }
+    }
+    Ok(())
+}
+
+/// Output format of `megaton size`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SizeFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Fail if any kind of section in the ELF is larger than the budget
+pub fn check_size_budget(elf: &Path, budget: &SizeBudget) -> cu::Result<()> {
+    if SectionKind::ALL
+        .iter()
+        .all(|kind| budget.get(kind.as_str()).is_none())
+    {
+        return Ok(());
+    }
+    let data = cu::fs::read(elf)?;
+    let (sections, _, _) = read_elf(&data)?;
+    let totals = section_totals(&sections);
+    let mut exceeded = vec![];
+    for kind in SectionKind::ALL {
+        let Some(max) = budget.get(kind.as_str()) else {
+            continue;
+        };
+        let size = totals.get(&kind).copied().unwrap_or_default();
+        if size > max {
+            exceeded.push(format!(
+                "{}: {} > {} (by {})",
+                kind.as_str(),
+                format_size(size),
+                format_size(max),
+                format_size(size - max)
+            ));
+        } else {
+            cu::debug!(
+                "Check: {} is {} of {}",
+                kind.as_str(),
+                format_size(size),
+                format_size(max)
+            );
+        }
+    }
+    if !exceeded.is_empty() {
+        cu::bail!(
+            "Size budget exceeded in {}:\n{:#?}",
+            elf.display(),
+            exceeded
+        );
+    }
+    Ok(())
+}
+
+/// Analyze the ELF, with the link map and the NSO if they exist
+fn analyze(
+    root: &Path,
+    elf_path: &Path,
+    map_path: &Path,
+    nso_path: &Path,
+) -> cu::Result<SizeReport> {
+    let data = cu::fs::read(elf_path)?;
+    let (sections, symbols, crates) =
+        read_elf(&data).context(format!("failed to read ELF '{}'", elf_path.display()))?;
+
+    let objects = if map_path.exists() {
+        let map = LinkMap::parse(&cu::fs::read_string(map_path)?, root);
+        object_sizes(&map, &sections)
+    } else {
+        cu::warn!("link map not found; please rebuild the module to see the size of object files");
+        vec![]
+    };
+    let nso = if nso_path.exists() {
+        let nso = parse_nso_header(&cu::fs::read(nso_path)?)
+            .context(format!("failed to read NSO '{}'", nso_path.display()))?;
+        Some(nso)
+    } else {
+        None
+    };
+
+    Ok(SizeReport {
+        elf: elf_path.to_path_buf(),
+        totals: section_totals(&sections),
+        sections,
+        nso,
+        symbols,
+        objects,
+        crates,
+    })
+}
+
+fn section_totals(sections: &[SectionSize]) -> BTreeMap<SectionKind, u64> {
+    let mut totals: BTreeMap<_, _> = SectionKind::ALL.iter().map(|x| (*x, 0)).collect();
+    for section in sections {
+        *totals.entry(section.kind).or_default() += section.size;
+    }
+    totals
+}
+
+/// Read the allocated sections, the symbols and the size of each Rust crate
+fn read_elf(data: &[u8]) -> cu::Result<(Vec<SectionSize>, Vec<NamedSize>, Vec<NamedSize>)> {
+    let file = object::File::parse(data).map_err(|e| cu::fmterr!("invalid ELF: {e}"))?;
+
+    let mut sections = vec![];
+    let mut section_kinds = HashMap::new();
+    for section in file.sections() {
+        let object::SectionFlags::Elf { sh_flags } = section.flags() else {
+            continue;
+        };
+        let Some(kind) = section_kind(sh_flags, section.kind()) else {
+            continue;
+        };
+        section_kinds.insert(section.index(), kind);
+        if section.size() == 0 {
+            continue;
+        }
+        sections.push(SectionSize {
+            name: section.name().unwrap_or_default().to_string(),
+            kind,
+            size: section.size(),
+        });
+    }
+
+    // the symbol table could be stripped
+    let mut all_symbols = file.symbols().collect::<Vec<_>>();
+    if all_symbols.is_empty() {
+        all_symbols = file.dynamic_symbols().collect();
+    }
+    // aliases (like C1 and C2 constructors) are only counted once
+    let mut seen = std::collections::HashSet::new();
+    let mut symbols = vec![];
+    let mut crates = BTreeMap::<String, u64>::new();
+    for symbol in all_symbols {
+        if symbol.size() == 0
+            || !matches!(
+                symbol.kind(),
+                object::SymbolKind::Text | object::SymbolKind::Data | object::SymbolKind::Tls
+            )
+        {
+            continue;
+        }
+        let Some(kind) = symbol
+            .section_index()
+            .and_then(|x| section_kinds.get(&x).copied())
+        else {
+            continue;
+        };
+        if !seen.insert((symbol.address(), symbol.size())) {
+            continue;
+        }
+        let Ok(name) = symbol.name() else {
+            continue;
+        };
+        if let Some(krate) = rust_crate(name) {
+            *crates.entry(krate).or_default() += symbol.size();
+        }
+        symbols.push(NamedSize {
+            name: demangle(name),
+            kind: Some(kind),
+            size: symbol.size(),
+        });
+    }
+    sort_by_size(&mut symbols);
+    Ok((sections, symbols, into_sorted(crates)))
+}
+
+fn section_kind(sh_flags: u64, kind: object::SectionKind) -> Option<SectionKind> {
+    let sh_flags = sh_flags as u32;
+    if sh_flags & object::elf::SHF_ALLOC == 0 {
+        return None;
+    }
+    if sh_flags & object::elf::SHF_EXECINSTR != 0 {
+        return Some(SectionKind::Text);
+    }
+    if matches!(
+        kind,
+        object::SectionKind::UninitializedData | object::SectionKind::UninitializedTls
+    ) {
+        return Some(SectionKind::Bss);
+    }
+    if sh_flags & object::elf::SHF_WRITE != 0 {
+        return Some(SectionKind::Data);
+    }
+    Some(SectionKind::Rodata)
+}
+
+/// Demangle a C++ or Rust symbol. C symbols are returned as-is
+pub fn demangle(name: &str) -> String {
+    // without the check, C symbols like `f` are demangled as C++ types
+    if !name.starts_with("_Z") && !name.starts_with("_R") {
+        return name.to_string();
+    }
+    addr2line::demangle_auto(name.into(), None).into_owned()
+}
+
+/// Get the crate of a Rust symbol. Returns `None` if the symbol is not from Rust
+fn rust_crate(mangled: &str) -> Option<String> {
+    let is_v0 = mangled.starts_with("_R");
+    // legacy Rust symbols are like C++ symbols, but end with a hash (17h<16 hex digits>E)
+    let is_legacy = mangled.starts_with("_ZN")
+        && mangled.len() > 23
+        && mangled.ends_with('E')
+        && mangled[mangled.len() - 20..].starts_with("17h")
+        && mangled[mangled.len() - 17..mangled.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_hexdigit());
+    if !is_v0 && !is_legacy {
+        return None;
+    }
+    let path = addr2line::demangle(mangled, addr2line::gimli::DW_LANG_Rust)?;
+    let path = match path.strip_prefix('<') {
+        // <Type as Trait>::f, use the crate of the type if it has a path,
+        // otherwise the crate of the trait
+        Some(inner) => {
+            let (ty, tr) = inner.split_once(" as ").unwrap_or((inner, ""));
+            let path = if ty.contains("::") { ty } else { tr };
+            path.to_string()
+        }
+        None => path,
+    };
+    let path = path.trim_start_matches(['&', '*', '[', '(']);
+    let path = path.strip_prefix("mut ").unwrap_or(path);
+    let path = path.strip_prefix("dyn ").unwrap_or(path);
+    let (krate, _) = path.split_once("::")?;
+    if krate.is_empty() || !krate.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(krate.to_string())
+}
+
+/// Get the size of each input file from the link map, in the allocated sections
+fn object_sizes(map: &LinkMap, sections: &[SectionSize]) -> Vec<NamedSize> {
+    let allocated: HashSet<_> = sections.iter().map(|x| x.name.as_str()).collect();
+    let mut objects = BTreeMap::<String, u64>::new();
+    for section in &map.sections {
+        if allocated.contains(section.output.as_str()) {
+            *objects.entry(section.file.clone()).or_default() += section.size;
+        }
+    }
+    into_sorted(objects)
+}
+
+/// Read the sizes from the header of the NSO
+fn parse_nso_header(data: &[u8]) -> cu::Result<NsoSize> {
+    let header = NsoHeader::parse(data)?;
+    let segment = |segment: &NsoSegmentHeader| SegmentSize {
+        memory_size: u64::from(segment.size),
+        file_size: u64::from(segment.file_size),
+    };
+    Ok(NsoSize {
+        file_size: data.len() as u64,
+        memory_size: header.memory_size(),
+        text: segment(&header.text),
+        rodata: segment(&header.rodata),
+        data: segment(&header.data),
+        bss: u64::from(header.bss_size),
+    })
+}
+
+fn into_sorted(sizes: BTreeMap<String, u64>) -> Vec<NamedSize> {
+    let mut sizes = sizes
+        .into_iter()
+        .map(|(name, size)| NamedSize {
+            name,
+            kind: None,
+            size,
+        })
+        .collect::<Vec<_>>();
+    sort_by_size(&mut sizes);
+    sizes
+}
+
+fn sort_by_size(sizes: &mut [NamedSize]) {
+    sizes.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+}
+
+fn diff_named(old: &[NamedSize], new: &[NamedSize]) -> Vec<SizeChange> {
+    diff_sizes(
+        old.iter().map(|x| (&x.name, x.size)),
+        new.iter().map(|x| (&x.name, x.size)),
+    )
+}
+
+/// Compare sizes by name. Entries with the same name are added up
+fn diff_sizes<'a>(
+    old: impl IntoIterator<Item = (&'a String, u64)>,
+    new: impl IntoIterator<Item = (&'a String, u64)>,
+) -> Vec<SizeChange> {
+    let mut sizes = BTreeMap::<&String, (u64, u64)>::new();
+    for (name, size) in old {
+        sizes.entry(name).or_default().0 += size;
+    }
+    for (name, size) in new {
+        sizes.entry(name).or_default().1 += size;
+    }
+    let mut changes = sizes
+        .into_iter()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(name, (old, new))| SizeChange {
+            name: name.clone(),
+            old,
+            new,
+        })
+        .collect::<Vec<_>>();
+    changes.sort_by_key(|x| std::cmp::Reverse(x.old.abs_diff(x.new)));
+    changes
+}
+
+fn print_report(
+    report: &SizeReport,
+    changes: Option<&SizeChanges>,
+    budget: &SizeBudget,
+    top: usize,
+) {
+    println!("ELF: {}", report.elf.try_to_rel().display());
+    println!();
+    println!("{:<24} {:<8} {:>12}", "Section", "Kind", "Size");
+    for section in &report.sections {
+        let change = changes
+            .and_then(|x| x.sections.iter().find(|x| x.name == section.name))
+            .map(|x| format!(" {}", format_change(x)))
+            .unwrap_or_default();
+        println!(
+            "{:<24} {:<8} {:>12}{change}",
+            section.name,
+            section.kind.as_str(),
+            format_size(section.size)
+        );
+    }
+    println!();
+    println!("{:<24} {:>12}", "Total", "Size");
+    for (kind, size) in &report.totals {
+        let budget = match budget.get(kind.as_str()) {
+            Some(max) => {
+                let percent = *size as f64 / max.max(1) as f64 * 100.0;
+                let over = if *size > max { ", EXCEEDED" } else { "" };
+                format!(" of {} ({percent:.1}%{over})", format_size(max))
+            }
+            None => String::new(),
+        };
+        println!("{:<24} {:>12}{budget}", kind.as_str(), format_size(*size));
+    }
+
+    if let Some(nso) = &report.nso {
+        println!();
+        println!(
+            "NSO: {} in the file, {} in memory",
+            format_size(nso.file_size),
+            format_size(nso.memory_size)
+        );
+        for (name, segment) in [
+            ("text", nso.text),
+            ("rodata", nso.rodata),
+            ("data", nso.data),
+        ] {
+            println!(
+                "{name:<24} {:>12} ({} in the file)",
+                format_size(segment.memory_size),
+                format_size(segment.file_size)
+            );
+        }
+        println!("{:<24} {:>12}", "bss", format_size(nso.bss));
+    }
+
+    for (title, entries) in [
+        ("Largest symbols", &report.symbols),
+        ("Largest object files", &report.objects),
+        ("Largest crates", &report.crates),
+    ] {
+        if entries.is_empty() {
+            continue;
+        }
+        println!();
+        println!("{title} ({} of {}):", top.min(entries.len()), entries.len());
+        for entry in entries.iter().take(top) {
+            match entry.kind {
+                Some(kind) => println!(
+                    "{:>12} {:<8} {}",
+                    format_size(entry.size),
+                    kind.as_str(),
+                    entry.name
+                ),
+                None => println!("{:>12} {}", format_size(entry.size), entry.name),
+            }
+        }
+    }
+
+    let Some(changes) = changes else {
+        return;
+    };
+    for (title, entries) in [
+        ("Changed symbols", &changes.symbols),
+        ("Changed object files", &changes.objects),
+        ("Changed crates", &changes.crates),
+    ] {
+        println!();
+        if entries.is_empty() {
+            println!("{title}: none");
+            continue;
+        }
+        println!("{title} ({} of {}):", top.min(entries.len()), entries.len());
+        for entry in entries.iter().take(top) {
+            println!(
+                "{:>12} {:>12} -> {:<12} {}",
+                format_change(entry),
+                format_size(entry.old),
+                format_size(entry.new),
+                entry.name
+            );
+        }
+    }
+}
+
+fn format_size(size: u64) -> String {
+    if size < 1024 {
+        format!("{size} B")
+    } else if size < 1024 * 1024 {
+        format!("{:.1} KiB", size as f64 / 1024.0)
+    } else {
+        format!("{:.2} MiB", size as f64 / (1024.0 * 1024.0))
+    }
+}
+
+fn format_change(change: &SizeChange) -> String {
+    let sign = if change.new >= change.old { '+' } else { '-' };
+    format!("{sign}{}", format_size(change.old.abs_diff(change.new)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crate_from_symbol() {
+        assert_eq!(
+            rust_crate("_ZN4core3fmt5write17h0123456789abcdefE").as_deref(),
+            Some("core")
+        );
+        assert_eq!(
+            rust_crate("_ZN60_$LT$alloc..string..String$u20$as$u20$core..fmt..Display$GT$3fmt17h0123456789abcdefE")
+                .as_deref(),
+            Some("alloc")
+        );
+        // C++
+        assert_eq!(rust_crate("_ZN2nn2os11SleepThreadENS_8TimeSpanE"), None);
+        assert_eq!(rust_crate("main"), None);
+    }
+
+    #[test]
+    fn parse_nso() {
+        let mut data = vec![0u8; 0x100];
+        data[0..4].copy_from_slice(b"NSO0");
+        let mut put = |offset: usize, value: u32| {
+            data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        put(0x18, 0x3000); // text size
+        put(0x24, 0x3000); // rodata memory offset
+        put(0x28, 0x1000); // rodata size
+        put(0x34, 0x4000); // data memory offset
+        put(0x38, 0x800); // data size
+        put(0x3C, 0x1200); // bss size
+        put(0x60, 0x2000); // text file size
+        let nso = parse_nso_header(&data).unwrap();
+        assert_eq!(nso.text.memory_size, 0x3000);
+        assert_eq!(nso.text.file_size, 0x2000);
+        assert_eq!(nso.rodata.memory_size, 0x1000);
+        assert_eq!(nso.bss, 0x1200);
+        assert_eq!(nso.memory_size, 0x6000);
+    }
+}
@@ -0,0 +1,514 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Estimating the worst-case stack usage of the module, from the stack usage
+//! of each function reported by the compilers and the call graph in the ELF
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use object::{Object as _, ObjectSection as _, ObjectSymbol as _, SymbolKind};
+use yaxpeax_arm::armv8::a64::{Opcode, Operand};
+
+use crate::buildsys::disasm::DecodedInstruction;
+use crate::buildsys::link_map::{LinkMap, shorten_object_path};
+use crate::buildsys::size::demangle;
+
+/// Number of the deepest paths to report
+const DEEPEST_PATHS: usize = 5;
+
+/// Stack usage of a function, as reported by the compiler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSize {
+    pub size: u64,
+    /// The function also allocates a dynamic amount of stack (like `alloca`),
+    /// which is not included in the size
+    pub dynamic: bool,
+}
+
+/// A call path starting from a function that is not called by other functions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackPath {
+    /// Worst-case stack usage of the path
+    pub total: u64,
+    /// Demangled names of the functions in the path and their frame sizes
+    pub functions: Vec<(String, Option<FrameSize>)>,
+}
+
+impl StackPath {
+    /// Format the path as one line per function
+    pub fn to_lines(&self) -> Vec<String> {
+        self.functions
+            .iter()
+            .map(|(name, frame)| match frame {
+                None => format!("  ?      {name} (unknown)"),
+                Some(frame) if frame.dynamic => format!("  {:<6} {name} (dynamic)", frame.size),
+                Some(frame) => format!("  {:<6} {name}", frame.size),
+            })
+            .collect()
+    }
+}
+
+/// Result of the stack usage analysis
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackReport {
+    /// The deepest paths, deepest first
+    pub deepest: Vec<StackPath>,
+    /// Number of functions with stack usage from the compilers
+    pub known_functions: usize,
+    /// Number of functions without stack usage, like ones written in assembly
+    pub unknown_functions: usize,
+    /// Demangled names of the recursive functions, which are not counted more than once
+    pub recursive: Vec<String>,
+}
+
+impl StackReport {
+    /// Worst-case stack usage of the module
+    pub fn max_usage(&self) -> u64 {
+        self.deepest.first().map(|x| x.total).unwrap_or_default()
+    }
+}
+
+/// A function in the linked ELF
+struct Function {
+    /// Mangled names. Functions can have aliases, like C++ constructors
+    names: Vec<String>,
+    address: u64,
+    size: u64,
+    frame: Option<FrameSize>,
+    calls: Vec<usize>,
+    called: bool,
+}
+
+/// Analyze the stack usage of the functions in the ELF
+///
+/// Stack usage of C/C++ functions is read from the `.su` files next to the object files
+/// in the link inputs, and matched with the functions using the link map. Stack usage of
+/// Rust functions is read from the `.stack_sizes` section.
+pub fn analyze_stack(
+    file: &object::File,
+    instructions: &[DecodedInstruction],
+    map: &LinkMap,
+    root: &Path,
+    link_inputs: &[PathBuf],
+) -> StackReport {
+    let mut functions = read_functions(file);
+
+    // stack usage from rust
+    let stack_sizes = match file.section_by_name(".stack_sizes") {
+        Some(section) => parse_stack_sizes(section.data().unwrap_or_default()),
+        None => HashMap::new(),
+    };
+    // stack usage from C/C++, by object file
+    let su_files = load_su_files(link_inputs, root);
+    for function in &mut functions {
+        if let Some(size) = stack_sizes.get(&function.address) {
+            function.frame = Some(FrameSize {
+                size: *size,
+                dynamic: false,
+            });
+            continue;
+        }
+        let section_name = file
+            .sections()
+            .find(|x| x.address() <= function.address && function.address < x.address() + x.size())
+            .and_then(|x| x.name().ok().map(String::from));
+        let object_file = section_name
+            .and_then(|x| map.section_at(&x, function.address))
+            .map(|x| x.file.as_str());
+        function.frame = find_su_frame(&su_files, object_file, &function.names);
+    }
+
+    add_calls(&mut functions, instructions);
+
+    let known_functions = functions.iter().filter(|x| x.frame.is_some()).count();
+    let mut graph = CallGraph {
+        functions: &functions,
+        worst: vec![None; functions.len()],
+        on_stack: vec![false; functions.len()],
+        recursive: vec![false; functions.len()],
+    };
+    for i in 0..functions.len() {
+        graph.worst_case(i);
+    }
+
+    // roots are functions not called directly, like entry points, hooks and callbacks
+    let mut roots = (0..functions.len())
+        .filter(|i| !functions[*i].called)
+        .collect::<Vec<_>>();
+    roots.sort_by_key(|i| std::cmp::Reverse(graph.total(*i)));
+    let deepest = roots
+        .into_iter()
+        .take(DEEPEST_PATHS)
+        .map(|i| graph.path(i))
+        .collect();
+
+    StackReport {
+        deepest,
+        known_functions,
+        unknown_functions: functions.len() - known_functions,
+        recursive: functions
+            .iter()
+            .zip(&graph.recursive)
+            .filter(|(_, recursive)| **recursive)
+            .map(|(function, _)| demangle(&function.names[0]))
+            .collect(),
+    }
+}
+
+struct CallGraph<'a> {
+    functions: &'a [Function],
+    /// Worst-case stack usage and the callee on the worst path of each function
+    worst: Vec<Option<(u64, Option<usize>)>>,
+    on_stack: Vec<bool>,
+    recursive: Vec<bool>,
+}
+
+impl CallGraph<'_> {
+    fn worst_case(&mut self, i: usize) -> u64 {
+        if let Some((total, _)) = self.worst[i] {
+            return total;
+        }
+        if self.on_stack[i] {
+            // recursion, the function is only counted once
+            self.recursive[i] = true;
+            return 0;
+        }
+        self.on_stack[i] = true;
+        let mut deepest = None;
+        let mut deepest_total = 0;
+        for &callee in &self.functions[i].calls {
+            let total = self.worst_case(callee);
+            if deepest.is_none() || total > deepest_total {
+                deepest = Some(callee);
+                deepest_total = total;
+            }
+        }
+        self.on_stack[i] = false;
+        let frame = self.functions[i].frame.map(|x| x.size).unwrap_or_default();
+        let total = frame + deepest_total;
+        self.worst[i] = Some((total, deepest));
+        total
+    }
+
+    fn total(&self, i: usize) -> u64 {
+        self.worst[i].map(|x| x.0).unwrap_or_default()
+    }
+
+    fn path(&self, start: usize) -> StackPath {
+        let mut functions = vec![];
+        let mut visited = vec![false; self.functions.len()];
+        let mut next = Some(start);
+        while let Some(i) = next {
+            if visited[i] {
+                break;
+            }
+            visited[i] = true;
+            let function = &self.functions[i];
+            functions.push((demangle(&function.names[0]), function.frame));
+            next = self.worst[i].and_then(|x| x.1);
+        }
+        StackPath {
+            total: self.total(start),
+            functions,
+        }
+    }
+}
+
+/// Read the defined functions from the symbol table, sorted by address
+fn read_functions(file: &object::File) -> Vec<Function> {
+    let mut by_address = BTreeMap::<u64, Function>::new();
+    for symbol in file.symbols() {
+        if symbol.kind() != SymbolKind::Text || symbol.is_undefined() || symbol.size() == 0 {
+            continue;
+        }
+        let Ok(name) = symbol.name() else {
+            continue;
+        };
+        let function = by_address
+            .entry(symbol.address())
+            .or_insert_with(|| Function {
+                names: vec![],
+                address: symbol.address(),
+                size: symbol.size(),
+                frame: None,
+                calls: vec![],
+                called: false,
+            });
+        function.names.push(name.to_string());
+    }
+    by_address.into_values().collect()
+}
+
+/// Add the direct calls (`bl`) and tail calls (`b` to another function) to the functions
+fn add_calls(functions: &mut [Function], instructions: &[DecodedInstruction]) {
+    let find = |functions: &[Function], address: u64| {
+        let i = functions.partition_point(|x| x.address <= address);
+        let i = i.checked_sub(1)?;
+        let function = &functions[i];
+        (address < function.address + function.size).then_some(i)
+    };
+    for instruction in instructions {
+        let Some(inst) = &instruction.instruction else {
+            continue;
+        };
+        if !matches!(inst.opcode, Opcode::BL | Opcode::B) {
+            continue;
+        }
+        let Operand::PCOffset(offset) = inst.operands[0] else {
+            continue;
+        };
+        let target = instruction.address.wrapping_add_signed(offset);
+        let (Some(caller), Some(callee)) = (
+            find(functions, instruction.address),
+            find(functions, target),
+        ) else {
+            // calls to other modules through the PLT are not counted
+            continue;
+        };
+        if caller == callee && inst.opcode == Opcode::B {
+            // branch within the function
+            continue;
+        }
+        if !functions[caller].calls.contains(&callee) {
+            functions[caller].calls.push(callee);
+        }
+        if caller != callee {
+            functions[callee].called = true;
+        }
+    }
+}
+
+/// Load the `.su` files of the object files, by the object file relative to the root
+fn load_su_files(
+    link_inputs: &[PathBuf],
+    root: &Path,
+) -> HashMap<String, Vec<(String, FrameSize)>> {
+    let mut out = HashMap::new();
+    for input in link_inputs {
+        let su_path = input.with_extension("su");
+        let Ok(content) = cu::fs::read_string(&su_path) else {
+            continue;
+        };
+        let object_file = shorten_object_path(&input.display().to_string(), root);
+        out.insert(object_file, parse_su(&content));
+    }
+    out
+}
+
+/// Find the stack usage of a function in the `.su` file of the object file it is from
+///
+/// Static functions in different object files can have the same name, so the stack
+/// usage is unknown if the object file is not known
+fn find_su_frame(
+    su_files: &HashMap<String, Vec<(String, FrameSize)>>,
+    object_file: Option<&str>,
+    names: &[String],
+) -> Option<FrameSize> {
+    let entries = su_files.get(object_file?)?;
+    let demangled = names.iter().map(|x| demangle(x)).collect::<Vec<_>>();
+    entries
+        .iter()
+        .find(|(su_name, _)| demangled.iter().any(|x| su_name_matches(su_name, x)))
+        .map(|(_, frame)| *frame)
+}
+
+/// Parse the `.su` file generated by `-fstack-usage`, with lines like
+/// `main.cpp:3:5:int foo(int)\t16\tstatic`
+fn parse_su(content: &str) -> Vec<(String, FrameSize)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let location = parts.next()?;
+            let size = parts.next()?.trim().parse().ok()?;
+            let qualifier = parts.next().unwrap_or_default();
+            // <file>:<line>:<column>:<name>, the name can contain `:`
+            let name = location.splitn(4, ':').nth(3)?;
+            let frame = FrameSize {
+                size,
+                dynamic: qualifier.starts_with("dynamic"),
+            };
+            Some((name.to_string(), frame))
+        })
+        .collect()
+}
+
+/// Check if the name in the `.su` file is the demangled function.
+/// The names of C++ functions have the return type, like `int ns::foo(int)`
+fn su_name_matches(su_name: &str, demangled: &str) -> bool {
+    su_name == demangled
+        || su_name
+            .strip_suffix(demangled)
+            .is_some_and(|x| x.ends_with(' '))
+}
+
+/// Parse the `.stack_sizes` section generated by `-Zemit-stack-sizes`,
+/// which has the address (8 bytes) and the stack usage (ULEB128) of each function
+fn parse_stack_sizes(mut data: &[u8]) -> HashMap<u64, u64> {
+    let mut out = HashMap::new();
+    while data.len() > 8 {
+        let address = u64::from_le_bytes(data[..8].try_into().unwrap());
+        data = &data[8..];
+        let mut size = 0u64;
+        let mut shift = 0;
+        loop {
+            let Some((byte, rest)) = data.split_first() else {
+                return out;
+            };
+            data = rest;
+            if shift < 64 {
+                size |= u64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        out.insert(address, size);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stack_usage() {
+        let su = "a.cpp:2:12:int h(int)\t32\tdynamic
+a.cpp:1:20:int ns::g(int)\t8\tstatic
+c.c:2:5:cfun\t32\tstatic
+";
+        let entries = parse_su(su);
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "int h(int)".to_string(),
+                    FrameSize {
+                        size: 32,
+                        dynamic: true
+                    }
+                ),
+                (
+                    "int ns::g(int)".to_string(),
+                    FrameSize {
+                        size: 8,
+                        dynamic: false
+                    }
+                ),
+                (
+                    "cfun".to_string(),
+                    FrameSize {
+                        size: 32,
+                        dynamic: false
+                    }
+                ),
+            ]
+        );
+        assert!(su_name_matches("int ns::g(int)", "ns::g(int)"));
+        assert!(su_name_matches("cfun", "cfun"));
+        assert!(!su_name_matches("int ns::g(int)", "g(int)"));
+
+        // static functions with the same name in different object files
+        let frame = |size| FrameSize {
+            size,
+            dynamic: false,
+        };
+        let su_files = HashMap::from([
+            ("a.o".to_string(), vec![("helper".to_string(), frame(16))]),
+            ("b.o".to_string(), vec![("helper".to_string(), frame(64))]),
+        ]);
+        let names = ["helper".to_string()];
+        assert_eq!(
+            find_su_frame(&su_files, Some("b.o"), &names),
+            Some(frame(64))
+        );
+        assert_eq!(find_su_frame(&su_files, Some("c.o"), &names), None);
+        assert_eq!(find_su_frame(&su_files, None, &names), None);
+
+        let mut stack_sizes = vec![];
+        stack_sizes.extend(0x1000u64.to_le_bytes());
+        stack_sizes.push(0x30);
+        stack_sizes.extend(0x2000u64.to_le_bytes());
+        stack_sizes.extend([0x80, 0x02]);
+        let stack_sizes = parse_stack_sizes(&stack_sizes);
+        assert_eq!(stack_sizes.get(&0x1000), Some(&0x30));
+        assert_eq!(stack_sizes.get(&0x2000), Some(&0x100));
+    }
+
+    #[test]
+    fn worst_case_path() {
+        let function = |name: &str, size: u64, calls: Vec<usize>| Function {
+            names: vec![name.to_string()],
+            address: 0,
+            size: 4,
+            frame: Some(FrameSize {
+                size,
+                dynamic: false,
+            }),
+            calls,
+            called: false,
+        };
+        // main -> a -> b -> a (recursion), main -> c
+        let functions = vec![
+            function("main", 16, vec![1, 3]),
+            function("a", 32, vec![2]),
+            function("b", 64, vec![1]),
+            function("c", 48, vec![]),
+        ];
+        let mut graph = CallGraph {
+            functions: &functions,
+            worst: vec![None; functions.len()],
+            on_stack: vec![false; functions.len()],
+            recursive: vec![false; functions.len()],
+        };
+        assert_eq!(graph.worst_case(0), 16 + 32 + 64);
+        assert_eq!(graph.recursive, vec![false, true, false, false]);
+        let path = graph.path(0);
+        let names = path
+            .functions
+            .iter()
+            .map(|x| x.0.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["main", "a", "b"]);
+    }
+
+    #[test]
+    fn calls_from_instructions() {
+        use yaxpeax_arch::{Decoder as _, U8Reader};
+        use yaxpeax_arm::armv8::a64::InstDecoder;
+
+        let function = |address: u64| Function {
+            names: vec![],
+            address,
+            size: 8,
+            frame: None,
+            calls: vec![],
+            called: false,
+        };
+        let mut functions = vec![function(0), function(8), function(0x10)];
+        // 0x0: bl 0x8, 0x4: b 0x4 (loop), 0x8: b 0x10 (tail call), 0xc: bl 0x1000 (PLT)
+        let instructions = [0x94000002u32, 0x14000000, 0x14000002, 0x940003fd]
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let bytes = word.to_le_bytes();
+                let mut reader = U8Reader::new(&bytes);
+                DecodedInstruction {
+                    address: i as u64 * 4,
+                    instruction: InstDecoder::default().decode(&mut reader).ok(),
+                }
+            })
+            .collect::<Vec<_>>();
+        add_calls(&mut functions, &instructions);
+        let calls = functions
+            .iter()
+            .map(|x| x.calls.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(calls, vec![vec![1], vec![2], vec![]]);
+        let called = functions.iter().map(|x| x.called).collect::<Vec<_>>();
+        assert_eq!(called, vec![false, true, true]);
+    }
+}
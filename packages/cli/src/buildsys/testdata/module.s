@@ -0,0 +1,47 @@
+// Source of module.elf, a minimal module linked with the Megaton linker script
+// for the NSO tests, and its link map module.map. To regenerate:
+//
+//   llvm-mc -triple=aarch64-none-elf -filetype=obj module.s -o module.o
+//   ld.lld -T packages/lib/link.ld --shared --export-dynamic --build-id=sha1 \
+//     -z nodynamic-undefined-weak -z max-page-size=0x1000 --strip-all module.o -o module.elf \
+//     -Map=module.map
+
+    .section .text.crt0, "ax", %progbits
+    .global __module_start
+__module_start:
+    b main
+
+    .text
+    .global main
+    .type main, %function
+main:
+    adrp x0, counter
+    ldr w1, [x0, :lo12:counter]
+    add w1, w1, #1
+    str w1, [x0, :lo12:counter]
+    adrp x2, message
+    add x2, x2, :lo12:message
+    ret
+
+    .section .nx-module-name, "a", %progbits
+    .word 0
+    .word 9
+    .ascii "test_mod\0"
+
+    .section .rodata.message, "a", %progbits
+message:
+    .asciz "hello from megaton"
+
+    .data
+    .hidden value
+value:
+    .quad 0x1122334455667788
+    .quad message
+
+    .bss
+    .global counter
+    .hidden counter
+counter:
+    .zero 0x2345
+
+    .section .note.GNU-stack, "", %progbits
@@ -0,0 +1,21 @@
+// Source of tls.o, an object with a thread-local variable for the link tests.
+// To regenerate:
+//
+//   llvm-mc -triple=aarch64-none-elf -filetype=obj tls.s -o tls.o
+
+    .text
+    .global get_counter
+    .type get_counter, %function
+get_counter:
+    mrs x0, tpidr_el0
+    add x0, x0, :tprel_hi12:counter
+    add x0, x0, :tprel_lo12_nc:counter
+    ret
+
+    .section .tbss, "awT", %nobits
+    .global counter
+    .type counter, %object
+    .p2align 2
+counter:
+    .zero 4
+    .size counter, 4
@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Recording how long each phase of the build takes, enabled with `--timings`
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use cu::pre::*;
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+/// Number of translation units shown in the slowest list
+const SLOWEST_COUNT: usize = 10;
+
+struct Recorder {
+    start: Instant,
+    /// Directory to write the report into, set once the profile is resolved
+    report_dir: Option<PathBuf>,
+    entries: Vec<TimingEntry>,
+}
+
+/// Kind of a timed span
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimingKind {
+    /// A phase of the build, like linking
+    Phase,
+    /// Compiling one translation unit
+    Compile,
+}
+
+/// A recorded span, in seconds since the start of the build
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimingEntry {
+    pub kind: TimingKind,
+    pub name: String,
+    pub start: f64,
+    pub duration: f64,
+    /// Row in the timeline, such that entries in the same lane don't overlap
+    pub lane: usize,
+}
+
+/// Start recording timings for this build
+pub fn enable() {
+    if let Ok(mut recorder) = RECORDER.lock() {
+        *recorder = Some(Recorder {
+            start: Instant::now(),
+            report_dir: None,
+            entries: vec![],
+        });
+    }
+}
+
+/// Set the directory to write the report into
+pub fn set_report_dir(dir: &Path) {
+    if let Ok(mut recorder) = RECORDER.lock()
+        && let Some(recorder) = recorder.as_mut()
+    {
+        recorder.report_dir = Some(dir.to_path_buf());
+    }
+}
+
+/// Start timing a span. The span is recorded when the returned guard is dropped.
+/// Does nothing if timings are not enabled
+pub fn span(kind: TimingKind, name: impl Into<String>) -> Span {
+    let enabled = RECORDER.lock().is_ok_and(|x| x.is_some());
+    Span {
+        inner: enabled.then(|| (kind, name.into(), Instant::now())),
+    }
+}
+
+/// Guard returned by [`span`]
+#[must_use]
+pub struct Span {
+    inner: Option<(TimingKind, String, Instant)>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some((kind, name, start)) = self.inner.take() else {
+            return;
+        };
+        let end = Instant::now();
+        if let Ok(mut recorder) = RECORDER.lock()
+            && let Some(recorder) = recorder.as_mut()
+        {
+            recorder.entries.push(TimingEntry {
+                kind,
+                name,
+                start: start
+                    .saturating_duration_since(recorder.start)
+                    .as_secs_f64(),
+                duration: end.saturating_duration_since(start).as_secs_f64(),
+                lane: 0,
+            });
+        }
+    }
+}
+
+/// The report written to `timings.json`
+#[derive(Debug, Serialize)]
+struct Report {
+    total: f64,
+    max_concurrency: usize,
+    entries: Vec<TimingEntry>,
+}
+
+/// Stop recording, and write the report if timings are enabled
+pub fn finish() -> cu::Result<()> {
+    let recorder = match RECORDER.lock() {
+        Ok(mut recorder) => recorder.take(),
+        Err(_) => None,
+    };
+    let Some(recorder) = recorder else {
+        return Ok(());
+    };
+    let Some(report_dir) = recorder.report_dir else {
+        cu::warn!("build failed before timings could be reported");
+        return Ok(());
+    };
+    let total = recorder.start.elapsed().as_secs_f64();
+    let mut entries = recorder.entries;
+    entries.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let max_concurrency = assign_lanes(&mut entries, TimingKind::Compile);
+    assign_lanes(&mut entries, TimingKind::Phase);
+    let report = Report {
+        total,
+        max_concurrency,
+        entries,
+    };
+
+    let json_path = report_dir.join("timings.json");
+    let html_path = report_dir.join("timings.html");
+    cu::fs::write_json_pretty(&json_path, &report)?;
+    cu::fs::write(&html_path, render_html(&report))?;
+
+    cu::info!("build timings: {:.2}s", report.total);
+    for entry in report
+        .entries
+        .iter()
+        .filter(|x| x.kind == TimingKind::Phase)
+    {
+        cu::info!("  {:<20} {:>8.2}s", entry.name, entry.duration);
+    }
+    cu::info!("timing report: {}", html_path.try_to_rel().display());
+    Ok(())
+}
+
+/// Assign lanes to entries of the kind, sorted by start time,
+/// and return the number of lanes used (i.e. the max concurrency)
+fn assign_lanes(entries: &mut [TimingEntry], kind: TimingKind) -> usize {
+    // end time of the last entry in each lane
+    let mut lane_ends: Vec<f64> = vec![];
+    for entry in entries.iter_mut().filter(|x| x.kind == kind) {
+        let lane = match lane_ends.iter().position(|end| *end <= entry.start) {
+            Some(lane) => lane,
+            None => {
+                lane_ends.push(0.0);
+                lane_ends.len() - 1
+            }
+        };
+        lane_ends[lane] = entry.start + entry.duration;
+        entry.lane = lane;
+    }
+    lane_ends.len()
+}
+
+/// Render the report as a self-contained HTML page
+fn render_html(report: &Report) -> String {
+    let total = report.total.max(f64::EPSILON);
+    let mut out = String::new();
+    out.push_str(concat!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Megaton Build Timings</title>\n",
+        "<style>\n",
+        "body { font-family: sans-serif; margin: 20px; }\n",
+        ".timeline { position: relative; border: 1px solid #ccc; margin-bottom: 20px; }\n",
+        ".bar { position: absolute; height: 18px; font-size: 11px; overflow: hidden; white-space: nowrap; ",
+        "box-sizing: border-box; border: 1px solid #fff; }\n",
+        ".phase { background: #f0a860; }\n",
+        ".compile { background: #6fa8dc; }\n",
+        "table { border-collapse: collapse; }\n",
+        "td, th { border: 1px solid #ccc; padding: 2px 8px; text-align: left; }\n",
+        "</style></head><body>\n",
+    ));
+    let _ = writeln!(
+        out,
+        "<h1>Build Timings</h1>\n<p>Total: {:.2}s, max concurrency: {}</p>",
+        report.total, report.max_concurrency
+    );
+
+    for (title, kind, class) in [
+        ("Phases", TimingKind::Phase, "phase"),
+        ("Compile Jobs", TimingKind::Compile, "compile"),
+    ] {
+        let entries = report.entries.iter().filter(|x| x.kind == kind);
+        let lanes = entries.clone().map(|x| x.lane + 1).max().unwrap_or(0);
+        let _ = writeln!(
+            out,
+            "<h2>{title}</h2>\n<div class=\"timeline\" style=\"height: {}px\">",
+            lanes * 20
+        );
+        for entry in entries {
+            let name = escape_html(&entry.name);
+            let _ = writeln!(
+                out,
+                "<div class=\"bar {class}\" style=\"left: {:.3}%; width: {:.3}%; top: {}px\" \
+                 title=\"{name} ({:.2}s)\">{name}</div>",
+                entry.start / total * 100.0,
+                (entry.duration / total * 100.0).max(0.1),
+                entry.lane * 20,
+                entry.duration,
+            );
+        }
+        out.push_str("</div>\n");
+    }
+
+    let mut slowest = report
+        .entries
+        .iter()
+        .filter(|x| x.kind == TimingKind::Compile)
+        .collect::<Vec<_>>();
+    slowest.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+    out.push_str(
+        "<h2>Slowest Translation Units</h2>\n<table>\n<tr><th>Source</th><th>Time</th></tr>\n",
+    );
+    for entry in slowest.into_iter().take(SLOWEST_COUNT) {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{:.2}s</td></tr>",
+            escape_html(&entry.name),
+            entry.duration
+        );
+    }
+    out.push_str("</table>\n</body></html>\n");
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lanes_show_concurrency() {
+        let entry = |start: f64, duration: f64| TimingEntry {
+            kind: TimingKind::Compile,
+            name: String::new(),
+            start,
+            duration,
+            lane: 0,
+        };
+        // 0 [0, 2), 1 [0.5, 1.5), 2 [1.5, 3), 3 [2, 4)
+        let mut entries = vec![
+            entry(0.0, 2.0),
+            entry(0.5, 1.0),
+            entry(1.5, 1.5),
+            entry(2.0, 2.0),
+        ];
+        assert_eq!(assign_lanes(&mut entries, TimingKind::Compile), 2);
+        let lanes = entries.iter().map(|x| x.lane).collect::<Vec<_>>();
+        assert_eq!(lanes, vec![0, 1, 1, 0]);
+    }
+}
@@ -3,7 +3,10 @@
 
 use cu::pre::*;
 
-use crate::cmds::{CmdBuild, CmdToolchain, CmdVersion};
+use crate::cmds::{
+    CmdBuild, CmdCheck, CmdClean, CmdConfig, CmdCrashReport, CmdDeploy, CmdInit, CmdNew,
+    CmdPackage, CmdSize, CmdSymbolize, CmdSymbols, CmdToolchain, CmdVersion, CmdWatch,
+};
 
 static LOGO: &str = r#"
  __    __ ______ ______ ______ ______ ______ __   __  
@@ -33,6 +36,13 @@ impl Cmd {
         if let Some(command) = &self.command {
             self.flags.merge(command.as_ref());
         }
+        // logs are printed to stdout, which would mix with the JSON messages
+        if let Some(CmdSubcommand::Build(cmd)) = &self.command
+            && cmd.is_message_json()
+        {
+            self.flags.verbose = 0;
+            self.flags.quiet = 2;
+        }
     }
     pub fn run(self) -> cu::Result<()> {
         if self.version || matches!(self.command, Some(CmdSubcommand::Version(_))) {
@@ -44,7 +54,19 @@ impl Cmd {
             return Ok(());
         };
         match command {
+            CmdSubcommand::New(cmd) => cmd.run()?,
+            CmdSubcommand::Init(cmd) => cmd.run()?,
             CmdSubcommand::Build(cmd) => cmd.run()?,
+            CmdSubcommand::Check(cmd) => cmd.run()?,
+            CmdSubcommand::Size(cmd) => cmd.run()?,
+            CmdSubcommand::Watch(cmd) => cmd.run()?,
+            CmdSubcommand::Clean(cmd) => cmd.run()?,
+            CmdSubcommand::Package(cmd) => cmd.run()?,
+            CmdSubcommand::Deploy(cmd) => cmd.run()?,
+            CmdSubcommand::CrashReport(cmd) => cmd.run()?,
+            CmdSubcommand::Symbolize(cmd) => cmd.run()?,
+            CmdSubcommand::Symbols(cmd) => cmd.run()?,
+            CmdSubcommand::Config(cmd) => cmd.run()?,
             CmdSubcommand::Toolchain(cmd) => cmd.run()?,
             CmdSubcommand::Version(_) => {}
         }
@@ -55,8 +77,33 @@ impl Cmd {
 
 #[derive(clap::Subcommand)]
 pub enum CmdSubcommand {
+    /// Create a new project in a new directory
+    New(CmdNew),
+    /// Create a new project in the current directory
+    Init(CmdInit),
     /// Build the project into an executable
     Build(CmdBuild),
+    /// Build the project, and rebuild when files change
+    Watch(CmdWatch),
+    /// Check a built ELF for missing symbols and disallowed instructions
+    Check(CmdCheck),
+    /// Show the size of the built module by section, symbol, object file and crate
+    Size(CmdSize),
+    /// Remove build artifacts
+    Clean(CmdClean),
+    /// Build the project and package it into mod folders
+    Package(CmdPackage),
+    /// Build the project and upload it to the console over FTP, or pull logs and crash reports
+    Deploy(CmdDeploy),
+    /// Symbolize an Atmosphere crash report with the built module
+    CrashReport(CmdCrashReport),
+    /// Print the function and source location of offsets in the module
+    #[clap(alias = "addr2line")]
+    Symbolize(CmdSymbolize),
+    /// Generate symbol files for the check from the game
+    Symbols(CmdSymbols),
+    /// Inspect the resolved configuration
+    Config(CmdConfig),
     /// Manage the custom `megaton` Rust toolchain
     Toolchain(CmdToolchain),
     /// Print the version. -v to show toolchain information. -q to only print the version number
@@ -66,7 +113,19 @@ pub enum CmdSubcommand {
 impl AsRef<cu::cli::Flags> for CmdSubcommand {
     fn as_ref(&self) -> &cu::cli::Flags {
         match self {
+            CmdSubcommand::New(cmd) => cmd.as_ref(),
+            CmdSubcommand::Init(cmd) => cmd.as_ref(),
             CmdSubcommand::Build(cmd) => cmd.as_ref(),
+            CmdSubcommand::Check(cmd) => cmd.as_ref(),
+            CmdSubcommand::Size(cmd) => cmd.as_ref(),
+            CmdSubcommand::Watch(cmd) => cmd.as_ref(),
+            CmdSubcommand::Clean(cmd) => cmd.as_ref(),
+            CmdSubcommand::Package(cmd) => cmd.as_ref(),
+            CmdSubcommand::Deploy(cmd) => cmd.as_ref(),
+            CmdSubcommand::CrashReport(cmd) => cmd.as_ref(),
+            CmdSubcommand::Symbolize(cmd) => cmd.as_ref(),
+            CmdSubcommand::Symbols(cmd) => cmd.as_ref(),
+            CmdSubcommand::Config(cmd) => cmd.as_ref(),
             CmdSubcommand::Toolchain(cmd) => cmd.as_ref(),
             CmdSubcommand::Version(cmd) => cmd.as_ref(),
         }
@@ -4,7 +4,7 @@
 use cu::pre::*;
 
 use crate::{
-    buildsys::{self, BuildArgs},
+    buildsys::{self, BuildArgs, MessageFormat},
     env,
 };
 
@@ -20,8 +20,17 @@ pub struct CmdBuild {
 }
 
 impl CmdBuild {
+    /// If JSON messages are printed, in which case the logs should be disabled
+    pub fn is_message_json(&self) -> bool {
+        self.args.message_format == MessageFormat::Json
+    }
+
     pub fn run(self) -> cu::Result<()> {
-        env::init()?;
-        cu::co::run(async move { buildsys::run(self.args).await })
+        if let Err(e) = env::init() {
+            buildsys::emit_build_failed(self.args.message_format, &e);
+            return Err(e);
+        }
+        cu::co::run(async move { buildsys::run(self.args).await })?;
+        Ok(())
     }
 }
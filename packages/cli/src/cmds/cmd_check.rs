@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::{
+    buildsys::{self, CheckArgs},
+    env,
+};
+
+/// The `check` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdCheck {
+    #[clap(flatten)]
+    args: CheckArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdCheck {
+    pub fn run(self) -> cu::Result<()> {
+        env::init()?;
+        cu::co::run(async move { buildsys::check_elf(self.args).await })
+    }
+}
@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::buildsys::{self, CleanArgs};
+
+/// The `clean` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdClean {
+    #[clap(flatten)]
+    args: CleanArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdClean {
+    pub fn run(self) -> cu::Result<()> {
+        cu::co::run(async move { buildsys::clean(self.args).await })
+    }
+}
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::config::{self, Build, CargoConfig, Check, SourcedFlags};
+use crate::env::{self, Compiler};
+
+/// The `config` subcommand
+#[derive(Debug, clap::Parser)]
+pub struct CmdConfig {
+    #[clap(subcommand)]
+    command: CmdConfigSubcommand,
+}
+
+impl CmdConfig {
+    pub fn run(self) -> cu::Result<()> {
+        match self.command {
+            CmdConfigSubcommand::Show {
+                profile,
+                format,
+                config,
+                ..
+            } => show(&profile, format, config.as_deref()),
+        }
+    }
+}
+
+impl AsRef<cu::cli::Flags> for CmdConfig {
+    fn as_ref(&self) -> &cu::cli::Flags {
+        match &self.command {
+            CmdConfigSubcommand::Show { common, .. } => common,
+        }
+    }
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum CmdConfigSubcommand {
+    /// Print the configuration resolved for a profile, including the final flags
+    /// and where each flag comes from
+    Show {
+        /// Select profile to resolve
+        ///
+        /// See https://megaton-new.pistonite.dev/tutorial/profiles
+        #[clap(short, long, default_value = "none")]
+        profile: String,
+
+        /// Output format
+        #[clap(short, long, default_value = "toml")]
+        format: ConfigFormat,
+
+        /// Specify the location of the Megaton.toml config file
+        #[clap(short = 'c', long)]
+        config: Option<String>,
+
+        #[clap(flatten)]
+        common: cu::cli::Flags,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+/// Configuration resolved for a profile
+#[derive(Debug, Serialize)]
+struct ResolvedConfig<'a> {
+    profile: &'a str,
+    build: Build,
+    check: Option<Check>,
+    cargo: &'a CargoConfig,
+    compiler: Compiler,
+    flags: SourcedFlags,
+}
+
+fn show(profile: &str, format: ConfigFormat, config: Option<&str>) -> cu::Result<()> {
+    let (_, manifest_path) = config::get_root_and_manifest(config)?;
+    let config = config::load(&manifest_path)?;
+    let profile = config.profile.resolve(profile)?;
+
+    // the default flags depend on the compiler
+    let compiler = match env::init() {
+        Ok(()) => env::get().compiler(),
+        Err(e) => {
+            cu::warn!("failed to detect the compiler, showing the default flags of gcc: {e:?}");
+            Compiler::Gcc
+        }
+    };
+    let flags = {
+        let base = &config.build.base().flags;
+        let named = config.build.profile(profile).map(|x| (profile, &x.flags));
+        SourcedFlags::resolve(base, named, compiler)
+    };
+    let resolved = ResolvedConfig {
+        profile,
+        build: config.build.get_profile(profile),
+        check: config.check.as_ref().map(|x| x.get_profile(profile)),
+        cargo: &config.cargo,
+        compiler,
+        flags,
+    };
+    let output = match format {
+        ConfigFormat::Toml => toml::stringify_pretty(&resolved)?,
+        ConfigFormat::Json => json::stringify_pretty(&resolved)?,
+    };
+    // printed as-is so the output can be piped to other tools
+    cu::lv::disable_print_time();
+    println!("{}", output.trim_end());
+    Ok(())
+}
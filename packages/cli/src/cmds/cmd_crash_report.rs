@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::symbolize::{self, CrashReportArgs};
+
+/// The `crash-report` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdCrashReport {
+    #[clap(flatten)]
+    args: CrashReportArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdCrashReport {
+    pub fn run(self) -> cu::Result<()> {
+        symbolize::symbolize_crash_report(self.args)
+    }
+}
@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::{
+    deploy::{self, DeployArgs},
+    env,
+};
+
+/// The `deploy` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdDeploy {
+    #[clap(flatten)]
+    args: DeployArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdDeploy {
+    pub fn run(self) -> cu::Result<()> {
+        // pulling does not build
+        if !self.args.pull {
+            env::init()?;
+        }
+        cu::co::run(async move { deploy::deploy(self.args).await })
+    }
+}
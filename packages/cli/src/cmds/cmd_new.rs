@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use std::path::PathBuf;
+
+use cu::pre::*;
+
+use crate::scaffold::{self, InitArgs};
+
+/// The `new` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdNew {
+    /// Directory to create the project in. Must not exist or be empty
+    path: PathBuf,
+
+    #[clap(flatten)]
+    args: InitArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdNew {
+    pub fn run(self) -> cu::Result<()> {
+        if self.path.exists() && !cu::fs::is_empty_dir(&self.path)? {
+            cu::bail!(
+                "'{}' already exists and is not empty; use `megaton init` to initialize an existing directory",
+                self.path.display()
+            );
+        }
+        cu::fs::make_dir(&self.path)?;
+        scaffold::init(&self.path.normalize()?, &self.args)
+    }
+}
+
+/// The `init` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdInit {
+    #[clap(flatten)]
+    args: InitArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdInit {
+    pub fn run(self) -> cu::Result<()> {
+        let root = PathBuf::from(".").normalize()?;
+        scaffold::init(&root, &self.args)
+    }
+}
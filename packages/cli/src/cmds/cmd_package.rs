@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::{
+    buildsys::{self, PackageArgs},
+    env,
+};
+
+/// The `package` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdPackage {
+    #[clap(flatten)]
+    args: PackageArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdPackage {
+    pub fn run(self) -> cu::Result<()> {
+        env::init()?;
+        cu::co::run(async move { buildsys::package(self.args).await })
+    }
+}
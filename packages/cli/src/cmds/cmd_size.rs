@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::buildsys::{self, SizeArgs};
+
+/// The `size` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdSize {
+    #[clap(flatten)]
+    args: SizeArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdSize {
+    pub fn run(self) -> cu::Result<()> {
+        buildsys::size_module(self.args)
+    }
+}
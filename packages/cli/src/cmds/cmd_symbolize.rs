@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::symbolize::{self, SymbolizeArgs};
+
+/// The `symbolize` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdSymbolize {
+    #[clap(flatten)]
+    args: SymbolizeArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdSymbolize {
+    pub fn run(self) -> cu::Result<()> {
+        symbolize::symbolize_offsets(self.args)
+    }
+}
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::symbols::{self, ExtractArgs};
+
+/// The `symbols` subcommand
+#[derive(Debug, clap::Parser)]
+pub struct CmdSymbols {
+    #[clap(subcommand)]
+    command: CmdSymbolsSubcommand,
+}
+
+impl CmdSymbols {
+    pub fn run(self) -> cu::Result<()> {
+        match self.command {
+            CmdSymbolsSubcommand::Extract { args, .. } => symbols::extract_symbols(args),
+        }
+    }
+}
+
+impl AsRef<cu::cli::Flags> for CmdSymbols {
+    fn as_ref(&self) -> &cu::cli::Flags {
+        match &self.command {
+            CmdSymbolsSubcommand::Extract { common, .. } => common,
+        }
+    }
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum CmdSymbolsSubcommand {
+    /// Extract the dynamic symbols from modules of the game into a `.syms` file
+    /// for `check.symbols`. Multiple modules or `.syms` files are merged into one
+    Extract {
+        #[clap(flatten)]
+        args: ExtractArgs,
+
+        #[clap(flatten)]
+        common: cu::cli::Flags,
+    },
+}
@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use crate::{
+    env,
+    watch::{self, WatchArgs},
+};
+
+/// The `watch` subcommand
+#[derive(Debug, AsRef, clap::Parser)]
+pub struct CmdWatch {
+    #[clap(flatten)]
+    args: WatchArgs,
+
+    #[clap(flatten)]
+    #[as_ref]
+    common: cu::cli::Flags,
+}
+
+impl CmdWatch {
+    pub fn run(self) -> cu::Result<()> {
+        env::init()?;
+        cu::co::run(async move { watch::watch(self.args).await })
+    }
+}
@@ -8,5 +8,27 @@ mod cmd_version;
 use cmd_version::*;
 mod cmd_build;
 use cmd_build::*;
+mod cmd_check;
+use cmd_check::*;
+mod cmd_size;
+use cmd_size::*;
+mod cmd_watch;
+use cmd_watch::*;
+mod cmd_clean;
+use cmd_clean::*;
+mod cmd_package;
+use cmd_package::*;
+mod cmd_deploy;
+use cmd_deploy::*;
+mod cmd_crash_report;
+use cmd_crash_report::*;
+mod cmd_symbolize;
+use cmd_symbolize::*;
+mod cmd_symbols;
+use cmd_symbols::*;
+mod cmd_config;
+use cmd_config::*;
 mod cmd_toolchain;
 use cmd_toolchain::*;
+mod cmd_new;
+use cmd_new::*;
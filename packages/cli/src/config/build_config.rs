@@ -1,11 +1,12 @@
 // SPDX-License-Identifier: MIT
 // Copyright (c) 2025-2026 Megaton contributors
 
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use cu::pre::*;
 
-use super::{CaptureUnused, ExtendProfile, FlagConfig, Validate, ValidateCtx};
+use super::{CaptureUnused, ExtendProfile, FlagConfig, FlagOverride, Validate, ValidateCtx};
 
 /// Config in the `[build]` section
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -38,6 +39,17 @@ pub struct Build {
     #[serde(default)]
     pub flags: FlagConfig,
 
+    /// Flags for the sources matching glob patterns, relative to Megaton.toml
+    #[serde(default)]
+    pub overrides: BTreeMap<String, FlagOverride>,
+
+    #[serde(default)]
+    pub embed: Embed,
+
+    /// Headers to precompile and include in all C++ sources, relative to Megaton.toml
+    #[serde(default)]
+    pub pch: Vec<PathBuf>,
+
     #[serde(flatten, default)]
     unused: CaptureUnused,
 }
@@ -45,6 +57,14 @@ pub struct Build {
 impl Validate for Build {
     fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
         self.flags.validate_property(ctx, "flags")?;
+        for (pattern, flags) in &self.overrides {
+            if let Err(e) = globset::Glob::new(pattern) {
+                cu::error!("invalid pattern in build.overrides: {e}");
+                ctx.bail()?;
+            }
+            flags.validate_property(ctx, &format!("overrides.\"{pattern}\""))?;
+        }
+        self.embed.validate_property(ctx, "embed")?;
         self.unused.validate(ctx)?;
         Ok(())
     }
@@ -59,5 +79,65 @@ impl ExtendProfile for Build {
         self.ldscripts.extend(other.ldscripts.iter().cloned());
         self.objects.extend(other.objects.iter().cloned());
         self.flags.extend_profile(&other.flags);
+        for (pattern, flags) in &other.overrides {
+            self.overrides
+                .entry(pattern.clone())
+                .or_default()
+                .extend_profile(flags);
+        }
+        self.embed.extend_profile(&other.embed);
+        self.pch.extend(other.pch.iter().cloned());
+    }
+}
+
+/// Config in the `[build.embed]` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Embed {
+    /// Files or glob patterns of assets to embed, relative to Megaton.toml
+    #[serde(default)]
+    pub files: Vec<String>,
+
+    /// Alignment of the start of each asset in bytes, 16 by default
+    #[serde(default)]
+    pub align: Option<u64>,
+
+    #[serde(flatten, default)]
+    unused: CaptureUnused,
+}
+
+impl Embed {
+    pub const DEFAULT_ALIGN: u64 = 16;
+
+    pub fn align(&self) -> u64 {
+        self.align.unwrap_or(Self::DEFAULT_ALIGN)
+    }
+}
+
+impl Validate for Embed {
+    fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
+        for pattern in &self.files {
+            if let Err(e) = globset::Glob::new(pattern) {
+                cu::error!("invalid pattern in build.embed.files: {e}");
+                ctx.bail()?;
+            }
+        }
+        if let Some(align) = self.align
+            && !align.is_power_of_two()
+        {
+            cu::error!("build.embed.align must be a power of 2, got {align}");
+            ctx.bail()?;
+        }
+        self.unused.validate(ctx)?;
+        Ok(())
+    }
+}
+
+impl ExtendProfile for Embed {
+    fn extend_profile(&mut self, other: &Self) {
+        self.files.extend(other.files.iter().cloned());
+        if other.align.is_some() {
+            self.align = other.align;
+        }
     }
 }
@@ -2,10 +2,12 @@
 // Copyright (c) 2025-2026 Megaton contributors
 
 use std::fmt::Display;
+use std::ops::Range;
 
 use cu::pre::*;
 
 use super::{CaptureUnused, ExtendProfile, Validate, ValidateCtx};
+use crate::env::Compiler;
 
 /// Flags from configuration
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -40,6 +42,36 @@ impl ExtendProfile for FlagConfig {
     }
 }
 
+/// Flags for the sources matching a pattern in `build.overrides`
+///
+/// `<default>` in each list is the flags from `build.flags` of the language, like in
+/// [`FlagConfig`]. The flags they extend, the include paths and the defines are always kept
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FlagOverride {
+    pub c: Option<Vec<String>>,
+    pub cxx: Option<Vec<String>>,
+    #[serde(rename = "as")]
+    pub as_: Option<Vec<String>>,
+
+    #[serde(flatten, default)]
+    unused: CaptureUnused,
+}
+
+impl Validate for FlagOverride {
+    fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
+        self.unused.validate(ctx)
+    }
+}
+
+impl ExtendProfile for FlagOverride {
+    fn extend_profile(&mut self, other: &Self) {
+        extend_flags(&mut self.c, &other.c);
+        extend_flags(&mut self.cxx, &other.cxx);
+        extend_flags(&mut self.as_, &other.as_);
+    }
+}
+
 fn extend_flags(dst: &mut Option<Vec<String>>, src: &Option<Vec<String>>) {
     match (dst.as_mut(), src) {
         (_, None) => {}
@@ -70,6 +102,11 @@ pub struct Flags {
     pub ldflags: Vec<String>,
     pub rustflags: String,
     pub cargoflags: Vec<String>,
+    /// Where the flags from `build.flags.c`, `cxx` and `as` are in `cflags`, `cxxflags`
+    /// and `sflags`. These are the flags replaced by `build.overrides`
+    pub c_range: Range<usize>,
+    pub cxx_range: Range<usize>,
+    pub as_range: Range<usize>,
 }
 
 /// Default flags for `build.flags.common` in Megaton.toml
@@ -83,6 +120,20 @@ pub static DEFAULT_COMMON: &[&str] = &[
     "-g",
 ];
 
+/// Default flags for `build.flags.common` in Megaton.toml when compiling with clang
+///
+/// Clang does not have `-mtp=soft`, and needs the target to cross compile.
+/// Thread-local variables in C/C++ are rejected before linking instead
+pub static DEFAULT_COMMON_CLANG: &[&str] = &[
+    "--target=aarch64-none-elf",
+    "-march=armv8-a+crc+crypto",
+    "-mtune=cortex-a57",
+    "-fPIC",
+    "-fvisibility=hidden",
+    // debug info
+    "-g",
+];
+
 /// Default flags for `build.flags.c` in Megaton.toml
 ///
 /// By default, also extends from `DEFAULT_COMMON`
@@ -131,6 +182,22 @@ pub static DEFAULT_LD: &[&str] = &[
     "-Wl,--nx-module-name",
 ];
 
+/// Default flags for `build.flags.ld` in Megaton.toml when compiling with clang
+///
+/// Links with lld, which does not have `--nx-module-name` from devkitA64.
+/// The section is generated as a source instead, if libmegaton does not have it
+pub static DEFAULT_LD_CLANG: &[&str] = &[
+    "-fuse-ld=lld",
+    "-nostartfiles",
+    "-nodefaultlibs",
+    "-Wl,--shared",
+    "-Wl,--export-dynamic",
+    "-Wl,-z,nodynamic-undefined-weak",
+    "-Wl,--build-id=sha1",
+    // size optimization
+    "-Wl,--gc-sections",
+];
+
 pub static DEFAULT_RUST: &[&str] = &[];
 pub static DEFAULT_CARGO: &[&str] = &[
     // needed to strip unused symbols to pass the checker
@@ -180,9 +247,15 @@ macro_rules! create_flags {
 }
 
 impl Flags {
-    pub fn from_config(config: &FlagConfig) -> Self {
-        let common = create_flags!(&config.common, DEFAULT_COMMON);
+    /// Resolve the flags, with the default flags of the compiler
+    pub fn from_config(config: &FlagConfig, compiler: Compiler) -> Self {
+        let (default_common, default_ld) = match compiler {
+            Compiler::Gcc => (DEFAULT_COMMON, DEFAULT_LD),
+            Compiler::Clang => (DEFAULT_COMMON_CLANG, DEFAULT_LD_CLANG),
+        };
+        let common = create_flags!(&config.common, default_common);
         let mut cflags = create_flags!(&config.c, DEFAULT_C extends common);
+        let c_range = common.len()..cflags.len();
 
         // no need to check if the flag already exists.. that's O(N)
         // we already said in the docs don't do that
@@ -194,8 +267,10 @@ impl Flags {
         cflags.push(format!("-fdiagnostics-color={color_flag}"));
 
         let cxxflags = create_flags!(&config.cxx, DEFAULT_CPP extends cflags);
+        let cxx_range = cflags.len()..cxxflags.len();
         let sflags = create_flags!(&config.as_, DEFAULT_AS extends cxxflags);
-        let ldflags = create_flags!(&config.ld, DEFAULT_LD extends common);
+        let as_range = cxxflags.len()..sflags.len();
+        let ldflags = create_flags!(&config.ld, default_ld extends common);
 
         let rustflags = create_flags!(&config.rust, DEFAULT_RUST).join(" ");
         let cargoflags = create_flags!(&config.cargo, DEFAULT_CARGO);
@@ -207,6 +282,9 @@ impl Flags {
             ldflags,
             rustflags,
             cargoflags,
+            c_range,
+            cxx_range,
+            as_range,
         }
     }
 
@@ -230,6 +308,23 @@ impl Flags {
         self.cxxflags.extend(flags);
     }
 
+    /// Add the flags to output the stack usage of each function for the stack check:
+    /// `-fstack-usage` for C and C++ (`.su` files), and `-Zemit-stack-sizes` for Rust
+    /// (`.stack_sizes` section)
+    pub fn add_stack_usage(&mut self) {
+        for flags in [&mut self.cflags, &mut self.cxxflags] {
+            if !flags.iter().any(|x| x == "-fstack-usage") {
+                flags.push("-fstack-usage".to_string());
+            }
+        }
+        if !self.rustflags.contains("-Zemit-stack-sizes") {
+            if !self.rustflags.is_empty() {
+                self.rustflags.push(' ');
+            }
+            self.rustflags.push_str("-Zemit-stack-sizes");
+        }
+    }
+
     /// Set `-Wl,-init=<symbol>` for the linker
     #[inline]
     pub fn set_init(&mut self, symbol: impl Display) {
@@ -242,6 +337,12 @@ impl Flags {
         self.ldflags.push(format!("-Wl,--version-script={path}"));
     }
 
+    /// Set `-Wl,-Map=<path>` to output the link map
+    #[inline]
+    pub fn set_map_file(&mut self, path: impl Display) {
+        self.ldflags.push(format!("-Wl,-Map={path}"));
+    }
+
     /// Add library paths (`-L<path>`) for the linker
     #[inline]
     pub fn add_libpaths(&mut self, paths: impl IntoIterator<Item = impl Display>) {
@@ -263,3 +364,328 @@ impl Flags {
             .extend(scripts.into_iter().map(|x| format!("-Wl,-T,{x}")));
     }
 }
+
+/// Resolve the flags of a language with an override, replacing the flags from
+/// `build.flags` of the language (`language` in `flags`) like [`FlagConfig`] does:
+/// `<default>` is replaced with them, and a list without `<default>` replaces them.
+///
+/// The other flags, like `common`, the include paths and the defines, are kept.
+/// `None` means the override does not change the flags
+pub fn resolve_override(
+    flags: &[String],
+    language: Range<usize>,
+    flag_override: &Option<Vec<String>>,
+) -> Vec<String> {
+    let mut v = flags[..language.start].to_vec();
+    v.extend(create_flags!(flag_override, flags[language.clone()]));
+    v.extend_from_slice(&flags[language.end..]);
+    v
+}
+
+/// Where a resolved flag comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlagSource {
+    /// The default flags of megaton, or `<default>` in the config
+    Default,
+    /// The base profile in the config
+    Base,
+    /// A named profile in the config
+    Profile(String),
+}
+
+/// Displays as `default`, `base` or `profile.<name>`
+impl Display for FlagSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Base => write!(f, "base"),
+            Self::Profile(name) => write!(f, "profile.{name}"),
+        }
+    }
+}
+
+impl Serialize for FlagSource {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A resolved flag and where it comes from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourcedFlag {
+    pub flag: String,
+    pub source: FlagSource,
+}
+
+/// Same as [`Flags`], but each flag is tagged with where it comes from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourcedFlags {
+    pub cflags: Vec<SourcedFlag>,
+    pub cxxflags: Vec<SourcedFlag>,
+    pub sflags: Vec<SourcedFlag>,
+    pub ldflags: Vec<SourcedFlag>,
+    pub rustflags: Vec<SourcedFlag>,
+    pub cargoflags: Vec<SourcedFlag>,
+}
+
+/// Prefixes to tag the flags from the config with their source while resolving,
+/// so the source is known after `<default>` is expanded
+const BASE_TAG: &str = "\0base\0";
+const PROFILE_TAG: &str = "\0profile\0";
+
+impl SourcedFlags {
+    /// Resolve the flags of the base profile extended with the named profile (if any),
+    /// the same way as [`Flags::from_config`]
+    pub fn resolve(
+        base: &FlagConfig,
+        profile: Option<(&str, &FlagConfig)>,
+        compiler: Compiler,
+    ) -> Self {
+        let mut config = base.clone();
+        if let Some((_, profile)) = profile {
+            config.extend_profile(profile);
+        }
+        tag_flags(&mut config.common, &base.common);
+        tag_flags(&mut config.c, &base.c);
+        tag_flags(&mut config.cxx, &base.cxx);
+        tag_flags(&mut config.as_, &base.as_);
+        tag_flags(&mut config.ld, &base.ld);
+        tag_flags(&mut config.rust, &base.rust);
+        tag_flags(&mut config.cargo, &base.cargo);
+        let flags = Flags::from_config(&config, compiler);
+        // rustflags are joined into one string, so resolve them again
+        let rustflags = create_flags!(&config.rust, DEFAULT_RUST);
+
+        // flags without a tag are from the defaults
+        let untag = |flags: Vec<String>| {
+            flags
+                .into_iter()
+                .map(|flag| {
+                    if let Some(flag) = flag.strip_prefix(BASE_TAG) {
+                        return SourcedFlag {
+                            flag: flag.to_string(),
+                            source: FlagSource::Base,
+                        };
+                    }
+                    if let Some(flag) = flag.strip_prefix(PROFILE_TAG)
+                        && let Some((name, _)) = profile
+                    {
+                        return SourcedFlag {
+                            flag: flag.to_string(),
+                            source: FlagSource::Profile(name.to_string()),
+                        };
+                    }
+                    SourcedFlag {
+                        flag,
+                        source: FlagSource::Default,
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        Self {
+            cflags: untag(flags.cflags),
+            cxxflags: untag(flags.cxxflags),
+            sflags: untag(flags.sflags),
+            ldflags: untag(flags.ldflags),
+            rustflags: untag(rustflags),
+            cargoflags: untag(flags.cargoflags),
+        }
+    }
+}
+
+/// Tag the flags of a field extended with a profile. Extending keeps the flags
+/// of the base first, and only adds the flags of the profile after them
+fn tag_flags(flags: &mut Option<Vec<String>>, base: &Option<Vec<String>>) {
+    let Some(flags) = flags else {
+        return;
+    };
+    let base_len = base.as_ref().map(|x| x.len()).unwrap_or_default();
+    for (i, flag) in flags.iter_mut().enumerate() {
+        if flag == "<default>" {
+            continue;
+        }
+        let tag = if i < base_len { BASE_TAG } else { PROFILE_TAG };
+        *flag = format!("{tag}{flag}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sourced_flags() {
+        let base = FlagConfig {
+            common: Some(vec!["<default>".to_string(), "-DBASE".to_string()]),
+            rust: Some(vec!["-Cdebuginfo=2".to_string()]),
+            ..Default::default()
+        };
+        let profile = FlagConfig {
+            c: Some(vec!["-DDEBUG".to_string()]),
+            rust: Some(vec!["-Cignored".to_string()]),
+            ..Default::default()
+        };
+        let flags = SourcedFlags::resolve(&base, Some(("debug", &profile)), Compiler::Gcc);
+        let source_of = |flags: &[SourcedFlag], flag: &str| {
+            flags
+                .iter()
+                .find(|x| x.flag == flag)
+                .map(|x| x.source.to_string())
+        };
+        assert_eq!(source_of(&flags.cflags, "-g").as_deref(), Some("default"));
+        assert_eq!(
+            source_of(&flags.cflags, "-Wall").as_deref(),
+            Some("default")
+        );
+        assert_eq!(source_of(&flags.cflags, "-DBASE").as_deref(), Some("base"));
+        assert_eq!(
+            source_of(&flags.cxxflags, "-DDEBUG").as_deref(),
+            Some("profile.debug")
+        );
+        assert_eq!(source_of(&flags.ldflags, "-DDEBUG"), None);
+        assert_eq!(
+            source_of(&flags.rustflags, "-Cdebuginfo=2").as_deref(),
+            Some("base")
+        );
+        assert_eq!(source_of(&flags.rustflags, "-Cignored"), None);
+
+        let resolved = {
+            let mut config = base.clone();
+            config.extend_profile(&profile);
+            Flags::from_config(&config, Compiler::Gcc)
+        };
+        let unsourced =
+            |flags: &[SourcedFlag]| flags.iter().map(|x| x.flag.clone()).collect::<Vec<_>>();
+        assert_eq!(unsourced(&flags.cxxflags), resolved.cxxflags);
+        assert_eq!(unsourced(&flags.ldflags), resolved.ldflags);
+    }
+
+    #[test]
+    fn sourced_flags_duplicates() {
+        // -Wall is both in the defaults and in the config
+        let base = FlagConfig {
+            c: Some(vec!["<default>".to_string(), "-Wall".to_string()]),
+            ld: Some(vec!["-DBOTH".to_string(), "<default>".to_string()]),
+            ..Default::default()
+        };
+        let profile = FlagConfig {
+            cxx: Some(vec!["-Wall".to_string()]),
+            ld: Some(vec!["-DBOTH".to_string(), "-DPROFILE".to_string()]),
+            ..Default::default()
+        };
+        let flags = SourcedFlags::resolve(&base, Some(("debug", &profile)), Compiler::Gcc);
+        let sources_of = |flags: &[SourcedFlag], flag: &str| {
+            flags
+                .iter()
+                .filter(|x| x.flag == flag)
+                .map(|x| x.source.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sources_of(&flags.cflags, "-Wall"), ["default", "base"]);
+        assert_eq!(
+            sources_of(&flags.cxxflags, "-Wall"),
+            ["default", "base", "profile.debug"]
+        );
+        // the profile does not add a flag already in the base
+        assert_eq!(sources_of(&flags.ldflags, "-DBOTH"), ["base"]);
+        assert_eq!(sources_of(&flags.ldflags, "-DPROFILE"), ["profile.debug"]);
+        assert!(flags.cflags.iter().all(|x| !x.flag.contains('\0')));
+    }
+
+    #[test]
+    fn stack_usage_flags() {
+        let config = FlagConfig {
+            rust: Some(vec!["-Cdebuginfo=2".to_string()]),
+            ..Default::default()
+        };
+        let mut flags = Flags::from_config(&config, Compiler::Gcc);
+        assert!(!flags.cflags.iter().any(|x| x == "-fstack-usage"));
+        assert_eq!(flags.rustflags, "-Cdebuginfo=2");
+        flags.add_stack_usage();
+        flags.add_stack_usage();
+        let count = |flags: &[String]| flags.iter().filter(|x| *x == "-fstack-usage").count();
+        assert_eq!(count(&flags.cflags), 1);
+        assert_eq!(count(&flags.cxxflags), 1);
+        assert_eq!(flags.rustflags, "-Cdebuginfo=2 -Zemit-stack-sizes");
+    }
+
+    #[test]
+    fn flag_override() {
+        let config = FlagConfig {
+            common: Some(vec!["-DCOMMON".to_string()]),
+            c: Some(vec!["<default>".to_string(), "-DC".to_string()]),
+            ..Default::default()
+        };
+        let mut flags = Flags::from_config(&config, Compiler::Gcc);
+        flags.add_includes(["/inc"]);
+        flags.add_defines(["MEGATON"]);
+        let strings = |flags: &[&str]| flags.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        let c = |flag_override: &[&str]| {
+            resolve_override(
+                &flags.cflags,
+                flags.c_range.clone(),
+                &Some(strings(flag_override)),
+            )
+        };
+        let cxx = |flag_override: &[&str]| {
+            resolve_override(
+                &flags.cxxflags,
+                flags.cxx_range.clone(),
+                &Some(strings(flag_override)),
+            )
+        };
+        assert_eq!(
+            &flags.cflags[flags.c_range.clone()],
+            strings(&[DEFAULT_C, &["-DC"]].concat())
+        );
+        assert_eq!(
+            &flags.cxxflags[flags.cxx_range.clone()],
+            strings(DEFAULT_CPP)
+        );
+        assert_eq!(
+            resolve_override(&flags.cflags, flags.c_range.clone(), &None),
+            flags.cflags
+        );
+        assert_eq!(c(&["<default>"]), flags.cflags);
+
+        // like build.flags, a list without <default> replaces the flags of the language,
+        // and the flags it extends, the includes and the defines are kept
+        let color = flags.cflags[flags.c_range.end].as_str();
+        assert_eq!(c(&["-w"]), ["-DCOMMON", "-w", color, "-I/inc", "-DMEGATON"]);
+        assert_eq!(
+            c(&["<default>", "-w"]),
+            strings(
+                &[
+                    &["-DCOMMON"],
+                    DEFAULT_C,
+                    &["-DC", "-w", color, "-I/inc", "-DMEGATON"]
+                ]
+                .concat()
+            )
+        );
+        // C++ extends the C flags
+        let cxx_flags = cxx(&["-O1"]);
+        assert!(cxx_flags.iter().any(|x| x == "-Wall"));
+        assert!(!cxx_flags.iter().any(|x| x == "-std=c++20"));
+        assert_eq!(
+            cxx_flags[flags.cxx_range.start..],
+            ["-O1", "-I/inc", "-DMEGATON"]
+        );
+
+        // overrides in profiles are extended like build.flags
+        let mut base = FlagOverride {
+            c: Some(vec!["-w".to_string()]),
+            ..Default::default()
+        };
+        let profile = FlagOverride {
+            c: Some(vec!["-O0".to_string()]),
+            cxx: Some(vec!["-O1".to_string()]),
+            ..Default::default()
+        };
+        base.extend_profile(&profile);
+        assert_eq!(base.c, Some(strings(&["-w", "-O0"])));
+        assert_eq!(base.cxx, Some(strings(&["-O1", "<default>"])));
+        assert_eq!(base.as_, None);
+    }
+}
@@ -7,9 +7,13 @@ use std::path::{Path, PathBuf};
 use cu::pre::*;
 use semver::VersionReq;
 
+use crate::config::profile::is_profile_dir_name;
 use crate::config::util;
 
-use super::{BASE_PROFILE, Build, CaptureUnused, ExtendProfile, Profile, Validate, ValidateCtx};
+use super::{
+    BASE_PROFILE, Build, CaptureUnused, ExtendProfile, Npdm, Package, Profile, Validate,
+    ValidateCtx,
+};
 
 /// Get the root path of the project
 pub fn get_root_and_manifest(manifest_path: Option<&str>) -> cu::Result<(PathBuf, PathBuf)> {
@@ -79,6 +83,18 @@ pub struct Config {
     /// The `[check]` section (for checking unresolved dynamic symbols)
     pub check: Option<Profile<Check>>,
 
+    /// The `[npdm]` section
+    ///
+    /// Specify the program metadata (main.npdm) of the module
+    #[serde(default)]
+    pub npdm: Profile<Npdm>,
+
+    /// The `[package]` section
+    ///
+    /// Specify how the module is packaged into mod folders
+    #[serde(default)]
+    pub package: Package,
+
     #[serde(flatten, default)]
     unused: CaptureUnused,
 }
@@ -94,6 +110,8 @@ impl Validate for Config {
         if let Some(check) = &self.check {
             check.validate_property(ctx, "check")?;
         }
+        self.npdm.validate_property(ctx, "npdm")?;
+        self.package.validate_property(ctx, "package")?;
 
         if !self.megaton.lib_enabled() && self.cargo.enabled.is_some_and(|val| val) {
             cu::bail!("rust cannot be enabled unless libmegaton is enabled");
@@ -318,6 +336,13 @@ impl ProfileConfig {
             cu::bail!("failed to selected a profile");
         }
 
+        // the profile is used as a directory name in the target directory
+        if !is_profile_dir_name(profile) {
+            cu::error!("'{profile}' is not a valid profile name");
+            cu::hint!("- profile names cannot be 'lib', '.', '..', or contain path separators");
+            cu::bail!("failed to selected a profile");
+        }
+
         Ok(profile)
     }
 }
@@ -335,13 +360,32 @@ pub struct Check {
     /// Extra instructions to disallow (like `"msr"`). Values are regular expressions.
     #[serde(default)]
     pub disallowed_instructions: Vec<String>,
+    /// Maximum size in bytes of each kind of section. The check fails if exceeded
+    #[serde(default)]
+    pub max_size: SizeBudget,
+    /// Maximum stack usage in bytes of the deepest call path. The check fails if exceeded.
+    /// Defaults to `npdm.main-thread-stack-size`
+    pub max_stack: Option<u64>,
 
     #[serde(flatten, default)]
     unused: CaptureUnused,
 }
 
+impl Check {
+    /// Get the stack budget, which defaults to the stack size of the main thread
+    pub fn max_stack(&self, npdm: &Npdm) -> u64 {
+        self.max_stack
+            .unwrap_or_else(|| u64::from(npdm.main_thread_stack_size()))
+    }
+}
+
 impl Validate for Check {
     fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
+        self.max_size.validate_property(ctx, "max-size")?;
+        if self.max_stack == Some(0) {
+            cu::error!("check.max-stack must be greater than 0");
+            ctx.bail()?;
+        }
         self.unused.validate(ctx)
     }
 }
@@ -352,6 +396,62 @@ impl ExtendProfile for Check {
         self.symbols.extend(other.symbols.iter().cloned());
         self.disallowed_instructions
             .extend(other.disallowed_instructions.iter().cloned());
+        self.max_size.extend_profile(&other.max_size);
+        if other.max_stack.is_some() {
+            self.max_stack = other.max_stack;
+        }
+    }
+}
+
+/// The `check.max-size` table, size budget in bytes for each kind of section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SizeBudget {
+    /// Executable sections, like `.text` and `.plt`
+    pub text: Option<u64>,
+    /// Read-only sections, like `.rodata` and `.dynsym`
+    pub rodata: Option<u64>,
+    /// Writable sections, like `.data` and `.got`
+    pub data: Option<u64>,
+    /// Zero-initialized sections, like `.bss`
+    pub bss: Option<u64>,
+
+    #[serde(flatten, default)]
+    unused: CaptureUnused,
+}
+
+impl SizeBudget {
+    /// Get the budget of a kind of section by name (`text`, `rodata`, `data` or `bss`)
+    pub fn get(&self, kind: &str) -> Option<u64> {
+        match kind {
+            "text" => self.text,
+            "rodata" => self.rodata,
+            "data" => self.data,
+            "bss" => self.bss,
+            _ => None,
+        }
+    }
+
+    /// Values in the profile override the base
+    fn extend_profile(&mut self, other: &Self) {
+        if other.text.is_some() {
+            self.text = other.text;
+        }
+        if other.rodata.is_some() {
+            self.rodata = other.rodata;
+        }
+        if other.data.is_some() {
+            self.data = other.data;
+        }
+        if other.bss.is_some() {
+            self.bss = other.bss;
+        }
+    }
+}
+
+impl Validate for SizeBudget {
+    fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
+        self.unused.validate(ctx)
     }
 }
 
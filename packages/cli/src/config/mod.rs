@@ -10,6 +10,12 @@ mod build_flag;
 pub use build_flag::*;
 mod main_config;
 pub use main_config::*;
+mod npdm_config;
+pub use npdm_config::*;
+mod package_config;
+pub use package_config::*;
+mod user_config;
+pub use user_config::*;
 
 mod profile;
 pub use profile::BASE_PROFILE;
@@ -0,0 +1,407 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+use super::{CaptureUnused, ExtendProfile, Validate, ValidateCtx};
+
+/// Config in the `[npdm]` section
+///
+/// All values are optional. Unspecified values use the defaults that
+/// allow the module to be loaded in most games.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Npdm {
+    /// Name of the program
+    pub name: Option<String>,
+    /// Stack size of the main thread in bytes
+    pub main_thread_stack_size: Option<u32>,
+    /// Priority of the main thread
+    pub main_thread_priority: Option<u8>,
+    /// Core the main thread runs on
+    pub default_cpu_id: Option<u8>,
+    /// Range of thread priorities allowed, as `[highest, lowest]`
+    pub thread_priority: Option<[u8; 2]>,
+    /// Range of cores allowed, as `[lowest, highest]`
+    pub cpu_id: Option<[u8; 2]>,
+    /// Bitmask of filesystem permissions
+    pub filesystem_permissions: Option<u64>,
+    /// Services the program can access
+    pub service_access: Option<Vec<String>>,
+    /// Services the program can host
+    pub service_host: Option<Vec<String>>,
+    /// Syscalls the program can call, by name (like `svcSleepThread`)
+    pub syscalls: Option<Vec<String>>,
+    /// Application type in the kernel capabilities
+    pub application_type: Option<u8>,
+    /// Minimum kernel version in the kernel capabilities
+    pub min_kernel_version: Option<u16>,
+    /// Size of the handle table
+    pub handle_table_size: Option<u16>,
+    /// Allow the program to be debugged
+    pub allow_debug: Option<bool>,
+    /// Force the program to be debugged
+    pub force_debug: Option<bool>,
+
+    #[serde(flatten, default)]
+    unused: CaptureUnused,
+}
+
+impl Validate for Npdm {
+    fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
+        if let Some(name) = &self.name
+            && (name.is_empty() || name.len() >= 0x10 || !name.is_ascii())
+        {
+            cu::error!("npdm.name must be 1 to 15 ASCII characters, got '{name}'");
+            ctx.bail()?;
+        }
+        if let Some(size) = self.main_thread_stack_size
+            && (size == 0 || size % 0x1000 != 0)
+        {
+            cu::error!("npdm.main-thread-stack-size must be a non-zero multiple of 0x1000");
+            ctx.bail()?;
+        }
+        if let Some(priority) = self.main_thread_priority
+            && priority > 63
+        {
+            cu::error!("npdm.main-thread-priority must be between 0 and 63, got {priority}");
+            ctx.bail()?;
+        }
+        if let Some(cpu) = self.default_cpu_id
+            && cpu > 3
+        {
+            cu::error!("npdm.default-cpu-id must be between 0 and 3, got {cpu}");
+            ctx.bail()?;
+        }
+        if let Some([highest, lowest]) = self.thread_priority
+            && (highest > 63 || lowest > highest)
+        {
+            cu::error!(
+                "npdm.thread-priority must be [highest, lowest] between 0 and 63, with lowest <= highest"
+            );
+            ctx.bail()?;
+        }
+        if let Some([lowest, highest]) = self.cpu_id
+            && (lowest > highest || highest > 3)
+        {
+            cu::error!("npdm.cpu-id must be [lowest, highest] between 0 and 3");
+            ctx.bail()?;
+        }
+        if let Some(application_type) = self.application_type
+            && application_type > 2
+        {
+            cu::error!(
+                "npdm.application-type must be 0 (system module), 1 (application) or 2 (applet), got {application_type}"
+            );
+            ctx.bail()?;
+        }
+        if let Some(size) = self.handle_table_size
+            && size > 1023
+        {
+            cu::error!("npdm.handle-table-size must be at most 1023, got {size}");
+            ctx.bail()?;
+        }
+        for (key, services) in [
+            ("service-access", &self.service_access),
+            ("service-host", &self.service_host),
+        ] {
+            for service in services.iter().flatten() {
+                if service.is_empty() || service.len() > 8 || !service.is_ascii() {
+                    cu::error!(
+                        "npdm.{key}: '{service}' is not a valid service name (must be 1 to 8 ASCII characters)"
+                    );
+                    ctx.bail()?;
+                }
+            }
+        }
+        for name in self.syscalls.iter().flatten() {
+            if syscall_id(name).is_none() {
+                cu::error!("npdm.syscalls: unknown syscall '{name}'");
+                ctx.bail()?;
+            }
+        }
+        self.unused.validate(ctx)
+    }
+}
+
+impl ExtendProfile for Npdm {
+    /// Values in the profile override the base
+    fn extend_profile(&mut self, other: &Self) {
+        macro_rules! extend {
+            ($($field:ident),*) => {$(
+                if other.$field.is_some() {
+                    self.$field = other.$field.clone();
+                }
+            )*};
+        }
+        extend!(
+            name,
+            main_thread_stack_size,
+            main_thread_priority,
+            default_cpu_id,
+            thread_priority,
+            cpu_id,
+            filesystem_permissions,
+            service_access,
+            service_host,
+            syscalls,
+            application_type,
+            min_kernel_version,
+            handle_table_size,
+            allow_debug,
+            force_debug
+        );
+    }
+}
+
+impl Npdm {
+    /// Check the values that depend on each other, after the profile is resolved.
+    /// Unspecified values are checked with their defaults
+    pub fn validate_resolved(&self) -> cu::Result<()> {
+        let (highest, lowest) = self.thread_priority();
+        let priority = self.main_thread_priority();
+        if priority < lowest || priority > highest {
+            cu::error!(
+                "npdm.main-thread-priority {priority} is not in npdm.thread-priority [{highest}, {lowest}]"
+            );
+            cu::bail!("invalid npdm config");
+        }
+        let (lowest, highest) = self.cpu_id();
+        let cpu = self.default_cpu_id();
+        if cpu < lowest || cpu > highest {
+            cu::error!("npdm.default-cpu-id {cpu} is not in npdm.cpu-id [{lowest}, {highest}]");
+            cu::bail!("invalid npdm config");
+        }
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("Application")
+    }
+    pub fn main_thread_stack_size(&self) -> u32 {
+        self.main_thread_stack_size.unwrap_or(0x100000)
+    }
+    pub fn main_thread_priority(&self) -> u8 {
+        self.main_thread_priority.unwrap_or(44)
+    }
+    pub fn default_cpu_id(&self) -> u8 {
+        self.default_cpu_id.unwrap_or(0)
+    }
+    /// Get the (highest, lowest) thread priority
+    pub fn thread_priority(&self) -> (u8, u8) {
+        let [highest, lowest] = self.thread_priority.unwrap_or([59, 28]);
+        (highest, lowest)
+    }
+    /// Get the (lowest, highest) core
+    pub fn cpu_id(&self) -> (u8, u8) {
+        let [lowest, highest] = self.cpu_id.unwrap_or([0, 3]);
+        (lowest, highest)
+    }
+    pub fn filesystem_permissions(&self) -> u64 {
+        self.filesystem_permissions.unwrap_or(u64::MAX)
+    }
+    pub fn service_access(&self) -> Vec<&str> {
+        match &self.service_access {
+            Some(services) => services.iter().map(String::as_str).collect(),
+            None => vec!["*"],
+        }
+    }
+    pub fn service_host(&self) -> Vec<&str> {
+        match &self.service_host {
+            Some(services) => services.iter().map(String::as_str).collect(),
+            None => vec![],
+        }
+    }
+    /// Get the IDs of the allowed syscalls. All syscalls are allowed by default
+    pub fn syscall_ids(&self) -> Vec<u8> {
+        match &self.syscalls {
+            Some(names) => names.iter().filter_map(|x| syscall_id(x)).collect(),
+            None => SYSCALLS.iter().map(|(_, id)| *id).collect(),
+        }
+    }
+    pub fn application_type(&self) -> u8 {
+        self.application_type.unwrap_or(1)
+    }
+    pub fn min_kernel_version(&self) -> u16 {
+        self.min_kernel_version.unwrap_or(0x60)
+    }
+    pub fn handle_table_size(&self) -> u16 {
+        self.handle_table_size.unwrap_or(512)
+    }
+    pub fn allow_debug(&self) -> bool {
+        self.allow_debug.unwrap_or(true)
+    }
+    pub fn force_debug(&self) -> bool {
+        self.force_debug.unwrap_or(false)
+    }
+}
+
+/// Get the ID of a syscall by name
+pub fn syscall_id(name: &str) -> Option<u8> {
+    SYSCALLS.iter().find(|(n, _)| *n == name).map(|(_, id)| *id)
+}
+
+/// Name and ID of the syscalls
+///
+/// See https://switchbrew.org/wiki/SVC
+static SYSCALLS: &[(&str, u8)] = &[
+    ("svcUnknown", 0x00),
+    ("svcSetHeapSize", 0x01),
+    ("svcSetMemoryPermission", 0x02),
+    ("svcSetMemoryAttribute", 0x03),
+    ("svcMapMemory", 0x04),
+    ("svcUnmapMemory", 0x05),
+    ("svcQueryMemory", 0x06),
+    ("svcExitProcess", 0x07),
+    ("svcCreateThread", 0x08),
+    ("svcStartThread", 0x09),
+    ("svcExitThread", 0x0a),
+    ("svcSleepThread", 0x0b),
+    ("svcGetThreadPriority", 0x0c),
+    ("svcSetThreadPriority", 0x0d),
+    ("svcGetThreadCoreMask", 0x0e),
+    ("svcSetThreadCoreMask", 0x0f),
+    ("svcGetCurrentProcessorNumber", 0x10),
+    ("svcSignalEvent", 0x11),
+    ("svcClearEvent", 0x12),
+    ("svcMapSharedMemory", 0x13),
+    ("svcUnmapSharedMemory", 0x14),
+    ("svcCreateTransferMemory", 0x15),
+    ("svcCloseHandle", 0x16),
+    ("svcResetSignal", 0x17),
+    ("svcWaitSynchronization", 0x18),
+    ("svcCancelSynchronization", 0x19),
+    ("svcArbitrateLock", 0x1a),
+    ("svcArbitrateUnlock", 0x1b),
+    ("svcWaitProcessWideKeyAtomic", 0x1c),
+    ("svcSignalProcessWideKey", 0x1d),
+    ("svcGetSystemTick", 0x1e),
+    ("svcConnectToNamedPort", 0x1f),
+    ("svcSendSyncRequestLight", 0x20),
+    ("svcSendSyncRequest", 0x21),
+    ("svcSendSyncRequestWithUserBuffer", 0x22),
+    ("svcSendAsyncRequestWithUserBuffer", 0x23),
+    ("svcGetProcessId", 0x24),
+    ("svcGetThreadId", 0x25),
+    ("svcBreak", 0x26),
+    ("svcOutputDebugString", 0x27),
+    ("svcReturnFromException", 0x28),
+    ("svcGetInfo", 0x29),
+    ("svcFlushEntireDataCache", 0x2a),
+    ("svcFlushDataCache", 0x2b),
+    ("svcMapPhysicalMemory", 0x2c),
+    ("svcUnmapPhysicalMemory", 0x2d),
+    ("svcGetFutureThreadInfo", 0x2e),
+    ("svcGetLastThreadInfo", 0x2f),
+    ("svcGetResourceLimitLimitValue", 0x30),
+    ("svcGetResourceLimitCurrentValue", 0x31),
+    ("svcSetThreadActivity", 0x32),
+    ("svcGetThreadContext3", 0x33),
+    ("svcWaitForAddress", 0x34),
+    ("svcSignalToAddress", 0x35),
+    ("svcDumpInfo", 0x3c),
+    ("svcDumpInfoNew", 0x3d),
+    ("svcCreateSession", 0x40),
+    ("svcAcceptSession", 0x41),
+    ("svcReplyAndReceiveLight", 0x42),
+    ("svcReplyAndReceive", 0x43),
+    ("svcReplyAndReceiveWithUserBuffer", 0x44),
+    ("svcCreateEvent", 0x45),
+    ("svcMapPhysicalMemoryUnsafe", 0x48),
+    ("svcUnmapPhysicalMemoryUnsafe", 0x49),
+    ("svcSetUnsafeLimit", 0x4a),
+    ("svcCreateCodeMemory", 0x4b),
+    ("svcControlCodeMemory", 0x4c),
+    ("svcSleepSystem", 0x4d),
+    ("svcReadWriteRegister", 0x4e),
+    ("svcSetProcessActivity", 0x4f),
+    ("svcCreateSharedMemory", 0x50),
+    ("svcMapTransferMemory", 0x51),
+    ("svcUnmapTransferMemory", 0x52),
+    ("svcCreateInterruptEvent", 0x53),
+    ("svcQueryPhysicalAddress", 0x54),
+    ("svcQueryIoMapping", 0x55),
+    ("svcCreateDeviceAddressSpace", 0x56),
+    ("svcAttachDeviceAddressSpace", 0x57),
+    ("svcDetachDeviceAddressSpace", 0x58),
+    ("svcMapDeviceAddressSpaceByForce", 0x59),
+    ("svcMapDeviceAddressSpaceAligned", 0x5a),
+    ("svcMapDeviceAddressSpace", 0x5b),
+    ("svcUnmapDeviceAddressSpace", 0x5c),
+    ("svcInvalidateProcessDataCache", 0x5d),
+    ("svcStoreProcessDataCache", 0x5e),
+    ("svcFlushProcessDataCache", 0x5f),
+    ("svcDebugActiveProcess", 0x60),
+    ("svcBreakDebugProcess", 0x61),
+    ("svcTerminateDebugProcess", 0x62),
+    ("svcGetDebugEvent", 0x63),
+    ("svcContinueDebugEvent", 0x64),
+    ("svcGetProcessList", 0x65),
+    ("svcGetThreadList", 0x66),
+    ("svcGetDebugThreadContext", 0x67),
+    ("svcSetDebugThreadContext", 0x68),
+    ("svcQueryDebugProcessMemory", 0x69),
+    ("svcReadDebugProcessMemory", 0x6a),
+    ("svcWriteDebugProcessMemory", 0x6b),
+    ("svcSetHardwareBreakPoint", 0x6c),
+    ("svcGetDebugThreadParam", 0x6d),
+    ("svcGetSystemInfo", 0x6f),
+    ("svcCreatePort", 0x70),
+    ("svcManageNamedPort", 0x71),
+    ("svcConnectToPort", 0x72),
+    ("svcSetProcessMemoryPermission", 0x73),
+    ("svcMapProcessMemory", 0x74),
+    ("svcUnmapProcessMemory", 0x75),
+    ("svcQueryProcessMemory", 0x76),
+    ("svcMapProcessCodeMemory", 0x77),
+    ("svcUnmapProcessCodeMemory", 0x78),
+    ("svcCreateProcess", 0x79),
+    ("svcStartProcess", 0x7a),
+    ("svcTerminateProcess", 0x7b),
+    ("svcGetProcessInfo", 0x7c),
+    ("svcCreateResourceLimit", 0x7d),
+    ("svcSetResourceLimitLimitValue", 0x7e),
+    ("svcCallSecureMonitor", 0x7f),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_ranges() {
+        let npdm = |toml: &str| toml::parse::<Npdm>(toml).unwrap();
+        assert!(Npdm::default().validate_root().is_ok());
+        assert!(Npdm::default().validate_resolved().is_ok());
+        assert!(npdm("main-thread-priority = 64").validate_root().is_err());
+        assert!(npdm("default-cpu-id = 4").validate_root().is_err());
+        assert!(npdm("thread-priority = [28, 59]").validate_root().is_err());
+        assert!(npdm("application-type = 3").validate_root().is_err());
+        assert!(npdm("handle-table-size = 1024").validate_root().is_err());
+        assert!(npdm("handle-table-size = 1023").validate_root().is_ok());
+    }
+
+    #[test]
+    fn validate_resolved() {
+        let mut npdm = Npdm {
+            thread_priority: Some([40, 30]),
+            ..Default::default()
+        };
+        // the default main thread priority is 44
+        assert!(npdm.validate_resolved().is_err());
+        npdm.main_thread_priority = Some(30);
+        assert!(npdm.validate_resolved().is_ok());
+
+        // the profile overrides the core range of the base
+        let mut npdm = Npdm {
+            default_cpu_id: Some(2),
+            ..Default::default()
+        };
+        assert!(npdm.validate_resolved().is_ok());
+        npdm.extend_profile(&Npdm {
+            cpu_id: Some([0, 1]),
+            ..Default::default()
+        });
+        assert!(npdm.validate_resolved().is_err());
+    }
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use std::fmt::Display;
+
+use cu::pre::*;
+
+use super::{CaptureUnused, Validate, ValidateCtx};
+
+/// Config in the `[package]` section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Package {
+    /// The exefs slot to place the module in, `subsdk0` to `subsdk9`
+    #[serde(default = "default_subsdk")]
+    pub subsdk: String,
+
+    /// The mod folder layouts to output
+    #[serde(default = "default_layouts")]
+    pub layouts: Vec<PackageLayout>,
+
+    /// Also create a `.zip` archive for each layout
+    #[serde(default)]
+    pub zip: bool,
+
+    /// Name of the game folder, used by the `simple-mod-manager` layout
+    pub game: Option<String>,
+
+    /// Name of the mod folder, used by the `simple-mod-manager` and `emulator`
+    /// layouts. Defaults to the module name
+    pub mod_name: Option<String>,
+
+    #[serde(flatten, default)]
+    unused: CaptureUnused,
+}
+
+impl Default for Package {
+    fn default() -> Self {
+        Self {
+            subsdk: default_subsdk(),
+            layouts: default_layouts(),
+            zip: false,
+            game: None,
+            mod_name: None,
+            unused: Default::default(),
+        }
+    }
+}
+
+fn default_subsdk() -> String {
+    String::from("subsdk9")
+}
+
+fn default_layouts() -> Vec<PackageLayout> {
+    vec![PackageLayout::Atmosphere]
+}
+
+impl Validate for Package {
+    fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
+        let valid_subsdk = self
+            .subsdk
+            .strip_prefix("subsdk")
+            .is_some_and(|n| n.len() == 1 && n.as_bytes()[0].is_ascii_digit());
+        if !valid_subsdk {
+            cu::bail!(
+                "'{}' is not a valid {}.subsdk slot (must be subsdk0 to subsdk9)",
+                self.subsdk,
+                ctx.key()
+            );
+        }
+        for name in [&self.game, &self.mod_name].into_iter().flatten() {
+            if name.is_empty() || name.contains(['/', '\\']) {
+                cu::error!("'{name}' is not a valid folder name");
+                ctx.bail()?;
+            }
+        }
+        self.unused.validate(ctx)
+    }
+}
+
+/// Folder layouts for the packaged mod
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackageLayout {
+    /// `atmosphere/contents/<title-id>/exefs/`, for copying to the root of the SD card
+    Atmosphere,
+    /// `mods/<game>/<mod>/contents/<title-id>/exefs/`, for SimpleModManager
+    SimpleModManager,
+    /// `<title-id>/<mod>/exefs/`, for the mod folder of emulators
+    Emulator,
+}
+
+impl Display for PackageLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Atmosphere => write!(f, "atmosphere"),
+            Self::SimpleModManager => write!(f, "simple-mod-manager"),
+            Self::Emulator => write!(f, "emulator"),
+        }
+    }
+}
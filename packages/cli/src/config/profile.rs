@@ -14,7 +14,13 @@ pub static BASE_PROFILE: &str = "none";
 
 /// Check whether a profile name is legal
 pub fn is_profile_name_allowed(name: &str) -> bool {
-    BASE_PROFILE != name
+    BASE_PROFILE != name && is_profile_dir_name(name)
+}
+
+/// Check whether a profile name can be the name of its directory in `target/megaton`,
+/// which also has the unpacked library in `lib`
+pub fn is_profile_dir_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && name != "lib" && !name.contains(['/', '\\'])
 }
 
 /// Generic config section that can be extended with profiles
@@ -38,13 +44,26 @@ impl<T: ExtendProfile> Profile<T> {
     /// extended with the profile with the given name.
     pub fn get_profile(&self, name: &str) -> T {
         let mut base = self.base.clone();
-        if name != BASE_PROFILE {
-            if let Some(profile) = self.profiles.0.get(name) {
-                base.extend_profile(profile);
-            }
+        if let Some(profile) = self.profile(name) {
+            base.extend_profile(profile);
         }
         base
     }
+
+    /// Get the base profile, without extending from any named profile
+    pub fn base(&self) -> &T {
+        &self.base
+    }
+
+    /// Get the named profile as written in the config, without the base profile
+    ///
+    /// Returns `None` if the name is "none", or there is no profile with that name
+    pub fn profile(&self, name: &str) -> Option<&T> {
+        if name == BASE_PROFILE {
+            return None;
+        }
+        self.profiles.0.get(name)
+    }
 }
 
 impl<T: ExtendProfile> Validate for Profile<T> {
@@ -63,7 +82,7 @@ impl<T: ExtendProfile> Validate for ProfileMap<T> {
     fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
         for (name, config) in &self.0 {
             if !is_profile_name_allowed(name) {
-                cu::error!("'{name}' is reserved and cannot be used as a profile name.");
+                cu::error!("'{name}' is reserved or not allowed as a profile name.");
                 ctx.bail()?;
             }
             config.validate_property(ctx, name)?;
@@ -77,3 +96,20 @@ pub trait ExtendProfile: Validate + Clone + std::fmt::Debug {
     /// Extend this config section with another
     fn extend_profile(&mut self, other: &Self);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_names() {
+        assert!(is_profile_name_allowed("debug"));
+        assert!(is_profile_name_allowed("release-2"));
+        assert!(!is_profile_name_allowed("none"));
+        assert!(is_profile_dir_name("none"));
+        for name in ["", ".", "..", "lib", "../..", "a/b", "/tmp", "a\\b"] {
+            assert!(!is_profile_dir_name(name), "{name}");
+            assert!(!is_profile_name_allowed(name), "{name}");
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! User-level config, stored in `config.toml` in MEGATON_HOME
+use std::path::PathBuf;
+
+use cu::pre::*;
+
+use super::{CaptureUnused, Validate, ValidateCtx};
+
+/// Get the path to the user config file
+pub fn user_config_path() -> cu::Result<PathBuf> {
+    let megaton_home = megaton_toolchain_build::get_megaton_home()?;
+    Ok(megaton_home.join("config.toml"))
+}
+
+/// Load the user config. Returns the default config if the file does not exist
+pub fn load_user() -> cu::Result<UserConfig> {
+    let path = user_config_path()?;
+    if !path.exists() {
+        cu::debug!("user config not found at '{}'", path.display());
+        return Ok(UserConfig::default());
+    }
+    let content = cu::fs::read_string(&path)?;
+    let config = cu::check!(
+        toml::parse::<UserConfig>(&content),
+        "failed to parse user config at '{}'",
+        path.display()
+    )?;
+    config.validate_root()?;
+    Ok(config)
+}
+
+/// Config data read from `config.toml` in MEGATON_HOME
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserConfig {
+    /// The `[deploy]` section
+    ///
+    /// Connection to the console for `megaton deploy`
+    pub deploy: Option<Deploy>,
+
+    #[serde(flatten, default)]
+    unused: CaptureUnused,
+}
+
+impl Validate for UserConfig {
+    fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
+        if let Some(deploy) = &self.deploy {
+            deploy.validate_property(ctx, "deploy")?;
+        }
+        self.unused.validate(ctx)
+    }
+}
+
+/// Config in the `[deploy]` section of the user config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Deploy {
+    /// Host name or IP address of the console's FTP server
+    pub host: String,
+
+    /// Port of the console's FTP server
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    /// User name to log in with
+    #[serde(default = "default_user")]
+    pub user: String,
+
+    /// Password to log in with
+    #[serde(default)]
+    pub password: String,
+
+    #[serde(flatten, default)]
+    unused: CaptureUnused,
+}
+
+impl Deploy {
+    pub fn new(host: String) -> Self {
+        Self {
+            host,
+            port: default_port(),
+            user: default_user(),
+            password: String::new(),
+            unused: Default::default(),
+        }
+    }
+}
+
+fn default_port() -> u16 {
+    5000
+}
+
+fn default_user() -> String {
+    String::from("anonymous")
+}
+
+impl Validate for Deploy {
+    fn validate(&self, ctx: &mut ValidateCtx) -> cu::Result<()> {
+        if self.host.is_empty() {
+            cu::bail!("{}.host must be non-empty", ctx.key());
+        }
+        self.unused.validate(ctx)
+    }
+}
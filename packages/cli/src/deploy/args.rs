@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+#[derive(Debug, clap::Parser)]
+pub struct DeployArgs {
+    /// Select profile to build and deploy
+    ///
+    /// See https://megaton-new.pistonite.dev/tutorial/profiles
+    #[clap(short, long, default_value = "none")]
+    pub profile: String,
+
+    /// Download logs and crash reports from the console instead of uploading
+    #[clap(long)]
+    pub pull: bool,
+
+    /// Do not delete crash reports on the console after downloading them
+    #[clap(long, requires = "pull")]
+    pub keep: bool,
+
+    /// Host of the console's FTP server, overrides the user config
+    #[clap(long)]
+    pub host: Option<String>,
+
+    /// Port of the console's FTP server, overrides the user config
+    #[clap(long)]
+    pub port: Option<u16>,
+
+    /// Specify the location of the Megaton.toml config file
+    #[clap(short = 'c', long)]
+    pub config: Option<String>,
+}
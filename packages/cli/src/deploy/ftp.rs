@@ -0,0 +1,565 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Minimal FTP client, enough to talk to the homebrew FTP servers on the console
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use cu::pre::*;
+
+static TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A reply from the server
+#[derive(Debug)]
+pub struct Reply {
+    pub code: u32,
+    pub message: String,
+}
+
+impl Reply {
+    fn is_positive(&self) -> bool {
+        (100..400).contains(&self.code)
+    }
+}
+
+/// An entry returned from listing a directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// A connection to an FTP server
+pub struct FtpClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    peer_ip: IpAddr,
+    /// If the server supports `MLSD`. Cleared when the server rejects it,
+    /// and `LIST` is used instead
+    mlsd: bool,
+}
+
+impl FtpClient {
+    /// Connect to the server and wait for the greeting
+    pub fn connect(host: &str, port: u16) -> cu::Result<Self> {
+        let addrs = cu::check!(
+            (host, port).to_socket_addrs(),
+            "failed to resolve address {host}:{port}"
+        )?;
+        let mut stream = None;
+        for addr in addrs {
+            cu::debug!("ftp: connecting to {addr}");
+            match TcpStream::connect_timeout(&addr, TIMEOUT) {
+                Ok(s) => {
+                    stream = Some(s);
+                    break;
+                }
+                Err(e) => cu::debug!("ftp: failed to connect to {addr}: {e}"),
+            }
+        }
+        let stream = cu::check!(stream, "failed to connect to {host}:{port}")?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let peer_ip = stream.peer_addr()?.ip();
+        let mut client = Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            peer_ip,
+            mlsd: true,
+        };
+        let greeting = client.read_reply()?;
+        if greeting.code != 220 {
+            cu::bail!("unexpected greeting from server: {greeting:?}");
+        }
+        Ok(client)
+    }
+
+    /// Log in and switch to binary mode
+    pub fn login(&mut self, user: &str, password: &str) -> cu::Result<()> {
+        let reply = self.command(&format!("USER {user}"))?;
+        match reply.code {
+            230 => {}
+            331 | 332 => {
+                self.expect(&format!("PASS {password}"), &[230, 202])?;
+            }
+            _ => cu::bail!("login failed: {reply:?}"),
+        }
+        self.expect("TYPE I", &[200])?;
+        Ok(())
+    }
+
+    /// Create a directory and all its parents. Existing directories are ignored
+    pub fn make_dirs(&mut self, path: &str) -> cu::Result<()> {
+        let mut current = String::new();
+        for part in path.split('/').filter(|x| !x.is_empty()) {
+            current.push('/');
+            current.push_str(part);
+            let reply = self.command(&format!("MKD {current}"))?;
+            // 550/521 are commonly used for directory already exists
+            if !reply.is_positive() && reply.code != 550 && reply.code != 521 {
+                cu::bail!("failed to create directory '{current}': {reply:?}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Upload a file
+    pub fn put(&mut self, path: &str, data: &[u8]) -> cu::Result<()> {
+        let mut stream = self.open_data(&format!("STOR {path}"))?;
+        cu::check!(stream.write_all(data), "failed to upload '{path}'")?;
+        drop(stream);
+        self.finish_data(path)
+    }
+
+    /// Download a file. Returns `None` if the file does not exist
+    pub fn get(&mut self, path: &str) -> cu::Result<Option<Vec<u8>>> {
+        let Some(mut stream) = self.try_open_data(&format!("RETR {path}"))? else {
+            return Ok(None);
+        };
+        let mut data = vec![];
+        cu::check!(stream.read_to_end(&mut data), "failed to download '{path}'")?;
+        drop(stream);
+        self.finish_data(path)?;
+        Ok(Some(data))
+    }
+
+    /// List a directory. Returns `None` if the directory does not exist
+    ///
+    /// `MLSD` is used if the server supports it, since the format of `LIST` is not standard
+    pub fn list(&mut self, path: &str) -> cu::Result<Option<Vec<Entry>>> {
+        if self.mlsd {
+            match self.open_data_or_reply(&format!("MLSD {path}"))? {
+                Ok(stream) => {
+                    let data = self.read_listing(stream, path)?;
+                    return Ok(Some(data.lines().filter_map(parse_mlsd_line).collect()));
+                }
+                Err(reply) if reply.code == 550 => return Ok(None),
+                Err(reply) if is_not_implemented(&reply) => {
+                    cu::debug!("ftp: MLSD is not supported, falling back to LIST");
+                    self.mlsd = false;
+                }
+                Err(reply) => cu::bail!("'MLSD {path}' failed: {reply:?}"),
+            }
+        }
+        let Some(stream) = self.try_open_data(&format!("LIST {path}"))? else {
+            return Ok(None);
+        };
+        let data = self.read_listing(stream, path)?;
+        Ok(Some(data.lines().filter_map(parse_list_line).collect()))
+    }
+
+    fn read_listing(&mut self, mut stream: TcpStream, path: &str) -> cu::Result<String> {
+        let mut data = String::new();
+        cu::check!(stream.read_to_string(&mut data), "failed to list '{path}'")?;
+        drop(stream);
+        self.finish_data(path)?;
+        Ok(data)
+    }
+
+    /// Delete a file
+    pub fn delete(&mut self, path: &str) -> cu::Result<()> {
+        self.expect(&format!("DELE {path}"), &[250])?;
+        Ok(())
+    }
+
+    /// Close the connection
+    pub fn quit(mut self) -> cu::Result<()> {
+        self.command("QUIT")?;
+        Ok(())
+    }
+
+    /// Send a command that opens a data connection, fail if the server rejects it
+    fn open_data(&mut self, cmd: &str) -> cu::Result<TcpStream> {
+        match self.try_open_data(cmd)? {
+            Some(stream) => Ok(stream),
+            None => cu::bail!("'{cmd}' failed: file unavailable"),
+        }
+    }
+
+    /// Send a command that opens a data connection. Returns `None` if the file is unavailable
+    fn try_open_data(&mut self, cmd: &str) -> cu::Result<Option<TcpStream>> {
+        match self.open_data_or_reply(cmd)? {
+            Ok(stream) => Ok(Some(stream)),
+            Err(reply) if reply.code == 550 => Ok(None),
+            Err(reply) => cu::bail!("'{cmd}' failed: {reply:?}"),
+        }
+    }
+
+    /// Send a command that opens a data connection. Returns the reply if the server
+    /// rejects the command
+    fn open_data_or_reply(&mut self, cmd: &str) -> cu::Result<Result<TcpStream, Reply>> {
+        let reply = self.expect("PASV", &[227])?;
+        let port = cu::check!(
+            parse_pasv_port(&reply.message),
+            "failed to parse passive mode reply: {}",
+            reply.message
+        )?;
+        // use the control connection's address in case the server reports
+        // an internal address
+        let addr = SocketAddr::new(self.peer_ip, port);
+        let stream = cu::check!(
+            TcpStream::connect_timeout(&addr, TIMEOUT),
+            "failed to open data connection to {addr}"
+        )?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let reply = self.command(cmd)?;
+        match reply.code {
+            125 | 150 => Ok(Ok(stream)),
+            _ => Ok(Err(reply)),
+        }
+    }
+
+    /// Wait for the server to confirm the transfer
+    fn finish_data(&mut self, path: &str) -> cu::Result<()> {
+        let reply = self.read_reply()?;
+        if reply.code != 226 && reply.code != 250 {
+            cu::bail!("transfer of '{path}' failed: {reply:?}");
+        }
+        Ok(())
+    }
+
+    /// Send a command and check the reply code
+    fn expect(&mut self, cmd: &str, codes: &[u32]) -> cu::Result<Reply> {
+        let reply = self.command(cmd)?;
+        if !codes.contains(&reply.code) {
+            let verb = cmd.split(' ').next().unwrap_or(cmd);
+            cu::bail!("'{verb}' failed: {} {}", reply.code, reply.message);
+        }
+        Ok(reply)
+    }
+
+    /// Send a command and read the reply
+    fn command(&mut self, cmd: &str) -> cu::Result<Reply> {
+        if cmd.starts_with("PASS ") {
+            cu::trace!("ftp: > PASS ***");
+        } else {
+            cu::trace!("ftp: > {cmd}");
+        }
+        cu::check!(
+            self.writer.write_all(format!("{cmd}\r\n").as_bytes()),
+            "failed to send command to server"
+        )?;
+        self.read_reply()
+    }
+
+    /// Read a (possibly multi-line) reply
+    fn read_reply(&mut self) -> cu::Result<Reply> {
+        let first = self.read_line()?;
+        let code = cu::check!(
+            first.get(..3).and_then(|c| c.parse::<u32>().ok()),
+            "invalid reply from server: {first}"
+        )?;
+        let mut message = first.get(4..).unwrap_or_default().to_string();
+        if first.as_bytes().get(3) == Some(&b'-') {
+            // multi-line reply ends with `123 message`
+            let end = format!("{code:03} ");
+            loop {
+                let line = self.read_line()?;
+                message.push('\n');
+                message.push_str(line.strip_prefix(&end).unwrap_or(&line));
+                if line.starts_with(&end) || line == end.trim_end() {
+                    break;
+                }
+            }
+        }
+        Ok(Reply { code, message })
+    }
+
+    fn read_line(&mut self) -> cu::Result<String> {
+        let mut line = String::new();
+        let n = cu::check!(
+            self.reader.read_line(&mut line),
+            "failed to read reply from server"
+        )?;
+        if n == 0 {
+            cu::bail!("connection closed by server");
+        }
+        let line = line.trim_end().to_string();
+        cu::trace!("ftp: < {line}");
+        Ok(line)
+    }
+}
+
+/// Parse the port from `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)`
+fn parse_pasv_port(message: &str) -> Option<u16> {
+    let start = message.find('(')?;
+    let end = message[start..].find(')')? + start;
+    let parts = message[start + 1..end]
+        .split(',')
+        .map(|x| x.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if parts.len() != 6 {
+        return None;
+    }
+    Some(u16::from(parts[4]) << 8 | u16::from(parts[5]))
+}
+
+/// If the reply means the command is not recognized or not implemented
+fn is_not_implemented(reply: &Reply) -> bool {
+    matches!(reply.code, 500 | 502 | 504)
+}
+
+/// Parse a line like `type=file;size=123; name`
+fn parse_mlsd_line(line: &str) -> Option<Entry> {
+    let (facts, name) = line.split_once(' ')?;
+    let mut is_dir = false;
+    for fact in facts.split(';') {
+        let Some((key, value)) = fact.split_once('=') else {
+            continue;
+        };
+        if key.eq_ignore_ascii_case("type") {
+            let value = value.to_ascii_lowercase();
+            match value.as_str() {
+                // skip the current and parent directory
+                "cdir" | "pdir" => return None,
+                "dir" => is_dir = true,
+                _ => {}
+            }
+        }
+    }
+    Some(Entry {
+        name: name.to_string(),
+        is_dir,
+    })
+}
+
+/// Parse a line of `LIST` in the Unix `ls -l` format, like
+/// `drwxr-xr-x 1 user group 0 Jan 01 00:00 name`
+fn parse_list_line(line: &str) -> Option<Entry> {
+    let is_dir = match line.chars().next()? {
+        'd' => true,
+        '-' | 'l' => false,
+        // `total 123` and other lines
+        _ => return None,
+    };
+    // the name is after 8 fields, and may contain spaces
+    let mut rest = line;
+    for _ in 0..8 {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace)?..];
+    }
+    let name = rest.trim_start();
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+    Some(Entry {
+        name: name.to_string(),
+        is_dir,
+    })
+}
+
+#[cfg(test)]
+pub mod test_server {
+    //! A stand-in FTP server with an in-memory file system, for testing
+
+    use std::collections::BTreeMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+
+    /// Files in the server, path -> content. Directories have `None` as content
+    pub type Files = Arc<Mutex<BTreeMap<String, Option<Vec<u8>>>>>;
+
+    /// Start the server on a random port, serving one connection at a time
+    pub fn start(files: Files) -> u16 {
+        start_with(files, true)
+    }
+
+    /// Start the server, optionally without support for `MLSD`
+    pub fn start_with(files: Files, mlsd: bool) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    break;
+                };
+                handle(stream, &files, mlsd);
+            }
+        });
+        port
+    }
+
+    fn handle(stream: TcpStream, files: &Files, mlsd: bool) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut data_listener: Option<TcpListener> = None;
+        let _ = writer.write_all(b"220-Stand-in FTP server\r\n220 Ready\r\n");
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let line = line.trim_end();
+            let (cmd, arg) = line.split_once(' ').unwrap_or((line, ""));
+            let mut files = files.lock().unwrap();
+            let reply = match cmd {
+                "USER" => "331 Password required".to_string(),
+                "PASS" => "230 Logged in".to_string(),
+                "TYPE" => "200 OK".to_string(),
+                "PASV" => {
+                    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+                    let port = listener.local_addr().unwrap().port();
+                    data_listener = Some(listener);
+                    format!(
+                        "227 Entering Passive Mode (127,0,0,1,{},{})",
+                        port >> 8,
+                        port & 0xff
+                    )
+                }
+                "MKD" => {
+                    if files.contains_key(arg) {
+                        "550 Already exists".to_string()
+                    } else {
+                        files.insert(arg.to_string(), None);
+                        "257 Created".to_string()
+                    }
+                }
+                "DELE" => match files.remove(arg) {
+                    Some(_) => "250 Deleted".to_string(),
+                    None => "550 Not found".to_string(),
+                },
+                "MLSD" if !mlsd => "500 Unknown command".to_string(),
+                "STOR" | "RETR" | "MLSD" | "LIST" => {
+                    let listener = data_listener.take().unwrap();
+                    let content = match cmd {
+                        "STOR" => Some(vec![]),
+                        "RETR" => files.get(arg).cloned().flatten(),
+                        "MLSD" => files.get(arg).map(|_| list(&files, arg, true)),
+                        _ => files.get(arg).map(|_| list(&files, arg, false)),
+                    };
+                    let Some(content) = content else {
+                        let _ = writer.write_all(b"550 Not found\r\n");
+                        continue;
+                    };
+                    let _ = writer.write_all(b"150 Opening data connection\r\n");
+                    let (mut data, _) = listener.accept().unwrap();
+                    if cmd == "STOR" {
+                        let mut buf = vec![];
+                        data.read_to_end(&mut buf).unwrap();
+                        files.insert(arg.to_string(), Some(buf));
+                    } else {
+                        data.write_all(&content).unwrap();
+                    }
+                    drop(data);
+                    "226 Transfer complete".to_string()
+                }
+                "QUIT" => {
+                    let _ = writer.write_all(b"221 Bye\r\n");
+                    return;
+                }
+                _ => "502 Not implemented".to_string(),
+            };
+            let _ = writer.write_all(format!("{reply}\r\n").as_bytes());
+        }
+    }
+
+    fn list(files: &BTreeMap<String, Option<Vec<u8>>>, dir: &str, mlsd: bool) -> Vec<u8> {
+        let mut out = if mlsd {
+            String::from("type=cdir; .\r\n")
+        } else {
+            String::from("total 0\r\ndrwxr-xr-x 1 root root 0 Jan 01 00:00 .\r\n")
+        };
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        for (path, content) in files {
+            let Some(name) = path.strip_prefix(&prefix) else {
+                continue;
+            };
+            if name.contains('/') {
+                continue;
+            }
+            if mlsd {
+                let kind = if content.is_some() { "file" } else { "dir" };
+                out.push_str(&format!("type={kind};size=0; {name}\r\n"));
+            } else {
+                let kind = if content.is_some() { '-' } else { 'd' };
+                out.push_str(&format!(
+                    "{kind}rwxr-xr-x 1 root root 0 Jan 01 00:00 {name}\r\n"
+                ));
+            }
+        }
+        out.into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pasv() {
+        assert_eq!(
+            parse_pasv_port("Entering Passive Mode (192,168,0,170,19,137)"),
+            Some(19 * 256 + 137)
+        );
+        assert_eq!(parse_pasv_port("Entering Passive Mode"), None);
+        assert_eq!(parse_pasv_port("(1,2,3)"), None);
+    }
+
+    #[test]
+    fn parse_list() {
+        assert_eq!(
+            parse_list_line("drwxr-xr-x 1 root root 0 Jan 01 00:00 dumps"),
+            Some(Entry {
+                name: "dumps".to_string(),
+                is_dir: true
+            })
+        );
+        assert_eq!(
+            parse_list_line("-rw-rw-rw-   1 user  group   1234 Oct 18  2026 crash report.log"),
+            Some(Entry {
+                name: "crash report.log".to_string(),
+                is_dir: false
+            })
+        );
+        assert_eq!(parse_list_line("total 12"), None);
+        assert_eq!(
+            parse_list_line("drwxr-xr-x 1 root root 0 Jan 01 00:00 .."),
+            None
+        );
+    }
+
+    #[test]
+    fn round_trip() -> cu::Result<()> {
+        round_trip_with(true)
+    }
+
+    #[test]
+    fn round_trip_without_mlsd() -> cu::Result<()> {
+        round_trip_with(false)
+    }
+
+    fn round_trip_with(mlsd: bool) -> cu::Result<()> {
+        let files = test_server::Files::default();
+        let port = test_server::start_with(files.clone(), mlsd);
+        let mut client = FtpClient::connect("127.0.0.1", port)?;
+        client.login("anonymous", "")?;
+        client.make_dirs("/a/b")?;
+        client.make_dirs("/a/b/c")?;
+        client.put("/a/b/foo.bin", b"hello")?;
+        assert_eq!(client.get("/a/b/foo.bin")?, Some(b"hello".to_vec()));
+        assert_eq!(client.get("/a/b/bar.bin")?, None);
+        assert_eq!(
+            client.list("/a/b")?,
+            Some(vec![
+                Entry {
+                    name: "c".to_string(),
+                    is_dir: true
+                },
+                Entry {
+                    name: "foo.bin".to_string(),
+                    is_dir: false
+                },
+            ])
+        );
+        assert_eq!(client.list("/x")?, None);
+        client.delete("/a/b/foo.bin")?;
+        assert_eq!(client.get("/a/b/foo.bin")?, None);
+        client.quit()?;
+        Ok(())
+    }
+}
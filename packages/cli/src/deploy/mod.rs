@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Deploying the module to the console over FTP
+
+use std::path::Path;
+
+use cu::pre::*;
+
+use crate::buildsys::{self, BuildArgs, BuildOutput, MessageFormat};
+use crate::config::{self, Config, Deploy, PackageLayout};
+
+mod args;
+pub use args::*;
+mod ftp;
+use ftp::FtpClient;
+
+/// Directory on the console where Atmosphere puts crash reports
+static REMOTE_CRASH_REPORTS: &str = "/atmosphere/crash_reports";
+/// Log file written by libmegaton on the console
+static REMOTE_LOG_FILE: &str = "/megaton_logs.txt";
+
+/// Build and upload the module, or pull logs and crash reports with `--pull`
+pub async fn deploy(args: DeployArgs) -> cu::Result<()> {
+    let (root_path, manifest_path) = config::get_root_and_manifest(args.config.as_deref())?;
+    let config = config::load(&manifest_path)?;
+    let connection = resolve_connection(args.host.as_deref(), args.port)?;
+
+    if args.pull {
+        let profile = config.profile.resolve(&args.profile)?;
+        let reports_path = {
+            let mut p = config.module.target_path(&root_path);
+            p.extend(["megaton", profile, "reports"]);
+            p
+        };
+        let mut client = connect(&connection)?;
+        pull(&mut client, &reports_path, args.keep)?;
+        return client.quit();
+    }
+
+    let build_args = BuildArgs {
+        profile: args.profile,
+        configure: false,
+        message_format: MessageFormat::Human,
+        timings: false,
+        config: Some(manifest_path.as_utf8()?.to_string()),
+    };
+    let Some(output) = buildsys::run(build_args).await? else {
+        cu::bail!("build did not produce any output");
+    };
+    upload(&connection, &config, &output)
+}
+
+/// Upload the output of a build to the console
+pub fn upload(connection: &Deploy, config: &Config, output: &BuildOutput) -> cu::Result<()> {
+    let exefs_path =
+        buildsys::exefs_path(PackageLayout::Atmosphere, &config.module, &config.package)?;
+    let exefs_path = to_remote_path(&exefs_path);
+
+    let mut client = connect(connection)?;
+    client.make_dirs(&exefs_path)?;
+    let files = [
+        (&output.nso, config.package.subsdk.as_str()),
+        (&output.npdm, "main.npdm"),
+    ];
+    for (local, name) in files {
+        let remote = format!("{exefs_path}/{name}");
+        let data = cu::fs::read(local)?;
+        cu::check!(client.put(&remote, &data), "failed to upload '{remote}'")?;
+        cu::info!("uploaded {remote} ({})", cu::ByteFormat(data.len() as u64));
+    }
+    client.quit()
+}
+
+/// Get the connection settings from the user config, with the host and port
+/// overridden from the command line
+pub fn resolve_connection(host: Option<&str>, port: Option<u16>) -> cu::Result<Deploy> {
+    let user_config = config::load_user()?;
+    match override_connection(user_config.deploy, host, port) {
+        Some(connection) => Ok(connection),
+        None => {
+            cu::error!("FTP server of the console is not configured");
+            cu::hint!(
+                "- please set deploy.host in '{}', or specify --host",
+                config::user_config_path()?.display()
+            );
+            cu::bail!("failed to resolve deploy connection");
+        }
+    }
+}
+
+/// Override the host and port of the connection in the user config. The user
+/// and password in the user config are kept
+fn override_connection(
+    deploy: Option<Deploy>,
+    host: Option<&str>,
+    port: Option<u16>,
+) -> Option<Deploy> {
+    let mut connection = match (deploy, host) {
+        (Some(mut deploy), Some(host)) => {
+            deploy.host = host.to_string();
+            deploy
+        }
+        (Some(deploy), None) => deploy,
+        (None, Some(host)) => Deploy::new(host.to_string()),
+        (None, None) => return None,
+    };
+    if let Some(port) = port {
+        connection.port = port;
+    }
+    Some(connection)
+}
+
+fn connect(connection: &Deploy) -> cu::Result<FtpClient> {
+    cu::info!("connecting to {}:{}", connection.host, connection.port);
+    let mut client = cu::check!(
+        FtpClient::connect(&connection.host, connection.port),
+        "failed to connect to the console; please check the FTP server is running"
+    )?;
+    client.login(&connection.user, &connection.password)?;
+    Ok(client)
+}
+
+/// Download the log file and crash reports
+fn pull(client: &mut FtpClient, reports_path: &Path, keep: bool) -> cu::Result<()> {
+    cu::fs::make_dir(reports_path)?;
+    let log_name = REMOTE_LOG_FILE.trim_start_matches('/');
+    match client.get(REMOTE_LOG_FILE)? {
+        Some(data) => {
+            cu::fs::write(reports_path.join(log_name), data)?;
+            cu::info!("downloaded {REMOTE_LOG_FILE}");
+        }
+        None => cu::warn!("{REMOTE_LOG_FILE} not found on the console"),
+    }
+    let count = pull_dir(
+        client,
+        REMOTE_CRASH_REPORTS,
+        &reports_path.join("crash_reports"),
+        keep,
+    )?;
+    if count == 0 {
+        cu::info!("no crash reports found");
+    } else {
+        cu::info!("downloaded {count} crash report file(s)");
+    }
+    cu::info!("saved reports to {}", reports_path.try_to_rel().display());
+    Ok(())
+}
+
+/// Download files in a remote directory recursively, returns the number of files downloaded
+fn pull_dir(client: &mut FtpClient, remote: &str, local: &Path, keep: bool) -> cu::Result<usize> {
+    let Some(entries) = client.list(remote)? else {
+        cu::debug!("{remote} not found on the console");
+        return Ok(0);
+    };
+    let mut count = 0;
+    for entry in entries {
+        let remote_path = format!("{remote}/{}", entry.name);
+        let local_path = local.join(&entry.name);
+        if entry.is_dir {
+            count += pull_dir(client, &remote_path, &local_path, keep)?;
+            continue;
+        }
+        let Some(data) = client.get(&remote_path)? else {
+            cu::warn!("{remote_path} disappeared while downloading");
+            continue;
+        };
+        cu::fs::write(&local_path, data)?;
+        cu::debug!("downloaded {remote_path}");
+        count += 1;
+        if !keep {
+            client.delete(&remote_path)?;
+        }
+    }
+    Ok(count)
+}
+
+/// Convert a relative path to an absolute path on the console
+fn to_remote_path(path: &Path) -> String {
+    let mut out = String::new();
+    for c in path.components() {
+        out.push('/');
+        out.push_str(&c.as_os_str().to_string_lossy());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pull_reports() -> cu::Result<()> {
+        let files = ftp::test_server::Files::default();
+        {
+            let mut files = files.lock().unwrap();
+            files.insert("/megaton_logs.txt".to_string(), Some(b"log".to_vec()));
+            files.insert("/atmosphere/crash_reports".to_string(), None);
+            files.insert(
+                "/atmosphere/crash_reports/01234.log".to_string(),
+                Some(b"crash".to_vec()),
+            );
+            files.insert("/atmosphere/crash_reports/dumps".to_string(), None);
+            files.insert(
+                "/atmosphere/crash_reports/dumps/01234.bin".to_string(),
+                Some(b"dump".to_vec()),
+            );
+        }
+        let port = ftp::test_server::start(files.clone());
+        let reports_path =
+            std::env::temp_dir().join(format!("megaton-deploy-test-{}", std::process::id()));
+        let mut client = FtpClient::connect("127.0.0.1", port)?;
+        client.login("anonymous", "")?;
+        pull(&mut client, &reports_path, false)?;
+        client.quit()?;
+
+        let read = |p: &str| cu::fs::read_string(reports_path.join(p));
+        assert_eq!(read("megaton_logs.txt")?, "log");
+        assert_eq!(read("crash_reports/01234.log")?, "crash");
+        assert_eq!(read("crash_reports/dumps/01234.bin")?, "dump");
+        cu::fs::rec_remove(&reports_path)?;
+
+        // crash reports are removed from the console, but not the log
+        let files = files.lock().unwrap();
+        assert!(files.contains_key("/megaton_logs.txt"));
+        assert!(!files.contains_key("/atmosphere/crash_reports/01234.log"));
+        assert!(!files.contains_key("/atmosphere/crash_reports/dumps/01234.bin"));
+        Ok(())
+    }
+
+    #[test]
+    fn override_user_config() {
+        let mut deploy = Deploy::new("192.168.0.2".to_string());
+        deploy.port = 5001;
+        deploy.user = "switch".to_string();
+        deploy.password = "secret".to_string();
+
+        let connection = override_connection(Some(deploy.clone()), None, None).unwrap();
+        assert_eq!(connection, deploy);
+
+        // only the host and port are overridden
+        let connection =
+            override_connection(Some(deploy.clone()), Some("192.168.0.3"), Some(5000)).unwrap();
+        assert_eq!(connection.host, "192.168.0.3");
+        assert_eq!(connection.port, 5000);
+        assert_eq!(connection.user, "switch");
+        assert_eq!(connection.password, "secret");
+
+        let connection = override_connection(None, Some("192.168.0.3"), None).unwrap();
+        assert_eq!(connection, Deploy::new("192.168.0.3".to_string()));
+        assert!(override_connection(None, None, Some(5000)).is_none());
+    }
+
+    #[test]
+    fn remote_path() {
+        assert_eq!(
+            to_remote_path(Path::new("atmosphere/contents/01007EF00011E000/exefs")),
+            "/atmosphere/contents/01007EF00011E000/exefs"
+        );
+    }
+}
@@ -38,21 +38,52 @@ pub fn init() -> cu::Result<()> {
     Ok(())
 }
 
+/// Kind of the C/C++ compiler, which determines the default flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compiler {
+    /// GCC from devkitA64
+    #[default]
+    Gcc,
+    /// Clang, linking with lld
+    Clang,
+}
+
+impl std::fmt::Display for Compiler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gcc => write!(f, "gcc"),
+            Self::Clang => write!(f, "clang"),
+        }
+    }
+}
+
+impl Compiler {
+    /// The flag to output the diagnostics as JSON.
+    ///
+    /// Clang does not have JSON diagnostics, so they are only in the output
+    pub fn json_diagnostics_flag(self) -> Option<&'static str> {
+        match self {
+            Self::Gcc => Some("-fdiagnostics-format=json"),
+            Self::Clang => None,
+        }
+    }
+}
+
 // Core environment variables needed to run the tool
 // Includes paths to build/debug utilities and caches
 #[derive(Debug)]
 pub struct Environment {
     megaton_home: PathBuf,
 
+    compiler: Compiler,
     cc: PathBuf,  // C compiler
     cxx: PathBuf, // C++ compiler
     asm: PathBuf, // Assembler
     ar: PathBuf,  // Archiver
-    objdump: PathBuf,
-    npdmtool: PathBuf,
-    elf2nso: PathBuf,
 
     cc_version: String,
+    cxx_version: String,
 
     devkitpro: PathBuf,
     dkp_includes: Vec<String>,
@@ -64,37 +95,65 @@ impl Environment {
     fn new(megaton_home: PathBuf, devkitpro: PathBuf) -> cu::Result<Self> {
         let devkita64 = devkitpro.join("devkitA64");
         let dkp_bin = devkita64.join("bin");
-        let cc = dkp_bin.join("aarch64-none-elf-gcc");
-        let cxx = dkp_bin.join("aarch64-none-elf-g++");
-        let asm = dkp_bin.join("aarch64-none-elf-gcc");
-        let ar = dkp_bin.join("aarch64-none-elf-ar");
-        let objdump = dkp_bin.join("aarch64-none-elf-objdump");
+        let dkp_gcc = dkp_bin.join("aarch64-none-elf-gcc");
+
+        // the tools can be replaced with MEGATON_CC, MEGATON_CXX and MEGATON_AR,
+        // and the defaults are selected with MEGATON_TOOLCHAIN
+        let toolchain = cu::env_var("MEGATON_TOOLCHAIN").unwrap_or_default();
+        let (default_cc, default_cxx, default_ar) = match toolchain.as_str() {
+            "" | "gcc" => (
+                dkp_gcc.clone(),
+                dkp_bin.join("aarch64-none-elf-g++"),
+                dkp_bin.join("aarch64-none-elf-ar"),
+            ),
+            "clang" => (
+                PathBuf::from("clang"),
+                PathBuf::from("clang++"),
+                PathBuf::from("llvm-ar"),
+            ),
+            _ => cu::bail!("MEGATON_TOOLCHAIN must be 'gcc' or 'clang', got '{toolchain}'"),
+        };
+        let cc = find_tool("MEGATON_CC", default_cc)?;
+        let cxx = find_tool("MEGATON_CXX", default_cxx)?;
+        let asm = cc.clone();
+        let ar = find_tool("MEGATON_AR", default_ar)?;
 
         // FIXME: some of these checks requires running the bins
         // which might be able to get parallelized while parsing the config :)
 
-        let dkp_tools_bin = devkitpro.join("tools").join("bin");
-        let npdmtool = dkp_tools_bin.join("npdmtool");
-        let elf2nso = dkp_tools_bin.join("elf2nso");
+        let (compiler, cc_version) = get_cc_version(&cc)?;
+        let (cxx_compiler, cxx_version) = get_cc_version(&cxx)?;
+        if compiler != cxx_compiler {
+            cu::bail!(
+                "the C compiler is {compiler} ({}), but the C++ compiler is {cxx_compiler} ({}); check MEGATON_CC and MEGATON_CXX",
+                cc.display(),
+                cxx.display()
+            );
+        }
 
-        let cc_version = get_cc_version(&cc)?;
+        // libstdc++ and newlib headers from devkitA64 are used by both compilers,
+        // so the version of GCC in devkitA64 is needed to find them
+        let dkp_version = if cc == dkp_gcc {
+            cc_version.clone()
+        } else {
+            get_cc_version(&dkp_gcc)?.1
+        };
 
         // FIXME: remove if not needed
         // let dkp_version = get_dkp_version(&devkita64, &cc)
         //     .expect("Failed to init environment: check that DKP is installed correctly");
-        let dkp_includes = get_dkp_includes(&devkita64, &cc_version)?;
+        let dkp_includes = get_dkp_includes(&devkita64, &dkp_version, compiler)?;
 
         let cxxbridge = megaton_toolchain_build::cxxbridge::binary_path(&megaton_home).ok();
         Ok(Self {
             megaton_home,
+            compiler,
             cc,
             cxx,
             asm,
             ar,
-            objdump,
-            npdmtool,
-            elf2nso,
-            cc_version,
+            cc_version: format!("{compiler} {cc_version}"),
+            cxx_version: format!("{compiler} {cxx_version}"),
             devkitpro,
             dkp_includes,
             cxxbridge,
@@ -111,6 +170,9 @@ impl Environment {
     pub fn dkp_includes(&self) -> &[String] {
         &self.dkp_includes
     }
+    pub fn compiler(&self) -> Compiler {
+        self.compiler
+    }
     pub fn cc(&self) -> &Path {
         &self.cc
     }
@@ -123,18 +185,14 @@ impl Environment {
     pub fn ar(&self) -> &Path {
         &self.ar
     }
-    pub fn objdump(&self) -> &Path {
-        &self.objdump
-    }
-    pub fn npdmtool(&self) -> &Path {
-        &self.npdmtool
-    }
-    pub fn elf2nso(&self) -> &Path {
-        &self.elf2nso
-    }
+    /// Version of the C compiler, like `gcc 14.2.0`
     pub fn cc_version(&self) -> &str {
         &self.cc_version
     }
+    /// Version of the C++ compiler, like `gcc 14.2.0`
+    pub fn cxx_version(&self) -> &str {
+        &self.cxx_version
+    }
     pub fn cxxbridge(&self) -> cu::Result<&Path> {
         cu::check!(
             self.cxxbridge.as_deref(),
@@ -149,10 +207,9 @@ impl Environment {
         cu::debug!("cxx: {}", self.cxx.display());
         cu::debug!("as: {}", self.asm.display());
         cu::debug!("ar: {}", self.ar.display());
-        cu::debug!("objdump: {}", self.objdump.display());
-        cu::debug!("npdmtool: {}", self.npdmtool.display());
-        cu::debug!("elf2nso: {}", self.elf2nso.display());
-        cu::debug!("compiler version: {}", self.cc_version);
+        cu::debug!("compiler: {}", self.compiler);
+        cu::debug!("cc version: {}", self.cc_version);
+        cu::debug!("cxx version: {}", self.cxx_version);
         cu::debug!("system header paths: {:#?}", self.dkp_includes);
         match &self.cxxbridge {
             None => {
@@ -211,8 +268,25 @@ impl Environment {
 //     // }
 // }
 
-#[cu::context("failed to get devkitpro compiler version (path: '{}')", cc_path.display())]
-fn get_cc_version(cc_path: &Path) -> cu::Result<String> {
+/// Find a tool from the environment variable, or use the default.
+/// Names without a directory are searched in PATH
+fn find_tool(var: &str, default: PathBuf) -> cu::Result<PathBuf> {
+    let tool = match cu::env_var(var) {
+        Ok(x) if !x.is_empty() => PathBuf::from(x),
+        _ => default,
+    };
+    if tool.components().count() > 1 {
+        return Ok(tool);
+    }
+    let name = tool.as_utf8()?;
+    cu::check!(
+        cu::which(name),
+        "cannot find {name} in PATH (from {var} or MEGATON_TOOLCHAIN)"
+    )
+}
+
+#[cu::context("failed to get compiler version (path: '{}')", cc_path.display())]
+fn get_cc_version(cc_path: &Path) -> cu::Result<(Compiler, String)> {
     let (child, _, output) = cc_path
         .command()
         .arg("-v")
@@ -221,17 +295,36 @@ fn get_cc_version(cc_path: &Path) -> cu::Result<String> {
         .spawn()?;
     child.wait_nz()?;
     let output = output.join()??;
-    let verline = output.lines().last().unwrap_or(&output);
-    let Some(verstring) = verline.split(" ").nth(2) else {
+    let Some(version) = parse_cc_version(&output) else {
         cu::error!("cannot determine version from cc output:\n{output}");
         cu::bail!("cannot determine cc version: failed to parse output");
     };
+    Ok(version)
+}
 
-    Ok(verstring.to_owned())
+/// Parse the output of `<cc> -v`, which has a line like `gcc version 14.2.0 (devkitA64)`
+/// for GCC, or `clang version 18.1.8` (possibly with a vendor prefix) for Clang
+fn parse_cc_version(output: &str) -> Option<(Compiler, String)> {
+    for line in output.lines() {
+        let (compiler, rest) = if let Some(rest) = line.strip_prefix("gcc version ") {
+            (Compiler::Gcc, rest)
+        } else if let Some((_, rest)) = line.split_once("clang version ") {
+            (Compiler::Clang, rest)
+        } else {
+            continue;
+        };
+        let version = rest.split(' ').next().filter(|x| !x.is_empty())?;
+        return Some((compiler, version.to_string()));
+    }
+    None
 }
 
 #[cu::context("failed to get devkitpro system include paths")]
-fn get_dkp_includes(devkita64: &Path, cc_version: &str) -> cu::Result<Vec<String>> {
+fn get_dkp_includes(
+    devkita64: &Path,
+    cc_version: &str,
+    compiler: Compiler,
+) -> cu::Result<Vec<String>> {
     // /opt/devkitpro/devkitA64/aarch64-none-elf/include
     let arch_include_path = {
         let mut p = devkita64.join("aarch64-none-elf");
@@ -251,16 +344,58 @@ fn get_dkp_includes(devkita64: &Path, cc_version: &str) -> cu::Result<Vec<String
         p
     };
 
-    Ok(vec![
+    let mut includes = vec![
         arch_include_path.into_utf8()?,
         cpp_include_path.join("aarch64-none-elf").into_utf8()?,
         cpp_include_path.join("backward").into_utf8()?,
         cpp_include_path.into_utf8()?,
-        gcc_include_path.join("include").into_utf8()?,
-        {
+    ];
+    // clang has its own builtin headers (stddef.h, arm_neon.h, ...)
+    if compiler == Compiler::Gcc {
+        includes.push(gcc_include_path.join("include").into_utf8()?);
+        includes.push({
             let mut p = gcc_include_path;
             p.push("include-fixed");
             p.into_utf8()?
-        },
-    ])
+        });
+    }
+    Ok(includes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_diagnostics() {
+        assert_eq!(
+            Compiler::Gcc.json_diagnostics_flag(),
+            Some("-fdiagnostics-format=json")
+        );
+        assert_eq!(Compiler::Clang.json_diagnostics_flag(), None);
+    }
+
+    #[test]
+    fn cc_version() {
+        let gcc = "Using built-in specs.
+COLLECT_GCC=aarch64-none-elf-gcc
+Target: aarch64-none-elf
+Thread model: single
+gcc version 14.2.0 (devkitA64)
+";
+        assert_eq!(
+            parse_cc_version(gcc),
+            Some((Compiler::Gcc, "14.2.0".to_string()))
+        );
+        let clang = "Ubuntu clang version 18.1.3 (1ubuntu1)
+Target: x86_64-pc-linux-gnu
+Thread model: posix
+InstalledDir: /usr/bin
+";
+        assert_eq!(
+            parse_cc_version(clang),
+            Some((Compiler::Clang, "18.1.3".to_string()))
+        );
+        assert_eq!(parse_cc_version("Target: aarch64-none-elf"), None);
+    }
 }
@@ -4,4 +4,9 @@
 pub mod buildsys;
 pub mod cmds;
 pub mod config;
+pub mod deploy;
 pub mod env;
+pub mod scaffold;
+pub mod symbolize;
+pub mod symbols;
+pub mod watch;
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use std::fmt::Display;
+
+use cu::pre::*;
+
+#[derive(Debug, clap::Parser)]
+pub struct InitArgs {
+    /// Name of the module. Defaults to the name of the project directory
+    #[clap(short, long)]
+    pub name: Option<String>,
+
+    /// Title ID of the target game, in hex (0x0100...) or decimal
+    ///
+    /// If not specified, a placeholder is used and must be changed in Megaton.toml
+    #[clap(short, long, value_parser = parse_title_id)]
+    pub title_id: Option<u64>,
+
+    /// The project template to use
+    #[clap(short = 'T', long, default_value = "cpp")]
+    pub template: Template,
+}
+
+/// Project templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Template {
+    /// C/C++ only, Rust is disabled
+    Cpp,
+    /// Rust crate with a minimal C++ entry point
+    Rust,
+    /// C++ and Rust, with a `cxx::bridge` between them
+    Mixed,
+}
+
+impl Template {
+    pub fn rust_enabled(self) -> bool {
+        !matches!(self, Self::Cpp)
+    }
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cpp => write!(f, "C++"),
+            Self::Rust => write!(f, "Rust"),
+            Self::Mixed => write!(f, "C++/Rust"),
+        }
+    }
+}
+
+fn parse_title_id(s: &str) -> Result<u64, String> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|e| format!("invalid title id '{s}': {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_id_hex_and_decimal() {
+        assert_eq!(parse_title_id("0x01007ef00011e000"), Ok(0x01007ef00011e000));
+        assert_eq!(parse_title_id("0X01007EF00011E000"), Ok(0x01007ef00011e000));
+        assert_eq!(parse_title_id("42"), Ok(42));
+        assert!(parse_title_id("0xzz").is_err());
+        assert!(parse_title_id("").is_err());
+    }
+}
@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Project scaffolding for `megaton new` and `megaton init`
+
+use std::path::{Path, PathBuf};
+
+use cu::pre::*;
+
+use crate::config;
+
+mod args;
+pub use args::*;
+
+/// Title ID used in the generated config when one is not specified
+static PLACEHOLDER_TITLE_ID: u64 = 0x0100000000010000;
+
+/// A file to be created in the new project
+#[derive(Debug)]
+pub struct TemplateFile {
+    /// Path relative to the project root
+    pub path: PathBuf,
+    pub content: String,
+}
+
+impl TemplateFile {
+    fn new(path: impl Into<PathBuf>, template: &str, vars: &[(&str, &str)]) -> Self {
+        let mut content = template.to_string();
+        for (key, value) in vars {
+            content = content.replace(&format!("{{{{{key}}}}}"), value);
+        }
+        Self {
+            path: path.into(),
+            content,
+        }
+    }
+}
+
+/// Render all files of a template for a module
+pub fn render(name: &str, title_id: u64, template: Template) -> Vec<TemplateFile> {
+    let title_id = format!("0x{title_id:016x}");
+    let includes = match template {
+        Template::Mixed => "\"include\"",
+        _ => "",
+    };
+    let rust = template.rust_enabled().to_string();
+    let cargo_deps = match template {
+        Template::Mixed => format!(
+            "cxx = \"{}\"\n",
+            megaton_toolchain_build::cxxbridge::BLESSED_VERSION
+        ),
+        _ => String::new(),
+    };
+    let vars = [
+        ("name", name),
+        ("title_id", &title_id),
+        ("version", env!("CARGO_PKG_VERSION")),
+        ("includes", includes),
+        ("rust", &rust),
+        ("cargo_deps", &cargo_deps),
+    ];
+
+    let mut files = vec![
+        TemplateFile::new(
+            "Megaton.toml",
+            include_str!("template/Megaton.toml.in"),
+            &vars,
+        ),
+        TemplateFile::new(".gitignore", include_str!("template/gitignore"), &vars),
+    ];
+    match template {
+        Template::Cpp => {
+            files.push(TemplateFile::new(
+                "src/main.cpp",
+                include_str!("template/main.cpp"),
+                &vars,
+            ));
+        }
+        Template::Rust => {
+            files.push(TemplateFile::new(
+                "src/main.cpp",
+                include_str!("template/main_rust.cpp"),
+                &vars,
+            ));
+            files.push(TemplateFile::new(
+                "src/lib.rs",
+                include_str!("template/lib.rs"),
+                &vars,
+            ));
+        }
+        Template::Mixed => {
+            files.push(TemplateFile::new(
+                "src/main.cpp",
+                include_str!("template/main_mixed.cpp"),
+                &vars,
+            ));
+            files.push(TemplateFile::new(
+                format!("include/{name}/mod.h"),
+                include_str!("template/mod.h"),
+                &vars,
+            ));
+            files.push(TemplateFile::new(
+                "src/lib.rs",
+                include_str!("template/lib_mixed.rs"),
+                &vars,
+            ));
+        }
+    }
+    if template.rust_enabled() {
+        files.push(TemplateFile::new(
+            "Cargo.toml",
+            include_str!("template/Cargo.toml.in"),
+            &vars,
+        ));
+    }
+
+    files
+}
+
+/// Create a new project at `root`, which must exist. Existing files are not overwritten
+pub fn init(root: &Path, args: &InitArgs) -> cu::Result<()> {
+    let manifest_path = root.join("Megaton.toml");
+    if manifest_path.exists() {
+        cu::bail!(
+            "'{}' already exists; the directory is already a megaton project",
+            manifest_path.display()
+        );
+    }
+
+    let name = match &args.name {
+        Some(name) => name.clone(),
+        None => root.file_name_str()?.to_string(),
+    };
+    let title_id = match args.title_id {
+        Some(title_id) => title_id,
+        None => {
+            cu::warn!("title id not specified, using a placeholder");
+            cu::hint!("- please set module.title-id in Megaton.toml to the title id of the game");
+            PLACEHOLDER_TITLE_ID
+        }
+    };
+
+    for file in render(&name, title_id, args.template) {
+        let path = root.join(&file.path);
+        if path.exists() {
+            cu::warn!("skipping '{}': file already exists", file.path.display());
+            continue;
+        }
+        cu::fs::write(&path, file.content)?;
+        cu::debug!("created {}", file.path.display());
+    }
+
+    // loading the config also validates the module name
+    cu::check!(
+        config::load(&manifest_path),
+        "generated config is invalid; please check the module name"
+    )?;
+    cu::info!("created {} project '{name}'", args.template);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_rendered(name: &str, template: Template) -> cu::Result<config::Config> {
+        let files = render(name, 0x01007ef00011e000, template);
+        let manifest = files
+            .iter()
+            .find(|f| f.path == Path::new("Megaton.toml"))
+            .expect("Megaton.toml not rendered");
+        let dir = std::env::temp_dir().join(format!(
+            "megaton-scaffold-test-{}-{name}",
+            std::process::id()
+        ));
+        let manifest_path = dir.join("Megaton.toml");
+        cu::fs::write(&manifest_path, &manifest.content)?;
+        let config = config::load(&manifest_path);
+        cu::fs::rec_remove(&dir)?;
+        config
+    }
+
+    #[test]
+    fn cpp_template_is_valid() -> cu::Result<()> {
+        let config = load_rendered("my-cpp-mod", Template::Cpp)?;
+        assert_eq!(config.module.name, "my-cpp-mod");
+        assert_eq!(config.module.title_id, 0x01007ef00011e000);
+        assert_eq!(config.cargo.enabled, Some(false));
+        Ok(())
+    }
+
+    #[test]
+    fn rust_template_is_valid() -> cu::Result<()> {
+        let config = load_rendered("my_rust_mod", Template::Rust)?;
+        assert_eq!(config.cargo.enabled, Some(true));
+        Ok(())
+    }
+
+    #[test]
+    fn mixed_template_is_valid() -> cu::Result<()> {
+        let config = load_rendered("mixed", Template::Mixed)?;
+        assert_eq!(config.cargo.enabled, Some(true));
+        assert_eq!(
+            config.build.get_profile(config::BASE_PROFILE).includes,
+            vec![PathBuf::from("include")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn no_unreplaced_vars() {
+        for template in [Template::Cpp, Template::Rust, Template::Mixed] {
+            for file in render("foo", 0, template) {
+                assert!(
+                    !file.content.contains("{{"),
+                    "unreplaced variable in {}",
+                    file.path.display()
+                );
+            }
+        }
+    }
+}
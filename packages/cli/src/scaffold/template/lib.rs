@@ -0,0 +1,3 @@
+// Called from `megaton::rust_main()` in src/main.cpp
+#[megaton::main]
+fn main() {}
@@ -0,0 +1,23 @@
+// The megaton build tool generates the C++ sources and headers
+// for the bridge. See https://cxx.rs for more info
+#[cxx::bridge]
+mod ffi {
+    unsafe extern "C++" {
+        include!("{{name}}/mod.h");
+
+        fn add_in_cpp(left: u64, right: u64) -> u64;
+    }
+    extern "Rust" {
+        fn add_in_rust(left: u64, right: u64) -> u64;
+    }
+}
+
+fn add_in_rust(left: u64, right: u64) -> u64 {
+    left + right
+}
+
+// Called from `megaton::rust_main()` in src/main.cpp
+#[megaton::main]
+fn main() {
+    let _ = ffi::add_in_cpp(1, 2);
+}
@@ -0,0 +1,6 @@
+#include <megaton/prelude.h>
+
+// This is the main mod entry point which will be called by the
+// megaton runtime library after it is initialized
+extern "C" void megaton_main() {
+}
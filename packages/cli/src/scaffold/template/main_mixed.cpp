@@ -0,0 +1,18 @@
+#include <megaton/prelude.h>
+#include <megaton/main.h>
+
+#include <{{name}}/mod.h>
+// Header generated by cxx for src/lib.rs
+#include <lib.h>
+
+// Exported to Rust through the cxx::bridge in src/lib.rs
+std::uint64_t add_in_cpp(std::uint64_t left, std::uint64_t right) {
+    return left + right;
+}
+
+// This is the main mod entry point which will be called by the
+// megaton runtime library after it is initialized
+extern "C" void megaton_main() {
+    // Call the function annotated with #[megaton::main]
+    megaton::rust_main();
+}
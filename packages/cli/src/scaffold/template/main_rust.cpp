@@ -0,0 +1,9 @@
+#include <megaton/prelude.h>
+#include <megaton/main.h>
+
+// This is the main mod entry point which will be called by the
+// megaton runtime library after it is initialized
+extern "C" void megaton_main() {
+    // Call the function annotated with #[megaton::main]
+    megaton::rust_main();
+}
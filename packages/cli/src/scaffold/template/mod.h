@@ -0,0 +1,5 @@
+#pragma once
+
+#include <cstdint>
+
+std::uint64_t add_in_cpp(std::uint64_t left, std::uint64_t right);
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use std::path::PathBuf;
+
+use cu::pre::*;
+
+#[derive(Debug, clap::Parser)]
+pub struct CrashReportArgs {
+    /// Path to the crash report
+    pub file: PathBuf,
+
+    /// Select profile of the ELF to symbolize with
+    ///
+    /// See https://megaton-new.pistonite.dev/tutorial/profiles
+    #[clap(short, long, default_value = "none")]
+    pub profile: String,
+
+    /// Name of the module in the crash report, defaults to the module name
+    #[clap(short, long)]
+    pub module: Option<String>,
+
+    /// Path to the ELF to symbolize with, instead of the one in the target directory
+    #[clap(long)]
+    pub elf: Option<PathBuf>,
+
+    /// Specify the location of the Megaton.toml config file
+    #[clap(short = 'c', long)]
+    pub config: Option<String>,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct SymbolizeArgs {
+    /// Offsets in the module to symbolize, in hex (0x prefix is optional)
+    ///
+    /// If --base is specified, these are absolute addresses instead
+    #[clap(required = true, value_parser = parse_address)]
+    pub addresses: Vec<u64>,
+
+    /// Base address of the module, for symbolizing absolute addresses
+    #[clap(short, long, value_parser = parse_address)]
+    pub base: Option<u64>,
+
+    /// Select profile of the ELF to symbolize with
+    ///
+    /// See https://megaton-new.pistonite.dev/tutorial/profiles
+    #[clap(short, long, default_value = "none")]
+    pub profile: String,
+
+    /// Path to the ELF to symbolize with, instead of the one in the target directory
+    #[clap(long)]
+    pub elf: Option<PathBuf>,
+
+    /// Specify the location of the Megaton.toml config file
+    #[clap(short = 'c', long)]
+    pub config: Option<String>,
+}
+
+fn parse_address(s: &str) -> Result<u64, String> {
+    super::parse_hex(s).ok_or_else(|| format!("invalid hex address '{s}'"))
+}
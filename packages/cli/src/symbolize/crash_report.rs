@@ -0,0 +1,332 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Parsing and symbolizing Atmosphere crash reports
+
+use cu::pre::*;
+
+use crate::buildsys;
+use crate::config;
+use crate::symbolize::{CrashReportArgs, Symbolizer, parse_hex};
+
+/// Value of x27 when aborting from libmegaton
+const ABORT_X27: u64 = 0x6969696969696969;
+/// Value of x28 when aborting from `__megaton_abort` (i.e. panic)
+const PANIC_X28: u64 = 0x00DEAD0000DEAD00;
+/// Value of x28 when aborting from `__megaton_crt_abort`
+const CRT_ABORT_X28: u64 = 0x0CCCCCCCCCCCCCCC;
+
+/// Data parsed from an Atmosphere crash report
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CrashReport {
+    /// The `Result` of the crash
+    pub result: Option<String>,
+    /// Program ID of the crashed process
+    pub program_id: Option<String>,
+    /// The exception type, like `Data Abort`
+    pub exception_type: Option<String>,
+    /// The address that caused the exception
+    pub exception_address: Option<u64>,
+    /// Registers of the crashed thread, in the order of the report
+    pub registers: Vec<(String, Address)>,
+    /// Return addresses in the stack trace of the crashed thread
+    pub stack_trace: Vec<Address>,
+    /// Modules loaded in the process
+    pub modules: Vec<ModuleInfo>,
+}
+
+/// An address in the report
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    pub value: u64,
+    /// `(module name, offset)`, if annotated by the report
+    pub annotation: Option<(String, u64)>,
+}
+
+/// A module loaded in the process
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleInfo {
+    pub name: Option<String>,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// What caused the crash, as far as the report can tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortKind {
+    /// `__megaton_abort`, called after a panic
+    Panic,
+    /// `__megaton_crt_abort`, called when the runtime failed to initialize
+    CrtAbort,
+}
+
+impl CrashReport {
+    /// Parse the text of a crash report
+    pub fn parse(content: &str) -> cu::Result<Self> {
+        let mut report = Self::default();
+        let mut section = "";
+        for line in content.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.trim().split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            // section headers are not indented
+            if !line.starts_with(char::is_whitespace) {
+                if key == "Result" {
+                    report.result = Some(value.to_string());
+                } else {
+                    section = key;
+                }
+                continue;
+            }
+            match (section, key) {
+                ("Crashed Process Info", "Program ID") => {
+                    report.program_id = Some(value.to_string());
+                }
+                ("Exception Info", "Type") => {
+                    report.exception_type = Some(value.to_string());
+                }
+                ("Exception Info", "Address") => {
+                    report.exception_address = parse_hex(value);
+                }
+                ("Crashed Thread Info", "FP" | "LR" | "SP" | "PC") => {
+                    if let Some(address) = parse_address(value) {
+                        report.registers.push((key.to_string(), address));
+                    }
+                }
+                ("Crashed Thread Info", _) if key.starts_with("X[") => {
+                    if let Some(address) = parse_address(value) {
+                        let name = key.trim_start_matches("X[").trim_end_matches(']');
+                        report.registers.push((format!("X{name}"), address));
+                    }
+                }
+                ("Crashed Thread Info", _) if key.starts_with("ReturnAddress[") => {
+                    if let Some(address) = parse_address(value) {
+                        report.stack_trace.push(address);
+                    }
+                }
+                ("Module Info", _) if key.starts_with("Module ") => {
+                    report.modules.push(ModuleInfo {
+                        name: None,
+                        start: 0,
+                        end: 0,
+                    });
+                }
+                ("Module Info", "Address") => {
+                    let (Some(module), Some((start, end))) =
+                        (report.modules.last_mut(), value.split_once('-'))
+                    else {
+                        continue;
+                    };
+                    module.start = parse_hex(start).unwrap_or_default();
+                    module.end = parse_hex(end).unwrap_or_default();
+                }
+                ("Module Info", "Name") => {
+                    if let Some(module) = report.modules.last_mut() {
+                        module.name = Some(value.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        if report.registers.is_empty() && report.stack_trace.is_empty() {
+            cu::bail!("no crashed thread info found; is this an Atmosphere crash report?");
+        }
+        Ok(report)
+    }
+
+    /// Get the value of a register of the crashed thread
+    pub fn register(&self, name: &str) -> Option<u64> {
+        self.registers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, a)| a.value)
+    }
+
+    /// Check the sentinel values set by libmegaton before aborting
+    pub fn abort_kind(&self) -> Option<AbortKind> {
+        if self.register("X27")? != ABORT_X27 {
+            return None;
+        }
+        match self.register("X28")? {
+            PANIC_X28 => Some(AbortKind::Panic),
+            CRT_ABORT_X28 => Some(AbortKind::CrtAbort),
+            _ => None,
+        }
+    }
+
+    /// Find the module and offset in the module of an address
+    pub fn locate<'a>(&'a self, address: &'a Address) -> Option<(&'a str, u64)> {
+        for module in &self.modules {
+            if module.start <= address.value && address.value < module.end {
+                let name = module.name.as_deref().unwrap_or("?");
+                return Some((name, address.value - module.start));
+            }
+        }
+        let (name, offset) = address.annotation.as_ref()?;
+        Some((name, *offset))
+    }
+}
+
+/// Parse `0000000008057e70 (name + 0x1bf70)` or `0000000008057e70`
+fn parse_address(s: &str) -> Option<Address> {
+    let (value, rest) = s.split_once(' ').unwrap_or((s, ""));
+    let value = parse_hex(value)?;
+    let annotation = rest
+        .trim()
+        .strip_prefix('(')
+        .and_then(|x| x.strip_suffix(')'))
+        .and_then(|x| x.rsplit_once(" + "))
+        .and_then(|(name, offset)| Some((name.to_string(), parse_hex(offset)?)));
+    Some(Address { value, annotation })
+}
+
+/// Run the `crash-report` command
+pub fn symbolize_crash_report(args: CrashReportArgs) -> cu::Result<()> {
+    let content = cu::fs::read_string(&args.file)?;
+    let report = cu::check!(
+        CrashReport::parse(&content),
+        "failed to parse crash report '{}'",
+        args.file.display()
+    )?;
+
+    let (root_path, manifest_path) = config::get_root_and_manifest(args.config.as_deref())?;
+    let config = config::load(&manifest_path)?;
+    let profile = config.profile.resolve(&args.profile)?;
+    let module_name = args.module.as_deref().unwrap_or(&config.module.name);
+    let elf_path = match &args.elf {
+        Some(elf) => elf.clone(),
+        None => buildsys::elf_path(&root_path, &config.module, profile),
+    };
+    let symbolizer = Symbolizer::open(&elf_path)?;
+    if !report.modules.is_empty()
+        && !report
+            .modules
+            .iter()
+            .any(|m| m.name.as_deref() == Some(module_name))
+    {
+        cu::warn!("module '{module_name}' is not loaded in the crashed process");
+        cu::hint!("- use --module to specify the name of the module in the report");
+    }
+
+    cu::lv::disable_print_time();
+    cu::print!("crash report: {}", args.file.display());
+    if let Some(program_id) = &report.program_id {
+        cu::print!("  program id: {program_id}");
+    }
+    if let Some(result) = &report.result {
+        cu::print!("  result:     {result}");
+    }
+    match (&report.exception_type, report.exception_address) {
+        (Some(t), Some(a)) => cu::print!("  exception:  {t} at {a:016x}"),
+        (Some(t), None) => cu::print!("  exception:  {t}"),
+        _ => {}
+    }
+    match report.abort_kind() {
+        Some(AbortKind::Panic) => {
+            cu::print!("  cause:      megaton panic (__megaton_abort)");
+            cu::hint!("the panic message is in the logs, pull it with `megaton deploy --pull`");
+        }
+        Some(AbortKind::CrtAbort) => {
+            cu::print!("  cause:      CRT abort (__megaton_crt_abort)");
+        }
+        None => {}
+    }
+
+    let symbolize = |address: &Address, is_return_address: bool| -> cu::Result<String> {
+        let Some((name, offset)) = report.locate(address) else {
+            return Ok(format!("{:016x}", address.value));
+        };
+        let mut out = format!("{:016x}  {name} + {offset:#x}", address.value);
+        if name == module_name {
+            let symbolized = symbolizer.symbolize(offset, is_return_address)?;
+            out.push_str(&format!("  {symbolized}"));
+        }
+        Ok(out)
+    };
+
+    cu::print!("registers:");
+    for (name, address) in &report.registers {
+        if name != "PC" && name != "LR" {
+            continue;
+        }
+        cu::print!("  {name:<4}{}", symbolize(address, name == "LR")?);
+    }
+    if !report.stack_trace.is_empty() {
+        cu::print!("stack trace:");
+        for (i, address) in report.stack_trace.iter().enumerate() {
+            cu::print!("  #{i:02} {}", symbolize(address, true)?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static REPORT: &str = r#"Atmosphère Crash Report (v1.7):
+Result:                          0x2A8 (2168-0001)
+
+Crashed Process Info:
+    Program ID:                  01007ef00011e000
+    Process Name:                U
+
+Exception Info:
+    Type:                        Data Abort
+    Address:                     6969696969696969
+
+Crashed Thread Info:
+    Thread ID:                   0000000000000083
+    Registers:
+        X[00]:                   0000000000000000
+        X[27]:                   6969696969696969
+        X[28]:                   00dead0000dead00
+        FP:                      0000000013fbee00
+        LR:                      0000000008a01234 (test-mod + 0x1234)
+        SP:                      0000000013fbed40
+        PC:                      0000000008a00100 (test-mod + 0x100)
+    Stack Trace:
+        ReturnAddress[00]:       0000000008a01234 (test-mod + 0x1234)
+        ReturnAddress[01]:       0000000008100200 (main + 0x100200)
+
+Module Info:
+    Number of Modules:           02
+    Module 00:
+        Address:                 0000000008000000-0000000008900000
+        Name:                    main
+    Module 01:
+        Address:                 0000000008a00000-0000000008b00000
+        Name:                    test-mod
+"#;
+
+    #[test]
+    fn parse_report() -> cu::Result<()> {
+        let report = CrashReport::parse(REPORT)?;
+        assert_eq!(report.result.as_deref(), Some("0x2A8 (2168-0001)"));
+        assert_eq!(report.program_id.as_deref(), Some("01007ef00011e000"));
+        assert_eq!(report.exception_type.as_deref(), Some("Data Abort"));
+        assert_eq!(report.register("PC"), Some(0x8a00100));
+        assert_eq!(report.abort_kind(), Some(AbortKind::Panic));
+        assert_eq!(report.stack_trace.len(), 2);
+        assert_eq!(report.modules.len(), 2);
+        assert_eq!(
+            report.locate(&report.stack_trace[0]),
+            Some(("test-mod", 0x1234))
+        );
+        assert_eq!(
+            report.locate(&report.stack_trace[1]),
+            Some(("main", 0x100200))
+        );
+
+        let crt = REPORT.replace("00dead0000dead00", "0ccccccccccccccc");
+        assert_eq!(
+            CrashReport::parse(&crt)?.abort_kind(),
+            Some(AbortKind::CrtAbort)
+        );
+        Ok(())
+    }
+}
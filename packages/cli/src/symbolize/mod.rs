@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Turning module offsets into functions and source locations
+
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::path::Path;
+
+mod args;
+pub use args::*;
+mod crash_report;
+pub use crash_report::*;
+mod offsets;
+pub use offsets::*;
+
+/// Parse a hex number, with or without the 0x prefix
+pub(crate) fn parse_hex(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u64::from_str_radix(s, 16).ok()
+}
+
+/// Symbolizer for offsets in the module, backed by the symbol table
+/// and DWARF info in the linked ELF
+pub struct Symbolizer {
+    loader: addr2line::Loader,
+}
+
+impl Symbolizer {
+    /// Load the ELF file
+    pub fn open(elf_path: &Path) -> cu::Result<Self> {
+        if !elf_path.exists() {
+            cu::bail!(
+                "'{}' does not exist; please build the module first",
+                elf_path.display()
+            );
+        }
+        let loader = addr2line::Loader::new(elf_path)
+            .map_err(|e| cu::fmterr!("failed to load ELF '{}': {e}", elf_path.display()))?;
+        Ok(Self { loader })
+    }
+
+    /// Symbolize an offset in the module
+    ///
+    /// If the offset is a return address (for example LR or a stack trace entry),
+    /// the source location is looked up for the call instruction before it
+    pub fn symbolize(&self, offset: u64, is_return_address: bool) -> cu::Result<Symbolized> {
+        let symbol = self.loader.find_symbol_info(offset).map(|symbol| {
+            let name = addr2line::demangle_auto(Cow::Borrowed(symbol.name()), None);
+            (name.into_owned(), offset - symbol.address())
+        });
+
+        let probe = if is_return_address {
+            offset.saturating_sub(4)
+        } else {
+            offset
+        };
+        let mut frames = vec![];
+        let mut iter = self
+            .loader
+            .find_frames(probe)
+            .map_err(|e| cu::fmterr!("failed to read debug info: {e}"))?;
+        while let Some(frame) = iter
+            .next()
+            .map_err(|e| cu::fmterr!("failed to read debug info: {e}"))?
+        {
+            let function = match &frame.function {
+                Some(f) => Some(
+                    f.demangle()
+                        .map_err(|e| cu::fmterr!("failed to read function name: {e}"))?
+                        .into_owned(),
+                ),
+                None => None,
+            };
+            let (file, line) = match &frame.location {
+                Some(loc) => (loc.file.map(|x| x.to_string()), loc.line),
+                None => (None, None),
+            };
+            frames.push(SourceFrame {
+                function,
+                file,
+                line,
+            });
+        }
+
+        Ok(Symbolized {
+            offset,
+            symbol,
+            frames,
+        })
+    }
+}
+
+/// Result of symbolizing an offset
+#[derive(Debug, Clone)]
+pub struct Symbolized {
+    /// The offset in the module
+    pub offset: u64,
+    /// The containing symbol (demangled) and the offset into it
+    pub symbol: Option<(String, u64)>,
+    /// Source frames, from the innermost inlined function to the outermost function
+    pub frames: Vec<SourceFrame>,
+}
+
+/// A function and source location from debug info
+#[derive(Debug, Clone)]
+pub struct SourceFrame {
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl SourceFrame {
+    /// Format the location as `file:line`
+    pub fn location(&self) -> Option<String> {
+        let file = self.file.as_ref()?;
+        Some(match self.line {
+            Some(line) => format!("{file}:{line}"),
+            None => file.clone(),
+        })
+    }
+}
+
+/// Displays as `function+0x10 (file:line)`
+impl Display for Symbolized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let function = self
+            .frames
+            .last()
+            .and_then(|frame| frame.function.as_deref());
+        match (&self.symbol, function) {
+            (Some((name, 0)), _) => write!(f, "{name}")?,
+            (Some((name, off)), _) => write!(f, "{name}+{off:#x}")?,
+            (None, Some(function)) => write!(f, "{function}")?,
+            (None, None) => write!(f, "??")?,
+        }
+        // the innermost frame has the actual source location
+        if let Some(location) = self.frames.first().and_then(|frame| frame.location()) {
+            write!(f, " ({location})")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_with_and_without_prefix() {
+        assert_eq!(parse_hex("0x1bf70"), Some(0x1bf70));
+        assert_eq!(parse_hex("0X1BF70"), Some(0x1bf70));
+        assert_eq!(parse_hex("0000000008057e70"), Some(0x8057e70));
+        assert_eq!(parse_hex("0xzz"), None);
+    }
+}
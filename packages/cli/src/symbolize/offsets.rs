@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use crate::buildsys;
+use crate::config;
+use crate::symbolize::{SymbolizeArgs, Symbolizer};
+
+/// Run the `symbolize` command
+pub fn symbolize_offsets(args: SymbolizeArgs) -> cu::Result<()> {
+    let elf_path = match args.elf {
+        Some(elf) => elf,
+        None => {
+            let (root_path, manifest_path) = config::get_root_and_manifest(args.config.as_deref())?;
+            let config = config::load(&manifest_path)?;
+            let profile = config.profile.resolve(&args.profile)?;
+            buildsys::elf_path(&root_path, &config.module, profile)
+        }
+    };
+    let symbolizer = Symbolizer::open(&elf_path)?;
+
+    cu::lv::disable_print_time();
+    for address in args.addresses {
+        let offset = match args.base {
+            None => address,
+            Some(base) => {
+                let Some(offset) = address.checked_sub(base) else {
+                    cu::warn!("{address:#x} is before the module base {base:#x}");
+                    continue;
+                };
+                offset
+            }
+        };
+        let symbolized = symbolizer.symbolize(offset, false)?;
+        match args.base {
+            None => cu::print!("{offset:#x}: {symbolized}"),
+            Some(_) => cu::print!("{address:#x} (+{offset:#x}): {symbolized}"),
+        }
+        // print the inline chain, from the innermost function
+        if symbolized.frames.len() > 1 {
+            for (i, frame) in symbolized.frames.iter().enumerate() {
+                let function = frame.function.as_deref().unwrap_or("??");
+                let prefix = if i == 0 { "at" } else { "inlined into" };
+                match frame.location() {
+                    Some(location) => cu::print!("  {prefix} {function} ({location})"),
+                    None => cu::print!("  {prefix} {function}"),
+                }
+            }
+        }
+    }
+    Ok(())
+}
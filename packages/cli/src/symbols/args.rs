@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use std::path::PathBuf;
+
+use cu::pre::*;
+
+#[derive(Debug, clap::Parser)]
+pub struct ExtractArgs {
+    /// Modules of the game to extract the dynamic symbols from, like `main`, `sdk`
+    /// and `subsdk0` in the ExeFS
+    ///
+    /// NSO and (decompressed) ELF files are supported, as well as existing `.syms` files
+    /// from `objdump -T`. Symbols from all inputs are merged, and duplicates are removed
+    #[clap(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Path to the `.syms` file to write
+    #[clap(short, long)]
+    pub output: PathBuf,
+
+    /// Also include undefined symbols, which are imported from other modules
+    #[clap(long)]
+    pub undefined: bool,
+}
@@ -0,0 +1,348 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Generating symbol files (`.syms`) for the check from the modules of the game
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use cu::pre::*;
+use object::{Object as _, ObjectSection as _, ObjectSymbol as _};
+
+use crate::buildsys::Nso;
+
+mod args;
+pub use args::*;
+
+/// Section name of undefined symbols in `objdump -T`
+const UNDEFINED_SECTION: &str = "*UND*";
+/// Size of an entry in `.dynsym` (`Elf64_Sym`)
+const ELF64_SYM_SIZE: usize = 24;
+
+/// Binding of a symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Local,
+    Global,
+    Weak,
+}
+
+/// Type of a symbol, only functions and objects are distinguished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolType {
+    Function,
+    Object,
+    Other,
+}
+
+/// A dynamic symbol, as printed by `objdump -T`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicSymbol {
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    pub binding: Binding,
+    pub ty: SymbolType,
+    /// Name of the section, or `*UND*` if undefined
+    pub section: String,
+}
+
+impl DynamicSymbol {
+    pub fn is_undefined(&self) -> bool {
+        self.section == UNDEFINED_SECTION
+    }
+
+    /// Format the symbol as a line in the output of `objdump -T`
+    pub fn to_objdump_line(&self) -> String {
+        let binding = match self.binding {
+            Binding::Local => 'l',
+            Binding::Global if !self.is_undefined() => 'g',
+            _ => ' ',
+        };
+        let weak = if self.binding == Binding::Weak {
+            'w'
+        } else {
+            ' '
+        };
+        let ty = match self.ty {
+            SymbolType::Function => 'F',
+            SymbolType::Object => 'O',
+            SymbolType::Other => ' ',
+        };
+        format!(
+            "{:016x} {binding}{weak}   D{ty} {}\t{:016x} {}",
+            self.value, self.section, self.size, self.name
+        )
+    }
+
+    /// Parse a line in the output of `objdump -T`
+    pub fn parse_objdump_line(line: &str) -> Option<Self> {
+        // 16 digits of value, a space, 7 characters of flags, a space, then the section
+        let value = u64::from_str_radix(line.get(0..16)?, 16).ok()?;
+        let flags = line.get(17..24)?.as_bytes();
+        let (section, rest) = line.get(25..)?.split_once('\t')?;
+        let (size, rest) = rest.split_once(' ')?;
+        let size = u64::from_str_radix(size, 16).ok()?;
+        // there could be version info before the name
+        let name = rest.split_whitespace().last()?;
+        let binding = match (flags[0], flags[1]) {
+            (b'l', _) => Binding::Local,
+            (_, b'w') => Binding::Weak,
+            _ => Binding::Global,
+        };
+        let ty = match flags[6] {
+            b'F' => SymbolType::Function,
+            b'O' => SymbolType::Object,
+            _ => SymbolType::Other,
+        };
+        Some(Self {
+            name: name.to_string(),
+            value,
+            size,
+            binding,
+            ty,
+            section: section.to_string(),
+        })
+    }
+}
+
+/// Run the `symbols extract` command
+pub fn extract_symbols(args: ExtractArgs) -> cu::Result<()> {
+    // sorted by name so the output is stable
+    let mut symbols = BTreeMap::<String, DynamicSymbol>::new();
+    for input in &args.inputs {
+        let data = cu::fs::read(input)?;
+        let from_input = read_symbols(&data)
+            .context(format!("failed to read symbols from '{}'", input.display()))?;
+        cu::info!("{}: {} symbols", input.display(), from_input.len());
+        merge_symbols(&mut symbols, from_input, args.undefined);
+    }
+
+    let output = format_syms(&args.output, symbols.values());
+    cu::fs::write(&args.output, output)?;
+    cu::info!(
+        "wrote {} symbols to {}",
+        symbols.len(),
+        args.output.try_to_rel().display()
+    );
+    Ok(())
+}
+
+/// Add symbols to the merged symbols by name. The first definition of a symbol is kept
+fn merge_symbols(
+    symbols: &mut BTreeMap<String, DynamicSymbol>,
+    new_symbols: Vec<DynamicSymbol>,
+    include_undefined: bool,
+) {
+    for symbol in new_symbols {
+        if symbol.is_undefined() && !include_undefined {
+            continue;
+        }
+        match symbols.get(&symbol.name) {
+            Some(existing) if !existing.is_undefined() || symbol.is_undefined() => {}
+            _ => {
+                symbols.insert(symbol.name.clone(), symbol);
+            }
+        }
+    }
+}
+
+/// Read the dynamic symbols from an NSO, an ELF, or the output of `objdump -T`
+fn read_symbols(data: &[u8]) -> cu::Result<Vec<DynamicSymbol>> {
+    if data.starts_with(b"NSO0") {
+        return read_nso_symbols(&Nso::parse(data)?);
+    }
+    if data.starts_with(b"\x7fELF") {
+        return read_elf_symbols(data);
+    }
+    let content = std::str::from_utf8(data).unwrap_or_default();
+    let mut lines = content.lines();
+    if !lines.any(|line| line == "DYNAMIC SYMBOL TABLE:") {
+        cu::bail!("unknown file format; expected a NSO, an ELF, or the output of `objdump -T`");
+    }
+    Ok(lines
+        .filter_map(DynamicSymbol::parse_objdump_line)
+        .collect())
+}
+
+fn read_nso_symbols(nso: &Nso) -> cu::Result<Vec<DynamicSymbol>> {
+    let dynsym = nso.dynsym()?;
+    let dynstr = nso.dynstr()?;
+    let header = &nso.header;
+    let bss_start = header.data.memory_end();
+    let bss_end = bss_start + u64::from(header.bss_size);
+
+    let mut symbols = vec![];
+    // the first entry is always null
+    for entry in dynsym.chunks_exact(ELF64_SYM_SIZE).skip(1) {
+        let read_u64 =
+            |offset: usize| u64::from_le_bytes(entry[offset..offset + 8].try_into().unwrap());
+        let name_offset = u32::from_le_bytes(entry[0..4].try_into().unwrap()) as usize;
+        let info = entry[4];
+        let section_index = u16::from_le_bytes(entry[6..8].try_into().unwrap());
+        let value = read_u64(8);
+        let size = read_u64(16);
+
+        let Some(name) = dynstr
+            .get(name_offset..)
+            .and_then(|x| x.split(|c| *c == 0).next())
+        else {
+            cu::bail!("symbol name is out of bounds of .dynstr: 0x{name_offset:x}");
+        };
+        let name = String::from_utf8_lossy(name);
+        if name.is_empty() {
+            continue;
+        }
+        // the NSO does not have section headers, so use the segments instead
+        let section = if section_index == object::elf::SHN_UNDEF {
+            UNDEFINED_SECTION
+        } else if header.text.contains(value) {
+            ".text"
+        } else if header.rodata.contains(value) {
+            ".rodata"
+        } else if header.data.contains(value) {
+            ".data"
+        } else if (bss_start..bss_end).contains(&value) {
+            ".bss"
+        } else {
+            "*ABS*"
+        };
+        let binding = match info >> 4 {
+            object::elf::STB_LOCAL => Binding::Local,
+            object::elf::STB_WEAK => Binding::Weak,
+            _ => Binding::Global,
+        };
+        let ty = match info & 0xf {
+            object::elf::STT_FUNC => SymbolType::Function,
+            object::elf::STT_OBJECT | object::elf::STT_TLS => SymbolType::Object,
+            _ => SymbolType::Other,
+        };
+        symbols.push(DynamicSymbol {
+            name: name.into_owned(),
+            value,
+            size,
+            binding,
+            ty,
+            section: section.to_string(),
+        });
+    }
+    Ok(symbols)
+}
+
+fn read_elf_symbols(data: &[u8]) -> cu::Result<Vec<DynamicSymbol>> {
+    let file = object::File::parse(data).map_err(|e| cu::fmterr!("invalid ELF: {e}"))?;
+    let mut symbols = vec![];
+    for symbol in file.dynamic_symbols() {
+        let Ok(name) = symbol.name() else {
+            continue;
+        };
+        if name.is_empty() {
+            continue;
+        }
+        let section = if symbol.is_undefined() {
+            UNDEFINED_SECTION.to_string()
+        } else {
+            symbol
+                .section_index()
+                .and_then(|x| file.section_by_index(x).ok())
+                .and_then(|x| x.name().ok().map(|x| x.to_string()))
+                .unwrap_or_else(|| "*ABS*".to_string())
+        };
+        let binding = if symbol.is_weak() {
+            Binding::Weak
+        } else if symbol.is_local() {
+            Binding::Local
+        } else {
+            Binding::Global
+        };
+        let ty = match symbol.kind() {
+            object::SymbolKind::Text => SymbolType::Function,
+            object::SymbolKind::Data | object::SymbolKind::Tls => SymbolType::Object,
+            _ => SymbolType::Other,
+        };
+        symbols.push(DynamicSymbol {
+            name: name.to_string(),
+            value: symbol.address(),
+            size: symbol.size(),
+            binding,
+            ty,
+            section,
+        });
+    }
+    Ok(symbols)
+}
+
+/// Format the symbols like the output of `objdump -T`
+fn format_syms<'a>(path: &Path, symbols: impl IntoIterator<Item = &'a DynamicSymbol>) -> String {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut out = format!("\n{file_name}:     file format elf64-little\n\nDYNAMIC SYMBOL TABLE:\n");
+    for symbol in symbols {
+        let _ = writeln!(out, "{}", symbol.to_objdump_line());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objdump_line_roundtrip() {
+        let lines = [
+            "0000000000000000      D  *UND*\t0000000000000000 malloc",
+            "0000000000000000  w   D  *UND*\t0000000000000000 __rel_dyn_end",
+            "0000000000183e5c  w   DF .text\t00000000000000b0 _ZN2nn2sf6detail7ReleaseEv",
+            "00000000009791e0 g    DO .data\t0000000000000040 _ZTVNSt3__18__c_nodeE",
+        ];
+        for line in lines {
+            let symbol = DynamicSymbol::parse_objdump_line(line).unwrap();
+            assert_eq!(symbol.to_objdump_line(), line);
+        }
+        let symbol = DynamicSymbol::parse_objdump_line(lines[2]).unwrap();
+        assert_eq!(symbol.binding, Binding::Weak);
+        assert_eq!(symbol.ty, SymbolType::Function);
+        assert_eq!(symbol.value, 0x183e5c);
+        assert_eq!(symbol.size, 0xb0);
+        assert!(DynamicSymbol::parse_objdump_line("DYNAMIC SYMBOL TABLE:").is_none());
+    }
+
+    #[test]
+    fn merge_prefers_definitions() {
+        let dump = |lines: &[&str]| {
+            let mut out = "DYNAMIC SYMBOL TABLE:\n".to_string();
+            for line in lines {
+                out.push_str(line);
+                out.push('\n');
+            }
+            read_symbols(out.as_bytes()).unwrap()
+        };
+        let sdk = dump(&[
+            "0000000000000000      D  *UND*\t0000000000000000 malloc",
+            "0000000000001000 g    DF .text\t0000000000000010 nnosSleepThread",
+        ]);
+        let main = dump(&["0000000000002000 g    DF .text\t0000000000000020 malloc"]);
+        assert_eq!(sdk.len(), 2);
+        assert!(sdk[0].is_undefined());
+
+        let mut symbols = BTreeMap::new();
+        merge_symbols(&mut symbols, sdk.clone(), true);
+        merge_symbols(&mut symbols, main, true);
+        merge_symbols(&mut symbols, sdk.clone(), true);
+        let lines = symbols
+            .values()
+            .map(|x| x.to_objdump_line())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "0000000000002000 g    DF .text\t0000000000000020 malloc",
+                "0000000000001000 g    DF .text\t0000000000000010 nnosSleepThread",
+            ]
+        );
+
+        let mut symbols = BTreeMap::new();
+        merge_symbols(&mut symbols, sdk, false);
+        assert_eq!(symbols.keys().collect::<Vec<_>>(), vec!["nnosSleepThread"]);
+    }
+}
@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+use cu::pre::*;
+
+#[derive(Debug, clap::Parser)]
+pub struct WatchArgs {
+    /// Select profile to build
+    ///
+    /// See https://megaton-new.pistonite.dev/tutorial/profiles
+    #[clap(short, long, default_value = "none")]
+    pub profile: String,
+
+    /// Package the module after each successful build
+    #[clap(long)]
+    pub package: bool,
+
+    /// Upload the module to the console after each successful build
+    #[clap(long)]
+    pub deploy: bool,
+
+    /// Host of the console's FTP server, overrides the user config
+    #[clap(long, requires = "deploy")]
+    pub host: Option<String>,
+
+    /// Port of the console's FTP server, overrides the user config
+    #[clap(long, requires = "deploy")]
+    pub port: Option<u16>,
+
+    /// Time in milliseconds to wait for more changes before rebuilding
+    #[clap(long, default_value_t = 300)]
+    pub debounce: u64,
+
+    /// Specify the location of the Megaton.toml config file
+    #[clap(short = 'c', long)]
+    pub config: Option<String>,
+}
@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Megaton contributors
+
+//! Rebuilding the module when files change
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use cu::pre::*;
+use notify::{Event, EventKind, RecursiveMode, Watcher as _};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::buildsys::{self, BuildArgs, MessageFormat};
+use crate::config::{self, CargoConfig, Config, Deploy};
+use crate::deploy;
+
+mod args;
+pub use args::*;
+
+/// Build the module, then rebuild whenever the sources or the config change
+///
+/// The environment should be initialized before calling this,
+/// and it is reused for every build
+pub async fn watch(args: WatchArgs) -> cu::Result<()> {
+    let (root_path, manifest_path) = config::get_root_and_manifest(args.config.as_deref())?;
+    let connection = if args.deploy {
+        Some(deploy::resolve_connection(args.host.as_deref(), args.port)?)
+    } else {
+        None
+    };
+    let debounce = Duration::from_millis(args.debounce);
+
+    let (send, mut recv) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        // the receiver is only dropped when exiting
+        let _ = send.send(event);
+    })?;
+    let mut watched: Vec<PathBuf> = vec![];
+    let mut ignored: Vec<PathBuf> = vec![];
+
+    loop {
+        // the config is loaded again every time, since it could be what changed
+        let loaded = config::load(&manifest_path).and_then(|config| {
+            let profile = config.profile.resolve(&args.profile)?.to_string();
+            Ok((config, profile))
+        });
+        match loaded {
+            Ok((config, profile)) => {
+                let paths = watch_paths(&root_path, &manifest_path, &config, &profile);
+                for path in watched.drain(..) {
+                    let _ = watcher.unwatch(&path);
+                }
+                for (path, mode) in paths {
+                    if let Err(e) = watcher.watch(&path, mode) {
+                        cu::warn!("cannot watch '{}': {e}", path.display());
+                        continue;
+                    }
+                    cu::debug!("watching {}", path.display());
+                    watched.push(path);
+                }
+                // build outputs could be inside the watched directories
+                ignored = vec![
+                    config.module.target_path(&root_path),
+                    config.module.compdb_path(&root_path),
+                ];
+                let result = build_once(
+                    &profile,
+                    &manifest_path,
+                    &config,
+                    args.package,
+                    connection.as_ref(),
+                )
+                .await;
+                if let Err(e) = result {
+                    cu::error!("{e:#}");
+                }
+            }
+            Err(e) => {
+                cu::error!("{e:#}");
+                // still wait for the config to change
+                if watched.is_empty() {
+                    watcher.watch(&manifest_path, RecursiveMode::NonRecursive)?;
+                    watched.push(manifest_path.clone());
+                }
+            }
+        }
+        cu::info!("watching for changes...");
+        wait_for_changes(&mut recv, &ignored, debounce).await?;
+        cu::info!("change detected, rebuilding");
+    }
+}
+
+/// Run one build, and package or deploy after it if requested
+async fn build_once(
+    profile: &str,
+    manifest_path: &Path,
+    config: &Config,
+    package: bool,
+    connection: Option<&Deploy>,
+) -> cu::Result<()> {
+    let build_args = BuildArgs {
+        profile: profile.to_string(),
+        configure: false,
+        message_format: MessageFormat::Human,
+        timings: false,
+        config: Some(manifest_path.as_utf8()?.to_string()),
+    };
+    let Some(output) = buildsys::run(build_args).await? else {
+        cu::bail!("build did not produce any output");
+    };
+    if package {
+        buildsys::package_output(config, &output, vec![], false, None)?;
+    }
+    if let Some(connection) = connection {
+        deploy::upload(connection, config, &output)?;
+    }
+    cu::info!("build finished");
+    Ok(())
+}
+
+/// Get the paths to watch for a resolved profile
+fn watch_paths(
+    root: &Path,
+    manifest_path: &Path,
+    config: &Config,
+    profile: &str,
+) -> Vec<(PathBuf, RecursiveMode)> {
+    let mut paths = vec![(manifest_path.to_path_buf(), RecursiveMode::NonRecursive)];
+    let build = config.build.get_profile(profile);
+    for dir in build.sources.iter().chain(&build.includes) {
+        paths.push((root.join(dir), RecursiveMode::Recursive));
+    }
+    for asset in buildsys::asset_watch_paths(&build.embed.files) {
+        let mode = if root.join(&asset).is_dir() {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        paths.push((root.join(asset), mode));
+    }
+    for header in &build.pch {
+        paths.push((root.join(header), RecursiveMode::NonRecursive));
+    }
+    if config.megaton.lib_enabled() && config.cargo.enabled != Some(false) {
+        let cargo_manifest = root.join(
+            config
+                .cargo
+                .manifest
+                .clone()
+                .unwrap_or_else(CargoConfig::default_manifest_path),
+        );
+        if let Some(crate_root) = cargo_manifest.parent() {
+            for dir in &config.cargo.sources {
+                paths.push((crate_root.join(dir), RecursiveMode::Recursive));
+            }
+        }
+        paths.push((cargo_manifest, RecursiveMode::NonRecursive));
+    }
+    // a directory can be both a source and an include directory
+    let mut seen = std::collections::BTreeSet::new();
+    paths.retain(|(path, _)| path.exists() && seen.insert(path.clone()));
+    paths
+}
+
+/// Wait for a relevant change, then wait until there are no more changes
+/// for the debounce duration
+async fn wait_for_changes(
+    recv: &mut UnboundedReceiver<notify::Result<Event>>,
+    ignored: &[PathBuf],
+    debounce: Duration,
+) -> cu::Result<()> {
+    loop {
+        let Some(event) = recv.recv().await else {
+            cu::bail!("file watcher stopped unexpectedly");
+        };
+        let event = event?;
+        if is_relevant(&event, ignored) {
+            cu::debug!("changed: {:?}", event.paths);
+            break;
+        }
+    }
+    loop {
+        match tokio::time::timeout(debounce, recv.recv()).await {
+            Err(_) => return Ok(()),
+            Ok(None) => cu::bail!("file watcher stopped unexpectedly"),
+            Ok(Some(_)) => {}
+        }
+    }
+}
+
+fn is_relevant(event: &Event, ignored: &[PathBuf]) -> bool {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return false;
+    }
+    event
+        .paths
+        .iter()
+        .any(|path| !ignored.iter().any(|x| path.starts_with(x)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignore_build_outputs() {
+        let ignored = vec![
+            PathBuf::from("/mod/target"),
+            PathBuf::from("/mod/compile_commands.json"),
+        ];
+        let event = |path: &str| {
+            Event::new(EventKind::Modify(notify::event::ModifyKind::Any))
+                .add_path(PathBuf::from(path))
+        };
+        assert!(is_relevant(&event("/mod/src/main.cpp"), &ignored));
+        assert!(!is_relevant(
+            &event("/mod/target/megaton/none/a.o"),
+            &ignored
+        ));
+        assert!(!is_relevant(&event("/mod/compile_commands.json"), &ignored));
+        let access = Event::new(EventKind::Access(notify::event::AccessKind::Any))
+            .add_path(PathBuf::from("/mod/src/main.cpp"));
+        assert!(!is_relevant(&access, &ignored));
+    }
+
+    #[test]
+    fn watch_default_profile() {
+        let root = std::env::temp_dir().join(format!("megaton-watch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("debug")).unwrap();
+        let manifest_path = root.join("Megaton.toml");
+        std::fs::write(
+            &manifest_path,
+            r#"
+[module]
+name = "test"
+title-id = 0x0100000000001000
+
+[profile]
+default = "debug"
+
+[build]
+sources = ["src"]
+
+[build.profiles.debug]
+sources = ["debug"]
+"#,
+        )
+        .unwrap();
+        let config = config::load(&manifest_path).unwrap();
+        let profile = config.profile.resolve("none").unwrap();
+        assert_eq!(profile, "debug");
+        let paths = watch_paths(&root, &manifest_path, &config, profile)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, [manifest_path, root.join("src"), root.join("debug")]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -120,6 +120,7 @@ fn test_consecutive_writes(mtt: &mut MegatonTests) {
 
     let open_result = OpenOptions::new()
         .create(true)
+        .truncate(false)
         .read(true)
         .write(true)
         .open(&path);
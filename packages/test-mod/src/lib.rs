@@ -87,21 +87,22 @@ impl MegatonTests {
     {
         self.total_tests += 1;
         self.category_tests += 1;
-        if result.is_err() {
-            self.megaton_log(
-                format!(
-                    "Test number {:#?} failed: received Err: {:?}. Message: {:?}\n",
-                    self.total_tests,
-                    result.unwrap_err(),
-                    msg
-                )
-                .as_str(),
-            );
-            return None;
-        } else {
-            self.passed_tests += 1;
-            self.category_passed_tests += 1;
-            return Some(result.unwrap());
+        match result {
+            Err(e) => {
+                self.megaton_log(
+                    format!(
+                        "Test number {:#?} failed: received Err: {:?}. Message: {:?}\n",
+                        self.total_tests, e, msg
+                    )
+                    .as_str(),
+                );
+                None
+            }
+            Ok(value) => {
+                self.passed_tests += 1;
+                self.category_passed_tests += 1;
+                Some(value)
+            }
         }
     }
     fn start_category(&mut self, category: &'static str) {
@@ -124,6 +125,8 @@ impl MegatonTests {
     }
 }
 
+// the math here is intentionally trivial
+#[allow(clippy::identity_op, clippy::eq_op, clippy::unnecessary_cast)]
 fn megaton_num_tests(mtt: &mut MegatonTests) {
     mtt.start_category("Math");
     // basic math
@@ -71,12 +71,10 @@ pub fn create_isolated_cargo_manifest_with_deps_removed<
             workspace_deps.get(&dep_name),
             "did not find dependency '{dep_name}' in workspace"
         )?;
-        if let Some(data) = workspace_dep_data.as_table() {
-            if data.get("path").is_some() {
-                cu::bail!(
-                    "workspace dep cannot be a path when creating isolated crate: {dep_name}"
-                );
-            }
+        if let Some(data) = workspace_dep_data.as_table()
+            && data.get("path").is_some()
+        {
+            cu::bail!("workspace dep cannot be a path when creating isolated crate: {dep_name}");
         }
         workspace_deps_to_add.insert(dep_name, workspace_dep_data.clone());
     }